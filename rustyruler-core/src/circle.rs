@@ -0,0 +1,384 @@
+//! Fitting circles to the edges around a point, for rounded corners and avatars
+//!
+//! Rays are cast in every direction from the point and the boundaries they hit
+//! are fitted with a circle. Around a rounded corner only some rays land on the
+//! arc, so straight edges are dropped first, then the circle following the
+//! longest run of boundaries is kept and the others are treated as outliers
+//! before the final least squares fit.
+
+use crate::detect::validate_coordinates;
+use crate::geometry::Point;
+use crate::measure::{Measurement, Shape};
+use crate::ray;
+use image::RgbImage;
+
+/// Number of rays cast around the point
+const RAY_COUNT: usize = 72;
+
+/// Distance in pixels from a circle under which a boundary agrees with it
+const INLIER_TOLERANCE: f64 = 1.0;
+
+/// Fewest boundaries a circle needs to agree with to be reported
+const MIN_INLIERS: usize = 8;
+
+/// Distance in pixels from a line under which a boundary lies on it
+const LINE_TOLERANCE: f64 = 0.25;
+
+/// Shortest run of boundaries on a line, in pixels, taken as a straight edge
+/// Arcs with a radius up to about 100px curve away from a line over less
+const MIN_STRAIGHT_LENGTH: f64 = 12.0;
+
+/// Rounds of refitting the circle to the boundaries agreeing with it
+const REFINE_STEPS: usize = 3;
+
+/// A circle in image coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    pub center: Point,
+    pub radius: f64,
+}
+
+impl Circle {
+    /// The circle passing through three points, `None` if they are aligned
+    pub fn through(a: &Point, b: &Point, c: &Point) -> Option<Circle> {
+        let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+        if d.abs() < f64::EPSILON {
+            return None;
+        }
+
+        let (a2, b2, c2) = (
+            a.x * a.x + a.y * a.y,
+            b.x * b.x + b.y * b.y,
+            c.x * c.x + c.y * c.y,
+        );
+        let center = Point::new(
+            (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+            (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+        );
+
+        Some(Circle {
+            center,
+            radius: center.distance(a),
+        })
+    }
+
+    /// Least squares fit of a circle to the given points, using the algebraic
+    /// (Kåsa) method
+    /// Returns `None` with fewer than three points or when they are aligned
+    pub fn fit(points: &[Point]) -> Option<Circle> {
+        if points.len() < 3 {
+            return None;
+        }
+
+        // Work relative to the centroid to keep the sums well conditioned
+        let count = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.x).sum::<f64>() / count;
+        let mean_y = points.iter().map(|p| p.y).sum::<f64>() / count;
+
+        let (mut suu, mut suv, mut svv) = (0.0, 0.0, 0.0);
+        let (mut suuu, mut svvv, mut suvv, mut svuu) = (0.0, 0.0, 0.0, 0.0);
+        for point in points {
+            let (u, v) = (point.x - mean_x, point.y - mean_y);
+            suu += u * u;
+            suv += u * v;
+            svv += v * v;
+            suuu += u * u * u;
+            svvv += v * v * v;
+            suvv += u * v * v;
+            svuu += v * u * u;
+        }
+
+        let det = suu * svv - suv * suv;
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+
+        let rhs_u = (suuu + suvv) / 2.0;
+        let rhs_v = (svvv + svuu) / 2.0;
+        let uc = (rhs_u * svv - rhs_v * suv) / det;
+        let vc = (rhs_v * suu - rhs_u * suv) / det;
+
+        Some(Circle {
+            center: Point::new(mean_x + uc, mean_y + vc),
+            radius: (uc * uc + vc * vc + (suu + svv) / count).sqrt(),
+        })
+    }
+
+    /// Distance from a point to the circumference
+    pub fn residual(&self, point: &Point) -> f64 {
+        (self.center.distance(point) - self.radius).abs()
+    }
+
+    /// Root mean square distance from the points to the circumference
+    pub fn error(&self, points: &[Point]) -> f64 {
+        if points.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = points.iter().map(|p| self.residual(p).powi(2)).sum();
+        (sum / points.len() as f64).sqrt()
+    }
+}
+
+/// Boundaries hit by rays cast in every direction from the point, in order
+/// Rays reaching the border of the image without finding an edge give `None`
+fn ray_hits(img: &RgbImage, x: u32, y: u32, threshold: f32) -> Vec<Option<Point>> {
+    let (width, height) = img.dimensions();
+    let origin = Point::pixel(x, y);
+
+    (0..RAY_COUNT)
+        .map(|index| index as f64 * std::f64::consts::TAU / RAY_COUNT as f64)
+        .map(|direction| ray::calculate_ray_limit(img, origin, direction, threshold))
+        .map(|point| {
+            (point.x > 0.0
+                && point.y > 0.0
+                && point.x < f64::from(width - 1)
+                && point.y < f64::from(height - 1))
+            .then_some(point)
+        })
+        .collect()
+}
+
+/// Distance from `point` to the line through `a` and `b`
+fn distance_to_line(point: &Point, a: &Point, b: &Point) -> f64 {
+    let length = a.distance(b);
+    if length == 0.0 {
+        return a.distance(point);
+    }
+    ((b.x - a.x) * (a.y - point.y) - (a.x - point.x) * (b.y - a.y)).abs() / length
+}
+
+/// Boundaries around the point that may lie on a curve, in ray order
+///
+/// Straight edges next to a rounded corner hug the circle near where they
+/// touch it and would pull the fit towards a bigger one, so runs of
+/// consecutive rays landing on a straight line are left out. Arcs only look
+/// straight over a short distance, less than `MIN_STRAIGHT_LENGTH`. Rays that
+/// were left out give `None`.
+pub fn edge_points(img: &RgbImage, x: u32, y: u32, threshold: f32) -> Vec<Option<Point>> {
+    let hits = ray_hits(img, x, y, threshold);
+    let count = hits.len();
+    let mut straight = vec![false; count];
+
+    for start in 0..count {
+        let Some(first) = hits[start] else {
+            continue;
+        };
+
+        // Longest run of hits from `start` that stays on a line, wrapping around
+        let mut run = 1;
+        let mut length = 0.0;
+        while run < count {
+            let Some(last) = hits[(start + run) % count] else {
+                break;
+            };
+            let on_line = (1..run).all(|offset| {
+                hits[(start + offset) % count]
+                    .is_some_and(|point| distance_to_line(&point, &first, &last) <= LINE_TOLERANCE)
+            });
+            if !on_line {
+                break;
+            }
+            length = first.distance(&last);
+            run += 1;
+        }
+
+        if length >= MIN_STRAIGHT_LENGTH {
+            for offset in 0..run {
+                straight[(start + offset) % count] = true;
+            }
+        }
+    }
+
+    hits.into_iter()
+        .zip(straight)
+        .map(|(hit, straight)| hit.filter(|_| !straight))
+        .collect()
+}
+
+/// Longest run of consecutive rays whose boundaries agree with the circle
+fn longest_run(hits: &[Option<Point>], circle: &Circle) -> Vec<Point> {
+    let count = hits.len();
+    let agrees: Vec<bool> = hits
+        .iter()
+        .map(|hit| hit.is_some_and(|point| circle.residual(&point) <= INLIER_TOLERANCE))
+        .collect();
+
+    // Going around twice finds runs that wrap past the first ray
+    let (mut longest_start, mut longest_length) = (0, 0);
+    let mut length = 0;
+    for index in 0..2 * count {
+        if agrees[index % count] && length < count {
+            length += 1;
+            if length > longest_length {
+                (longest_start, longest_length) = (index + 1 - length, length);
+            }
+        } else {
+            length = 0;
+        }
+    }
+
+    (longest_start..longest_start + longest_length)
+        .filter_map(|index| hits[index % count])
+        .collect()
+}
+
+/// Finds the circle following the longest arc of edge points and refits it to them
+///
+/// Candidates are circles through three points spread evenly over a run of
+/// neighboring rays, so arcs covering only part of the turn are still found.
+/// Counting only consecutive rays keeps far away curves that happen to lie on
+/// a common circle, like the other corners of a card, from winning.
+pub fn fit_edges(hits: &[Option<Point>]) -> Option<(Circle, Vec<Point>)> {
+    let points: Vec<Point> = hits.iter().flatten().copied().collect();
+    let count = points.len();
+    let mut best: Option<(Circle, usize, f64)> = None;
+
+    for span in 1..=count / 3 {
+        for start in 0..count {
+            let (a, b, c) = (
+                &points[start],
+                &points[(start + span) % count],
+                &points[(start + 2 * span) % count],
+            );
+            let Some(candidate) = Circle::through(a, b, c) else {
+                continue;
+            };
+
+            // Longer arcs win, then the ones closest to the circle
+            let run = longest_run(hits, &candidate);
+            let error = candidate.error(&run);
+            if best.is_none_or(|(_, length, lowest)| {
+                run.len() > length || (run.len() == length && error < lowest)
+            }) {
+                best = Some((candidate, run.len(), error));
+            }
+        }
+    }
+
+    let (mut circle, _, _) = best?;
+    let mut inliers = Vec::new();
+    for _ in 0..REFINE_STEPS {
+        inliers = longest_run(hits, &circle);
+        if inliers.len() < MIN_INLIERS {
+            return None;
+        }
+        circle = Circle::fit(&inliers)?;
+    }
+
+    Some((circle, inliers))
+}
+
+/// Fits a circle to the edges around the given point
+/// Returns `None` if the point is outside the image or no circle fits
+pub fn measure_circle(img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+    validate_coordinates(img, x, y)?;
+
+    let points = edge_points(img, x, y, threshold);
+    let (circle, inliers) = fit_edges(&points)?;
+
+    Some(Measurement::at(
+        x,
+        y,
+        threshold,
+        Shape::Circle {
+            center: circle.center,
+            radius: circle.radius,
+            error: circle.error(&inliers),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// Black shape on white, antialiased like toolkits draw rounded corners:
+    /// each pixel is shaded by the share of it covered by the shape
+    fn render(width: u32, height: u32, inside: impl Fn(f64, f64) -> bool) -> RgbImage {
+        const SAMPLES: u32 = 8;
+        let offset = |index: u32| (f64::from(index) + 0.5) / f64::from(SAMPLES) - 0.5;
+        RgbImage::from_fn(width, height, |x, y| {
+            let covered = (0..SAMPLES * SAMPLES)
+                .filter(|sample| {
+                    inside(
+                        f64::from(x) + offset(sample % SAMPLES),
+                        f64::from(y) + offset(sample / SAMPLES),
+                    )
+                })
+                .count();
+            let share = covered as f64 / f64::from(SAMPLES * SAMPLES);
+            Rgb([(255.0 * (1.0 - share)).round() as u8; 3])
+        })
+    }
+
+    fn circle(measurement: Measurement) -> (Point, f64, f64) {
+        match measurement.shape {
+            Shape::Circle {
+                center,
+                radius,
+                error,
+            } => (center, radius, error),
+            shape => panic!("expected a circle, got {shape:?}"),
+        }
+    }
+
+    #[test]
+    fn fit_recovers_a_circle_through_exact_points() {
+        let points: Vec<Point> = (0..12)
+            .map(|step| f64::from(step) * std::f64::consts::TAU / 12.0)
+            .map(|angle| Point::new(12.5 + 7.0 * angle.cos(), -3.0 + 7.0 * angle.sin()))
+            .collect();
+
+        let fitted = Circle::fit(&points).unwrap();
+        assert!(fitted.center.distance(&Point::new(12.5, -3.0)) < 1e-9);
+        assert!((fitted.radius - 7.0).abs() < 1e-9);
+        assert!(fitted.error(&points) < 1e-9);
+
+        let aligned = [
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(2.0, 2.0),
+        ];
+        assert_eq!(Circle::fit(&aligned), None);
+        assert_eq!(Circle::fit(&points[..2]), None);
+    }
+
+    #[test]
+    fn a_disc_is_fitted_from_anywhere_inside_it() {
+        let disc = render(120, 120, |x, y| (x - 60.0).hypot(y - 60.0) <= 30.0);
+
+        for (x, y) in [(60, 60), (50, 70), (75, 55)] {
+            let (center, radius, error) =
+                circle(measure_circle(&disc, x, y, crate::DEFAULT_THRESHOLD).unwrap());
+            assert!(center.distance(&Point::new(60.0, 60.0)) < 0.1, "{center:?}");
+            assert!((radius - 30.0).abs() < 0.1, "{radius}");
+            assert!(error < 0.1, "{error}");
+        }
+    }
+
+    #[test]
+    fn a_rounded_corner_is_fitted_without_its_straight_edges() {
+        // A card from 20 to 180 with corners of a 15px radius
+        let card = render(200, 200, |x, y| {
+            let (corner_x, corner_y) = (x.clamp(35.0, 165.0), y.clamp(35.0, 165.0));
+            (20.0..=180.0).contains(&x)
+                && (20.0..=180.0).contains(&y)
+                && (x - corner_x).hypot(y - corner_y) <= 15.0
+        });
+
+        for (x, y, corner) in [(30, 30, (35.0, 35.0)), (170, 168, (165.0, 165.0))] {
+            let (center, radius, error) =
+                circle(measure_circle(&card, x, y, crate::DEFAULT_THRESHOLD).unwrap());
+            assert!(
+                center.distance(&Point::new(corner.0, corner.1)) < 0.3,
+                "{center:?}"
+            );
+            assert!((radius - 15.0).abs() < 0.3, "{radius}");
+            assert!(error < 0.1, "{error}");
+        }
+
+        // The middle of the card is bounded by straight edges only
+        assert!(measure_circle(&card, 100, 100, crate::DEFAULT_THRESHOLD).is_none());
+    }
+}
@@ -0,0 +1,19 @@
+//! Color math used to decide where one element ends and the next begins
+
+use image::Rgb;
+
+/// Euclidean distance between two colors in RGB space
+/// Ranges from 0.0 for identical colors up to ~441.7 for black against white
+pub fn distance(a: &Rgb<u8>, b: &Rgb<u8>) -> f32 {
+    let diff_r = u32::from(a[0].abs_diff(b[0]));
+    let diff_g = u32::from(a[1].abs_diff(b[1]));
+    let diff_b = u32::from(a[2].abs_diff(b[2]));
+
+    ((diff_r * diff_r + diff_g * diff_g + diff_b * diff_b) as f32).sqrt()
+}
+
+/// Returns true when the step from `previous` to `current` is a boundary
+/// for the given threshold
+pub fn is_edge(previous: &Rgb<u8>, current: &Rgb<u8>, threshold: f32) -> bool {
+    distance(previous, current) > threshold
+}
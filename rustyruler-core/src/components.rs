@@ -0,0 +1,309 @@
+//! Finding the rectangular components of a whole image, such as buttons, cards and inputs
+//!
+//! The image is split into areas of touching pixels with no edge between them,
+//! using the rule [`calculate_limit`](crate::detect::calculate_limit) walks
+//! with: neighbors belong together unless their colors differ by more than the
+//! threshold. An area is a component when it reaches nearly all around its
+//! bounding box, which holds for filled rectangles with anything drawn inside
+//! them and for outlines alike, while glyphs and icons fall short of it.
+//!
+//! Components nest: each one has as parent the smallest component containing
+//! it, so from the one under a point the containers around it can be walked
+//! outward up to the whole screen.
+
+use crate::color;
+use crate::measure::{Limits, Measurement, Shape};
+use crate::rects::Rect;
+use image::RgbImage;
+
+/// Smallest width and height of a component in pixels, below which it is taken
+/// for a glyph or a separator
+pub const MIN_SIZE: u32 = 8;
+
+/// Share of the border of its bounding box an area must cover to be a component,
+/// leaving room for rounded and antialiased corners
+pub const MIN_BORDER: f32 = 0.9;
+
+/// The components of an image, from the smallest to the largest
+#[derive(Debug, Clone, Default)]
+pub struct Components {
+    rects: Vec<Rect>,
+    /// Position of the smallest component containing each one, if any
+    parents: Vec<Option<usize>>,
+}
+
+impl Components {
+    /// Bounds of every component, from the smallest to the largest
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    /// Smallest component containing the given one
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.parents.get(index).copied().flatten()
+    }
+
+    /// Smallest component containing the point
+    pub fn innermost_at(&self, x: u32, y: u32) -> Option<usize> {
+        // Sorted by area, so the first one found is the smallest
+        self.rects.iter().position(|rect| rect.contains(x, y))
+    }
+
+    /// Components containing the point, from the innermost outward
+    pub fn chain_at(&self, x: u32, y: u32) -> Vec<usize> {
+        let mut chain = Vec::new();
+        let mut current = self.innermost_at(x, y);
+        while let Some(index) = current {
+            chain.push(index);
+            current = self.parent(index);
+        }
+        chain
+    }
+
+    /// Measures the component at the given position for a point inside it
+    ///
+    /// The limits sit on the first pixel past each edge, like those found by
+    /// color changes, and the container is the parent component or the whole
+    /// image. Returns `None` for an unknown component.
+    pub fn measure(
+        &self,
+        index: usize,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Option<Measurement> {
+        let rect = self.rects.get(index)?;
+        let container = match self.parent(index).and_then(|parent| self.rects.get(parent)) {
+            Some(parent) => limits(parent, width, height),
+            None => Limits {
+                top: 0,
+                bottom: height - 1,
+                left: 0,
+                right: width - 1,
+            },
+        };
+
+        let mut measurement = Measurement::at(x, y, 0.0, Shape::Extent);
+        measurement.limits = limits(rect, width, height);
+        measurement.container = container;
+        Some(measurement)
+    }
+}
+
+/// Finds the components of the whole image
+pub fn find_components(img: &RgbImage, threshold: f32) -> Components {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Components::default();
+    }
+
+    // Areas are numbered from 1 by flood fill, 0 marks pixels not reached yet
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+    let mut labels = vec![0_u32; (width * height) as usize];
+    let mut areas = Vec::new();
+    let mut stack = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if labels[index(x, y)] != 0 {
+                continue;
+            }
+            let label = areas.len() as u32 + 1;
+            labels[index(x, y)] = label;
+            stack.push((x, y));
+
+            let (mut left, mut top, mut right, mut bottom) = (x, y, x, y);
+            while let Some((x, y)) = stack.pop() {
+                (left, top) = (left.min(x), top.min(y));
+                (right, bottom) = (right.max(x), bottom.max(y));
+
+                let pixel = img.get_pixel(x, y);
+                let neighbors = [
+                    (x > 0).then(|| (x - 1, y)),
+                    (x + 1 < width).then_some((x + 1, y)),
+                    (y > 0).then(|| (x, y - 1)),
+                    (y + 1 < height).then_some((x, y + 1)),
+                ];
+                for (neighbor_x, neighbor_y) in neighbors.into_iter().flatten() {
+                    if labels[index(neighbor_x, neighbor_y)] == 0
+                        && !color::is_edge(pixel, img.get_pixel(neighbor_x, neighbor_y), threshold)
+                    {
+                        labels[index(neighbor_x, neighbor_y)] = label;
+                        stack.push((neighbor_x, neighbor_y));
+                    }
+                }
+            }
+
+            areas.push(Rect {
+                x: left,
+                y: top,
+                width: right - left + 1,
+                height: bottom - top + 1,
+            });
+        }
+    }
+
+    let mut rects: Vec<Rect> = areas
+        .iter()
+        .enumerate()
+        .filter(|(area, rect)| {
+            rect.width >= MIN_SIZE
+                && rect.height >= MIN_SIZE
+                && border_coverage(&labels, width, rect, *area as u32 + 1) >= MIN_BORDER
+        })
+        .map(|(_, rect)| *rect)
+        .collect();
+
+    // Smallest first, so the parent of each one comes after it
+    rects.sort_by_key(|rect| {
+        (
+            u64::from(rect.width) * u64::from(rect.height),
+            rect.y,
+            rect.x,
+            rect.width,
+        )
+    });
+    // An outline and the area it encloses can share their bounds
+    rects.dedup();
+
+    let parents = rects
+        .iter()
+        .enumerate()
+        .map(|(index, rect)| {
+            rects
+                .iter()
+                .enumerate()
+                .skip(index + 1)
+                .find(|(_, other)| encloses(other, rect))
+                .map(|(parent, _)| parent)
+        })
+        .collect();
+
+    Components { rects, parents }
+}
+
+/// Share of the pixels along the border of `rect` labelled with `label`
+fn border_coverage(labels: &[u32], width: u32, rect: &Rect, label: u32) -> f32 {
+    let (right, bottom) = (rect.right() - 1, rect.bottom() - 1);
+    let horizontal = (rect.x..=right).flat_map(|x| [(x, rect.y), (x, bottom)]);
+    let vertical = (rect.y + 1..bottom).flat_map(|y| [(rect.x, y), (right, y)]);
+
+    let (mut covered, mut total) = (0, 0);
+    for (x, y) in horizontal.chain(vertical) {
+        total += 1;
+        if labels[(y * width + x) as usize] == label {
+            covered += 1;
+        }
+    }
+    covered as f32 / total as f32
+}
+
+/// Whether `outer` contains all of `inner`
+fn encloses(outer: &Rect, inner: &Rect) -> bool {
+    outer.x <= inner.x
+        && outer.y <= inner.y
+        && outer.right() >= inner.right()
+        && outer.bottom() >= inner.bottom()
+}
+
+/// Limits of a rectangle, on the first pixel past each edge within the image
+fn limits(rect: &Rect, width: u32, height: u32) -> Limits {
+    Limits {
+        top: rect.y.saturating_sub(1),
+        bottom: rect.bottom().min(height - 1),
+        left: rect.x.saturating_sub(1),
+        right: rect.right().min(width - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn fill(img: &mut RgbImage, rect: Rect, color: Rgb<u8>) {
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                img.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// A button with a glyph in a card, and an outlined box next to it
+    fn screen() -> RgbImage {
+        let mut img = RgbImage::from_pixel(100, 80, Rgb([255, 255, 255]));
+        fill(&mut img, rect(10, 10, 60, 50), Rgb([200, 200, 200]));
+        fill(&mut img, rect(20, 20, 30, 15), Rgb([40, 90, 200]));
+        fill(&mut img, rect(30, 25, 4, 4), Rgb([0, 0, 0]));
+        fill(&mut img, rect(75, 10, 20, 20), Rgb([0, 0, 0]));
+        fill(&mut img, rect(76, 11, 18, 18), Rgb([255, 255, 255]));
+        img
+    }
+
+    #[test]
+    fn components_nest_from_the_smallest_to_the_screen() {
+        let components = find_components(&screen(), crate::DEFAULT_THRESHOLD);
+
+        // The glyph is too small, the inside of the outline is a component of its own
+        assert_eq!(
+            components.rects(),
+            [
+                rect(76, 11, 18, 18),
+                rect(75, 10, 20, 20),
+                rect(20, 20, 30, 15),
+                rect(10, 10, 60, 50),
+                rect(0, 0, 100, 80),
+            ]
+        );
+        assert_eq!(components.chain_at(31, 26), [2, 3, 4]);
+        assert_eq!(components.chain_at(80, 15), [0, 1, 4]);
+        assert_eq!(components.chain_at(5, 70), [4]);
+        assert_eq!(components.chain_at(100, 0), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn measure_bounds_the_component_by_its_parent() {
+        let components = find_components(&screen(), crate::DEFAULT_THRESHOLD);
+
+        let button = components.measure(2, 25, 25, 100, 80).unwrap();
+        assert_eq!(
+            button.limits,
+            Limits {
+                top: 19,
+                bottom: 35,
+                left: 19,
+                right: 50,
+            }
+        );
+        assert_eq!(
+            button.container,
+            Limits {
+                top: 9,
+                bottom: 60,
+                left: 9,
+                right: 70,
+            }
+        );
+
+        // The screen has no parent, and its limits stay on the image
+        let whole = Limits {
+            top: 0,
+            bottom: 79,
+            left: 0,
+            right: 99,
+        };
+        let screen = components.measure(4, 5, 70, 100, 80).unwrap();
+        assert_eq!((screen.limits, screen.container), (whole, whole));
+        assert!(components.measure(5, 5, 70, 100, 80).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+//! Edge detection by walking along rows and columns of an image
+
+use crate::color;
+use crate::measure::{Axes, Limits, Measurement, Shape};
+use image::RgbImage;
+
+/// Validates that the given coordinates are within the image bounds
+/// Returns the image dimensions if they are, `None` otherwise
+pub fn validate_coordinates(img: &RgbImage, x: u32, y: u32) -> Option<(u32, u32)> {
+    let (width, height) = img.dimensions();
+
+    if x >= width || y >= height {
+        return None;
+    }
+
+    Some((width, height))
+}
+
+/// Measures the element under the given point
+/// Returns `None` if the point is outside the image
+pub fn measure_at(
+    img: &RgbImage,
+    x: u32,
+    y: u32,
+    axes: Axes,
+    threshold: f32,
+) -> Option<Measurement> {
+    validate_coordinates(img, x, y)?;
+
+    let limits = calculate_line_limits(img, x, y, axes, threshold);
+    Some(Measurement {
+        x,
+        y,
+        axes,
+        threshold,
+        limits,
+        container: calculate_container_limits(img, &limits, x, y, axes, threshold),
+        shape: Shape::Extent,
+    })
+}
+
+/// Calculates the limits of the element enclosing the one described by `limits`
+/// Continues walking from each detected edge until the next color change
+pub fn calculate_container_limits(
+    img: &RgbImage,
+    limits: &Limits,
+    x: u32,
+    y: u32,
+    axes: Axes,
+    magnitude_threshold: f32,
+) -> Limits {
+    let (width, height) = img.dimensions();
+    let mut container = *limits;
+
+    if axes.vertical() {
+        container.top = calculate_limit(img, x, limits.top, 0, true, magnitude_threshold);
+        container.bottom =
+            calculate_limit(img, x, limits.bottom, height - 1, true, magnitude_threshold);
+    }
+    if axes.horizontal() {
+        container.left = calculate_limit(img, limits.left, y, 0, false, magnitude_threshold);
+        container.right =
+            calculate_limit(img, limits.right, y, width - 1, false, magnitude_threshold);
+    }
+
+    container
+}
+
+/// Calculates the line limits for the crosshair based on color changes
+/// Extends lines along the requested axes until a significant color change is detected
+///
+/// The point must be inside the image, see [`validate_coordinates`].
+pub fn calculate_line_limits(
+    img: &RgbImage,
+    x: u32,
+    y: u32,
+    axes: Axes,
+    magnitude_threshold: f32,
+) -> Limits {
+    let (width, height) = img.dimensions();
+    let mut limits = Limits::at(x, y);
+
+    if axes.vertical() {
+        limits.top = calculate_limit(img, x, y, 0, true, magnitude_threshold);
+        limits.bottom = calculate_limit(img, x, y, height - 1, true, magnitude_threshold);
+    }
+    if axes.horizontal() {
+        limits.left = calculate_limit(img, x, y, 0, false, magnitude_threshold);
+        limits.right = calculate_limit(img, x, y, width - 1, false, magnitude_threshold);
+    }
+
+    limits
+}
+
+/// Calculates a single line limit in a specific direction
+/// Walks from the point towards `end` and returns the first position whose color
+/// differs from the previous one by more than the threshold, or `end` if none does
+pub fn calculate_limit(
+    img: &RgbImage,
+    x: u32,
+    y: u32,
+    end: u32,
+    vertical: bool,
+    magnitude_threshold: f32,
+) -> u32 {
+    let start = if vertical { y } else { x };
+    let fixed = if vertical { x } else { y };
+
+    let mut last_pixel = if vertical {
+        *img.get_pixel(fixed, start)
+    } else {
+        *img.get_pixel(start, fixed)
+    };
+
+    let iter: Box<dyn Iterator<Item = u32>> = if start > end {
+        Box::new((end..=start).rev())
+    } else {
+        Box::new(start..=end)
+    };
+
+    for pos in iter {
+        let current_pixel = if vertical {
+            img.get_pixel(fixed, pos)
+        } else {
+            img.get_pixel(pos, fixed)
+        };
+
+        if color::is_edge(&last_pixel, current_pixel, magnitude_threshold) {
+            return pos;
+        }
+
+        last_pixel = *current_pixel;
+    }
+
+    end
+}
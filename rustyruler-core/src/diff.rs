@@ -0,0 +1,214 @@
+//! Comparing an image against a reference, such as an earlier capture or a mockup
+
+use crate::color;
+use crate::rects::Rect;
+use image::RgbImage;
+
+/// Side in pixels of the square tiles changed pixels are grouped by
+/// Changes in touching tiles end up in the same region, so antialiased text
+/// or a moved icon is reported once rather than pixel by pixel
+pub const TILE_SIZE: u32 = 8;
+
+/// Bounds of the changed pixels found so far, inclusive on every side
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+}
+
+impl Bounds {
+    fn at(x: u32, y: u32) -> Self {
+        Bounds {
+            left: x,
+            top: y,
+            right: x,
+            bottom: y,
+        }
+    }
+
+    fn union(self, other: Bounds) -> Self {
+        Bounds {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    fn rect(self) -> Rect {
+        Rect {
+            x: self.left,
+            y: self.top,
+            width: self.right - self.left + 1,
+            height: self.bottom - self.top + 1,
+        }
+    }
+}
+
+/// Finds the regions where the image differs from the reference
+///
+/// The top left corner of the reference is placed at `offset` in the image,
+/// which may be negative, and only the pixels they overlap on are compared.
+/// A pixel has changed when its color differs from the reference by more than
+/// the threshold, see [`color::is_edge`]. The regions are the bounding boxes
+/// of changed pixels in touching [`TILE_SIZE`] tiles, in image pixels, sorted
+/// from top to bottom and left to right.
+pub fn changed_regions(
+    img: &RgbImage,
+    reference: &RgbImage,
+    offset: (i32, i32),
+    threshold: f32,
+) -> Vec<Rect> {
+    let (width, height) = img.dimensions();
+    let (columns, rows) = (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE));
+    let mut tiles: Vec<Option<Bounds>> = vec![None; (columns * rows) as usize];
+
+    let (x_range, y_range) = (
+        overlap(offset.0, reference.width(), width),
+        overlap(offset.1, reference.height(), height),
+    );
+    for y in y_range {
+        let reference_y = (i64::from(y) - i64::from(offset.1)) as u32;
+        for x in x_range.clone() {
+            let reference_x = (i64::from(x) - i64::from(offset.0)) as u32;
+            let changed = color::is_edge(
+                reference.get_pixel(reference_x, reference_y),
+                img.get_pixel(x, y),
+                threshold,
+            );
+            if changed {
+                let tile = &mut tiles[((y / TILE_SIZE) * columns + x / TILE_SIZE) as usize];
+                let pixel = Bounds::at(x, y);
+                *tile = Some(tile.map_or(pixel, |bounds| bounds.union(pixel)));
+            }
+        }
+    }
+
+    let mut regions = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..tiles.len() {
+        let Some(mut bounds) = tiles[start].take() else {
+            continue;
+        };
+
+        // Flood fill over the changed tiles touching this one, diagonals included
+        stack.push(start);
+        while let Some(index) = stack.pop() {
+            let (column, row) = (index as u32 % columns, index as u32 / columns);
+            for neighbor_row in row.saturating_sub(1)..=(row + 1).min(rows - 1) {
+                for neighbor_column in column.saturating_sub(1)..=(column + 1).min(columns - 1) {
+                    let neighbor = (neighbor_row * columns + neighbor_column) as usize;
+                    if let Some(found) = tiles[neighbor].take() {
+                        bounds = bounds.union(found);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        regions.push(bounds.rect());
+    }
+
+    regions.sort_by_key(|rect| (rect.y, rect.x));
+    regions
+}
+
+/// Positions along one axis of the image that the reference covers
+fn overlap(offset: i32, reference_length: u32, length: u32) -> std::ops::Range<u32> {
+    let start = i64::from(offset).clamp(0, i64::from(length));
+    let end = (i64::from(offset) + i64::from(reference_length)).clamp(start, i64::from(length));
+    start as u32..end as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgb};
+
+    /// Colors that differ from every neighbor, so any misplaced pixel shows up
+    fn screen(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            Rgb([
+                (x * 37 % 256) as u8,
+                (y * 53 % 256) as u8,
+                ((x + y) * 11 % 256) as u8,
+            ])
+        })
+    }
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn paint(img: &mut RgbImage, area: Rect) {
+        for y in area.y..area.bottom() {
+            for x in area.x..area.right() {
+                img.put_pixel(x, y, Rgb([255, 0, 255]));
+            }
+        }
+    }
+
+    #[test]
+    fn a_copy_at_its_offset_has_no_changes() {
+        let img = screen(64, 48);
+        let reference = img.view(10, 6, 30, 20).to_image();
+
+        assert_eq!(changed_regions(&img, &reference, (10, 6), 20.0), []);
+        assert_ne!(changed_regions(&img, &reference, (11, 6), 20.0), []);
+    }
+
+    #[test]
+    fn changes_are_found_in_image_pixels_with_a_negative_offset() {
+        let img = screen(64, 48);
+        // The reference starts 5 pixels left of and 3 above the image, and
+        // runs past its right and bottom edges
+        let mut reference = RgbImage::from_pixel(80, 60, Rgb([0, 0, 0]));
+        image::imageops::replace(&mut reference, &img, 5, 3);
+        paint(&mut reference, rect(20, 10, 4, 3));
+        paint(&mut reference, rect(50, 40, 6, 2));
+        // Outside the image, never compared
+        paint(&mut reference, rect(72, 55, 5, 5));
+
+        assert_eq!(
+            changed_regions(&img, &reference, (-5, -3), 20.0),
+            [rect(15, 7, 4, 3), rect(45, 37, 6, 2)]
+        );
+    }
+
+    #[test]
+    fn only_the_overlap_is_compared() {
+        let img = screen(64, 48);
+        // Overlaps the top left 20 by 12 pixels of the image only
+        let mut reference = RgbImage::from_pixel(28, 16, Rgb([0, 0, 0]));
+        image::imageops::replace(&mut reference, &img.view(0, 0, 20, 12).to_image(), 8, 4);
+        paint(&mut reference, rect(10, 6, 2, 2));
+        // Above and left of the image, never compared
+        paint(&mut reference, rect(0, 0, 4, 4));
+
+        assert_eq!(
+            changed_regions(&img, &reference, (-8, -4), 20.0),
+            [rect(2, 2, 2, 2)]
+        );
+    }
+
+    #[test]
+    fn changes_in_touching_tiles_are_one_region() {
+        let img = screen(64, 48);
+        let mut reference = img.clone();
+        // Spans the border between two tiles, and a separate change further away
+        paint(&mut reference, rect(6, 4, 4, 2));
+        paint(&mut reference, rect(40, 30, 1, 1));
+
+        assert_eq!(
+            changed_regions(&img, &reference, (0, 0), 20.0),
+            [rect(6, 4, 4, 2), rect(40, 30, 1, 1)]
+        );
+    }
+}
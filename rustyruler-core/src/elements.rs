@@ -0,0 +1,238 @@
+//! Finding the elements laid out in a region and the spacing between them
+//!
+//! The background of the region is the color most of its border has. Pixels
+//! that differ from it by more than the threshold, the same rule as
+//! [`color::is_edge`], belong to elements: touching pixels are grouped, and
+//! groups at most [`MERGE_DISTANCE`] apart are merged, so the letters of a label
+//! or the icon and text of a button make up a single element. An element
+//! filled with its own color holds everything drawn inside it, so the
+//! elements found are those placed directly on the background.
+
+use crate::color;
+use crate::rects::Rect;
+use crate::units::Axis;
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+
+/// Largest gap in pixels between two groups of pixels of the same element
+pub const MERGE_DISTANCE: u32 = 4;
+
+/// Groups with fewer pixels than this are taken for noise, such as a stray
+/// antialiased pixel
+const MIN_PIXELS: usize = 4;
+
+/// Space between two neighboring elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spacing {
+    /// Index of the element left of or above the gap
+    pub from: usize,
+    /// Index of the element right of or below the gap
+    pub to: usize,
+    /// Horizontal for a gap between side by side elements, vertical for stacked ones
+    pub axis: Axis,
+    /// First pixel of the gap along the axis, right after `from`
+    pub start: u32,
+    /// First pixel past the gap along the axis, where `to` starts
+    pub end: u32,
+    /// Position across the axis where both elements face each other, at the
+    /// middle of their overlap
+    pub across: u32,
+}
+
+impl Spacing {
+    /// Size of the gap in pixels
+    pub fn length(&self) -> u32 {
+        self.end - self.start
+    }
+}
+
+/// Finds the elements inside the region, sorted from top to bottom and left to right
+///
+/// The region is clipped to the image. Returns no element for an empty
+/// region or one filled with the background color.
+pub fn find_elements(img: &RgbImage, region: Rect, threshold: f32) -> Vec<Rect> {
+    let region = clip(region, img.width(), img.height());
+    if region.width == 0 || region.height == 0 {
+        return Vec::new();
+    }
+    let background = background(img, region);
+
+    let index = |x: u32, y: u32| ((y - region.y) * region.width + (x - region.x)) as usize;
+    let mut foreground = vec![false; (region.width * region.height) as usize];
+    for y in region.y..region.bottom() {
+        for x in region.x..region.right() {
+            foreground[index(x, y)] = color::is_edge(&background, img.get_pixel(x, y), threshold);
+        }
+    }
+
+    // Touching pixels, diagonals included, are grouped by flood fill
+    let mut groups = Vec::new();
+    let mut stack = Vec::new();
+    for y in region.y..region.bottom() {
+        for x in region.x..region.right() {
+            if !foreground[index(x, y)] {
+                continue;
+            }
+            foreground[index(x, y)] = false;
+            stack.push((x, y));
+
+            let (mut left, mut top, mut right, mut bottom) = (x, y, x, y);
+            let mut pixels = 0;
+            while let Some((x, y)) = stack.pop() {
+                pixels += 1;
+                (left, top) = (left.min(x), top.min(y));
+                (right, bottom) = (right.max(x), bottom.max(y));
+
+                for neighbor_y in
+                    y.saturating_sub(1).max(region.y)..=(y + 1).min(region.bottom() - 1)
+                {
+                    for neighbor_x in
+                        x.saturating_sub(1).max(region.x)..=(x + 1).min(region.right() - 1)
+                    {
+                        if foreground[index(neighbor_x, neighbor_y)] {
+                            foreground[index(neighbor_x, neighbor_y)] = false;
+                            stack.push((neighbor_x, neighbor_y));
+                        }
+                    }
+                }
+            }
+
+            if pixels >= MIN_PIXELS {
+                groups.push(Rect {
+                    x: left,
+                    y: top,
+                    width: right - left + 1,
+                    height: bottom - top + 1,
+                });
+            }
+        }
+    }
+
+    let mut elements = merge_close(groups);
+    elements.sort_by_key(|rect| (rect.y, rect.x));
+    elements
+}
+
+/// Finds the gap from every element to its nearest neighbor on the right and below
+///
+/// Neighbors on the right overlap the element vertically and start past its
+/// right edge, neighbors below overlap it horizontally and start past its
+/// bottom edge. Elements touching or overlapping have no gap between them.
+pub fn spacings(elements: &[Rect]) -> Vec<Spacing> {
+    let mut spacings = Vec::new();
+
+    for (from, a) in elements.iter().enumerate() {
+        let right = elements
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.x > a.right() && overlap(a.y, a.bottom(), b.y, b.bottom()).is_some())
+            .min_by_key(|(_, b)| b.x);
+        if let Some((to, b)) = right {
+            let (start, end) = overlap(a.y, a.bottom(), b.y, b.bottom()).unwrap_or_default();
+            spacings.push(Spacing {
+                from,
+                to,
+                axis: Axis::Horizontal,
+                start: a.right(),
+                end: b.x,
+                across: (start + end) / 2,
+            });
+        }
+
+        let below = elements
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.y > a.bottom() && overlap(a.x, a.right(), b.x, b.right()).is_some())
+            .min_by_key(|(_, b)| b.y);
+        if let Some((to, b)) = below {
+            let (start, end) = overlap(a.x, a.right(), b.x, b.right()).unwrap_or_default();
+            spacings.push(Spacing {
+                from,
+                to,
+                axis: Axis::Vertical,
+                start: a.bottom(),
+                end: b.y,
+                across: (start + end) / 2,
+            });
+        }
+    }
+
+    spacings
+}
+
+/// Part of the region inside the image
+fn clip(region: Rect, width: u32, height: u32) -> Rect {
+    let (x, y) = (region.x.min(width), region.y.min(height));
+    Rect {
+        x,
+        y,
+        width: region.right().min(width) - x,
+        height: region.bottom().min(height) - y,
+    }
+}
+
+/// Most common color along the border of the region
+fn background(img: &RgbImage, region: Rect) -> Rgb<u8> {
+    let (right, bottom) = (region.right() - 1, region.bottom() - 1);
+    let horizontal = (region.x..=right).flat_map(|x| [(x, region.y), (x, bottom)]);
+    let vertical = (region.y..=bottom).flat_map(|y| [(region.x, y), (right, y)]);
+
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for (x, y) in horizontal.chain(vertical) {
+        *counts.entry(img.get_pixel(x, y).0).or_default() += 1;
+    }
+
+    // Ties go to the lowest color value, so the result doesn't depend on the hash order
+    counts
+        .into_iter()
+        .max_by_key(|&(rgb, count)| (count, std::cmp::Reverse(rgb)))
+        .map(|(rgb, _)| Rgb(rgb))
+        .unwrap_or(Rgb([0, 0, 0]))
+}
+
+/// Merges rectangles overlapping or at most [`MERGE_DISTANCE`] apart until none are
+fn merge_close(mut rects: Vec<Rect>) -> Vec<Rect> {
+    let close = |a: &Rect, b: &Rect| {
+        a.x <= b.right() + MERGE_DISTANCE
+            && b.x <= a.right() + MERGE_DISTANCE
+            && a.y <= b.bottom() + MERGE_DISTANCE
+            && b.y <= a.bottom() + MERGE_DISTANCE
+    };
+
+    let mut merged = true;
+    while merged {
+        merged = false;
+        let mut index = 0;
+        while index < rects.len() {
+            let mut other = index + 1;
+            while other < rects.len() {
+                if close(&rects[index], &rects[other]) {
+                    let b = rects.swap_remove(other);
+                    rects[index] = union(&rects[index], &b);
+                    merged = true;
+                } else {
+                    other += 1;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    rects
+}
+
+fn union(a: &Rect, b: &Rect) -> Rect {
+    let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+    Rect {
+        x,
+        y,
+        width: a.right().max(b.right()) - x,
+        height: a.bottom().max(b.bottom()) - y,
+    }
+}
+
+/// Shared part of the spans `a_start..a_end` and `b_start..b_end`, if any
+fn overlap(a_start: u32, a_end: u32, b_start: u32, b_end: u32) -> Option<(u32, u32)> {
+    let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+    (start < end).then_some((start, end))
+}
@@ -0,0 +1,53 @@
+//! Points and angles in image coordinates
+
+/// A point in image coordinates, with sub-pixel precision
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+
+    /// Point on the given pixel, at its whole coordinates
+    ///
+    /// [`ray::sample`](crate::ray::sample) reads the exact color of a pixel
+    /// there, and blends neighbors between them. Drawn over the image, as the
+    /// cursor is, the point falls on the top left corner of the pixel.
+    pub fn pixel(x: u32, y: u32) -> Self {
+        Point::new(f64::from(x), f64::from(y))
+    }
+
+    pub fn distance(&self, other: &Point) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
+
+    /// Direction from this point towards another, in radians
+    /// Angles grow clockwise since the y axis points down
+    pub fn direction_to(&self, other: &Point) -> f64 {
+        (other.y - self.y).atan2(other.x - self.x)
+    }
+}
+
+/// Angle in degrees between the arms from `vertex` to `a` and to `b`, from 0 to 180
+pub fn angle_between(vertex: &Point, a: &Point, b: &Point) -> f64 {
+    let diff = (vertex.direction_to(a) - vertex.direction_to(b)).abs();
+    let diff = if diff > std::f64::consts::PI {
+        2.0 * std::f64::consts::PI - diff
+    } else {
+        diff
+    };
+    diff.to_degrees()
+}
+
+/// Moves `point` onto the line through `origin` with the given direction
+/// The projected point stays on the same side of the origin as the original one
+pub fn project_onto_direction(origin: &Point, point: &Point, direction: f64) -> Point {
+    let (dx, dy) = (direction.cos(), direction.sin());
+    let along = (point.x - origin.x) * dx + (point.y - origin.y) * dy;
+    Point::new(origin.x + along * dx, origin.y + along * dy)
+}
@@ -0,0 +1,69 @@
+//! Edge orientation from the color gradient of an image
+//!
+//! Uses the multi-channel structure tensor: per-channel Sobel gradients are
+//! summed as in the color distance, so an edge between two colors of equal
+//! brightness is found as well as a black and white one.
+
+use image::RgbImage;
+
+/// Half size of the window the gradient is averaged over
+pub const DEFAULT_RADIUS: u32 = 3;
+
+/// Finds the direction of the dominant edge around a point
+///
+/// Returns the angle in radians of the edge line itself (perpendicular to the
+/// gradient), or `None` when the window has no edge stronger than the threshold
+/// or no single dominant direction.
+pub fn edge_direction(img: &RgbImage, x: u32, y: u32, radius: u32, threshold: f32) -> Option<f64> {
+    let (width, height) = img.dimensions();
+    if width < 3 || height < 3 {
+        return None;
+    }
+
+    // Keep the Sobel kernel inside the image
+    let x_range = x.saturating_sub(radius).max(1)..=(x + radius).min(width - 2);
+    let y_range = y.saturating_sub(radius).max(1)..=(y + radius).min(height - 2);
+
+    let (mut gxx, mut gyy, mut gxy) = (0.0_f64, 0.0_f64, 0.0_f64);
+    let mut strongest = 0.0_f64;
+
+    for py in y_range {
+        for px in x_range.clone() {
+            let mut pixel_energy = 0.0;
+            for channel in 0..3 {
+                let value = |dx: i32, dy: i32| {
+                    let sx = (px as i32 + dx) as u32;
+                    let sy = (py as i32 + dy) as u32;
+                    f64::from(img.get_pixel(sx, sy)[channel])
+                };
+
+                let gx = (value(1, -1) + 2.0 * value(1, 0) + value(1, 1))
+                    - (value(-1, -1) + 2.0 * value(-1, 0) + value(-1, 1));
+                let gy = (value(-1, 1) + 2.0 * value(0, 1) + value(1, 1))
+                    - (value(-1, -1) + 2.0 * value(0, -1) + value(1, -1));
+
+                gxx += gx * gx;
+                gyy += gy * gy;
+                gxy += gx * gy;
+                pixel_energy += gx * gx + gy * gy;
+            }
+            strongest = strongest.max(pixel_energy);
+        }
+    }
+
+    // Sobel responses across a step are 4 times the plain pixel difference,
+    // which makes this comparable to the color distance used for the limits
+    if strongest.sqrt() / 4.0 <= f64::from(threshold) {
+        return None;
+    }
+
+    // Largest eigenvector of the tensor is the gradient direction, and the
+    // difference between eigenvalues tells how much one direction dominates
+    let coherence = ((gxx - gyy).powi(2) + 4.0 * gxy * gxy).sqrt() / (gxx + gyy);
+    if coherence < 0.5 {
+        return None;
+    }
+
+    let gradient_direction = 0.5 * (2.0 * gxy).atan2(gxx - gyy);
+    Some(gradient_direction + std::f64::consts::FRAC_PI_2)
+}
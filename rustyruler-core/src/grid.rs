@@ -0,0 +1,155 @@
+//! Layout grids measured edges are checked against
+//!
+//! A [`Grid`] is a set of evenly spaced lines in both directions, like an 8px
+//! baseline grid. [`Columns`] splits the width of the screen into columns
+//! separated by gutters, like a 12-column layout. Both work in image pixels.
+
+/// Evenly spaced lines in both directions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    /// Distance between two lines
+    pub spacing: f64,
+    /// Position of the first line on each axis
+    pub offset: (f64, f64),
+}
+
+impl Grid {
+    /// Signed distance from `position` to the nearest line, along the given axis
+    /// Positive when the position is past the line
+    pub fn offset_from_line(&self, position: f64, vertical: bool) -> f64 {
+        let origin = if vertical {
+            self.offset.1
+        } else {
+            self.offset.0
+        };
+        let relative = (position - origin).rem_euclid(self.spacing);
+        if relative > self.spacing / 2.0 {
+            relative - self.spacing
+        } else {
+            relative
+        }
+    }
+
+    /// Positions of the lines between 0 and `length`, along one axis
+    pub fn lines(&self, length: f64, vertical: bool) -> Vec<f64> {
+        let origin = if vertical {
+            self.offset.1
+        } else {
+            self.offset.0
+        };
+        let first = origin.rem_euclid(self.spacing);
+        (0..)
+            .map(|index| first + f64::from(index) * self.spacing)
+            .take_while(|position| *position <= length)
+            .collect()
+    }
+}
+
+/// Columns of equal width spread over the screen, with gutters between them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Columns {
+    pub count: u32,
+    /// Space between two columns
+    pub gutter: f64,
+    /// Space left empty on both sides of the first and last columns
+    pub margin: f64,
+}
+
+impl Columns {
+    /// Start and end of every column across the given width
+    /// Empty when the margins and gutters leave no room for the columns
+    pub fn spans(&self, width: f64) -> Vec<(f64, f64)> {
+        if self.count == 0 {
+            return Vec::new();
+        }
+
+        let count = f64::from(self.count);
+        let column_width = (width - 2.0 * self.margin - (count - 1.0) * self.gutter) / count;
+        if column_width <= 0.0 {
+            return Vec::new();
+        }
+
+        (0..self.count)
+            .map(|index| {
+                let start = self.margin + f64::from(index) * (column_width + self.gutter);
+                (start, start + column_width)
+            })
+            .collect()
+    }
+
+    /// Signed distance from `position` to the nearest column edge
+    /// Positive when the position is past the edge, `None` without columns
+    pub fn offset_from_edge(&self, position: f64, width: f64) -> Option<f64> {
+        self.spans(width)
+            .into_iter()
+            .flat_map(|(start, end)| [start, end])
+            .map(|edge| position - edge)
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+    }
+}
+
+/// Side of a measured element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    pub fn label(self) -> &'static str {
+        match self {
+            Edge::Left => "left",
+            Edge::Right => "right",
+            Edge::Top => "top",
+            Edge::Bottom => "bottom",
+        }
+    }
+
+    /// Whether the edge is a horizontal line, found walking up or down
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Edge::Top | Edge::Bottom)
+    }
+}
+
+/// How far a measured edge is from the nearest grid line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeSnap {
+    pub edge: Edge,
+    /// Signed distance in image pixels, positive past the line
+    pub offset: f64,
+}
+
+impl EdgeSnap {
+    /// Whether the edge lies on the line, allowing for rounding
+    pub fn on_line(&self) -> bool {
+        self.offset.abs() < 0.5
+    }
+}
+
+/// Checks every given edge position against the grid
+pub fn snap_to_grid(grid: &Grid, edges: &[(Edge, f64)]) -> Vec<EdgeSnap> {
+    edges
+        .iter()
+        .map(|&(edge, position)| EdgeSnap {
+            edge,
+            offset: grid.offset_from_line(position, edge.is_horizontal()),
+        })
+        .collect()
+}
+
+/// Checks the left and right edges against the column edges
+/// Top and bottom edges are skipped since columns only split the width
+pub fn snap_to_columns(columns: &Columns, width: f64, edges: &[(Edge, f64)]) -> Vec<EdgeSnap> {
+    edges
+        .iter()
+        .filter(|(edge, _)| !edge.is_horizontal())
+        .filter_map(|&(edge, position)| {
+            Some(EdgeSnap {
+                edge,
+                offset: columns.offset_from_edge(position, width)?,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+//! Guide lines placed by the user, snapping to the edges of elements
+
+use crate::color;
+use image::RgbImage;
+
+/// Direction a guide runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Orientation {
+    /// Runs left to right, placed at a y coordinate
+    Horizontal,
+    /// Runs top to bottom, placed at an x coordinate
+    Vertical,
+}
+
+/// A guide across the whole image
+/// The position is a boundary between two rows or columns of pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Guide {
+    pub orientation: Orientation,
+    pub position: u32,
+}
+
+impl Guide {
+    pub fn new(orientation: Orientation, position: u32) -> Self {
+        Guide {
+            orientation,
+            position,
+        }
+    }
+}
+
+/// Moves a guide position to the nearest edge within `distance` pixels
+///
+/// Edges are searched on the row (for vertical guides) or column (for
+/// horizontal guides) going through `across`, usually where the cursor is.
+/// The position is returned unchanged when no edge is close enough.
+pub fn snap(
+    img: &RgbImage,
+    orientation: Orientation,
+    position: u32,
+    across: u32,
+    threshold: f32,
+    distance: u32,
+) -> u32 {
+    let (width, height) = img.dimensions();
+    let (length, across_length) = match orientation {
+        Orientation::Vertical => (width, height),
+        Orientation::Horizontal => (height, width),
+    };
+    if across >= across_length || length < 2 {
+        return position;
+    }
+
+    let pixel = |along: u32| match orientation {
+        Orientation::Vertical => img.get_pixel(along, across),
+        Orientation::Horizontal => img.get_pixel(across, along),
+    };
+
+    // A boundary at `b` lies between pixels `b - 1` and `b`
+    let first = position.saturating_sub(distance).max(1);
+    let last = position.saturating_add(distance).min(length - 1);
+    (first..=last)
+        .filter(|&boundary| color::is_edge(pixel(boundary - 1), pixel(boundary), threshold))
+        .min_by_key(|&boundary| boundary.abs_diff(position))
+        .unwrap_or(position)
+}
+
+/// Positions of the guides with the given orientation, sorted
+pub fn positions(guides: &[Guide], orientation: Orientation) -> Vec<u32> {
+    let mut positions: Vec<u32> = guides
+        .iter()
+        .filter(|guide| guide.orientation == orientation)
+        .map(|guide| guide.position)
+        .collect();
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
@@ -0,0 +1,52 @@
+//! Measurement logic behind rustyruler, free of any GTK dependency.
+//!
+//! The crate works on plain [`image::RgbImage`] buffers: given a point and the
+//! axes to measure along, it walks outward until the color changes by more than
+//! a threshold and reports where the element under the point starts and ends.
+//!
+//! ```
+//! use image::{Rgb, RgbImage};
+//! use rustyruler_core::{Axes, DEFAULT_THRESHOLD, measure_at};
+//!
+//! // A 10px wide white bar on a black background
+//! let img = RgbImage::from_fn(30, 5, |x, _| {
+//!     if (10..20).contains(&x) { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) }
+//! });
+//!
+//! let measurement = measure_at(&img, 15, 2, Axes::Horizontal, DEFAULT_THRESHOLD).unwrap();
+//! assert_eq!(measurement.limits.left, 9);
+//! assert_eq!(measurement.limits.right, 20);
+//! ```
+
+pub mod circle;
+pub mod color;
+pub mod components;
+pub mod detect;
+pub mod diff;
+pub mod elements;
+pub mod geometry;
+pub mod gradient;
+pub mod grid;
+pub mod guides;
+pub mod measure;
+pub mod ray;
+pub mod rects;
+pub mod register;
+pub mod typography;
+pub mod units;
+
+pub use circle::measure_circle;
+pub use components::{Components, find_components};
+pub use detect::{calculate_line_limits, measure_at, validate_coordinates};
+pub use diff::changed_regions;
+pub use elements::{find_elements, spacings};
+pub use geometry::Point;
+pub use measure::{Axes, Limits, Measurement, Shape};
+pub use ray::measure_along;
+pub use rects::{Rect, measure_among};
+pub use register::align;
+pub use typography::measure_text;
+pub use units::{Unit, Units};
+
+/// Color distance above which a pixel is considered to belong to another element
+pub const DEFAULT_THRESHOLD: f32 = 20.0;
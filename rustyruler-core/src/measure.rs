@@ -0,0 +1,184 @@
+//! Types describing what is being measured and the result of a measurement
+
+use crate::geometry::{self, Point};
+
+/// Axes along which the edge detection walks from the measured point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Axes {
+    /// Both directions, giving width and height
+    #[default]
+    Both,
+    /// Left and right only, giving the width
+    Horizontal,
+    /// Up and down only, giving the height
+    Vertical,
+}
+
+impl Axes {
+    /// Whether the left and right limits are detected
+    pub fn horizontal(self) -> bool {
+        matches!(self, Axes::Both | Axes::Horizontal)
+    }
+
+    /// Whether the top and bottom limits are detected
+    pub fn vertical(self) -> bool {
+        matches!(self, Axes::Both | Axes::Vertical)
+    }
+}
+
+/// Image coordinates where the color change was detected in each direction
+///
+/// When an axis is not measured, its limits stay at the measured point.
+/// When no change is found, the limit is the last pixel of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limits {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl Limits {
+    /// Limits collapsed on a single point
+    pub fn at(x: u32, y: u32) -> Self {
+        Limits {
+            top: y,
+            bottom: y,
+            left: x,
+            right: x,
+        }
+    }
+
+    /// Horizontal size in pixels, as shown to the user
+    pub fn width(&self) -> u32 {
+        self.right - self.left + 1
+    }
+
+    /// Vertical size in pixels, as shown to the user
+    pub fn height(&self) -> u32 {
+        self.bottom - self.top + 1
+    }
+
+    /// Returns the limits moved by the given amount in both axes
+    pub fn offset(&self, delta: u32) -> Self {
+        Limits {
+            top: self.top + delta,
+            bottom: self.bottom + delta,
+            left: self.left + delta,
+            right: self.right + delta,
+        }
+    }
+}
+
+/// Geometry of a measurement beyond its axis-aligned limits
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Shape {
+    /// Nothing but the limits along the measured axes
+    #[default]
+    Extent,
+    /// Angle between two arms sharing a vertex
+    /// `second` is missing while the arms are being placed
+    Angle {
+        vertex: Point,
+        first: Point,
+        second: Option<Point>,
+    },
+    /// Extent of an element along a line at an arbitrary angle
+    /// `start` and `end` lie on the boundaries where the color changes
+    Ray {
+        start: Point,
+        end: Point,
+        /// Angle of the line in degrees, counterclockwise from the horizontal
+        degrees: f64,
+    },
+    /// Circle fitted to the edges around the measured point
+    Circle {
+        center: Point,
+        radius: f64,
+        /// Root mean square distance in pixels from the edges to the circle
+        error: f64,
+    },
+    /// Color of the pixel at the measured point
+    Color { rgb: [u8; 3] },
+    /// Metrics of the line of text at the measured point, whose ink the limits bound
+    Text {
+        /// First row below the bodies of the glyphs
+        baseline: u32,
+        /// Height of the lowercase letters above the baseline, if they differ from the capitals
+        x_height: Option<u32>,
+        /// Height of the capitals and ascenders above the baseline
+        cap_height: u32,
+        /// Distance from the baseline to that of the next line, if one is found below
+        line_height: Option<u32>,
+        /// Blank rows between the ink of the line and that of the next one
+        spacing: Option<u32>,
+    },
+}
+
+impl Shape {
+    /// Angle in degrees for a complete [`Shape::Angle`]
+    pub fn degrees(&self) -> Option<f64> {
+        match self {
+            Shape::Angle {
+                vertex,
+                first,
+                second: Some(second),
+            } => Some(geometry::angle_between(vertex, first, second)),
+            _ => None,
+        }
+    }
+
+    /// Length in pixels of a [`Shape::Ray`]
+    pub fn length(&self) -> Option<f64> {
+        match self {
+            Shape::Ray { start, end, .. } => Some(start.distance(end)),
+            _ => None,
+        }
+    }
+}
+
+/// A single measurement taken at a point of the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    pub x: u32,
+    pub y: u32,
+    pub axes: Axes,
+    pub threshold: f32,
+    pub limits: Limits,
+    /// Limits of the element enclosing the measured one, found by walking on
+    /// past its edges, used to report sizes relative to it
+    pub container: Limits,
+    /// Geometry measured by tools that go beyond axis-aligned limits
+    pub shape: Shape,
+}
+
+impl Measurement {
+    /// Measurement of a shape placed by the user at a point, without detected limits
+    pub fn at(x: u32, y: u32, threshold: f32, shape: Shape) -> Self {
+        Measurement {
+            x,
+            y,
+            axes: Axes::Both,
+            threshold,
+            limits: Limits::at(x, y),
+            container: Limits::at(x, y),
+            shape,
+        }
+    }
+
+    /// Width of the measured element, if it was measured horizontally
+    pub fn width(&self) -> Option<u32> {
+        self.axes.horizontal().then(|| self.limits.width())
+    }
+
+    /// Height of the measured element, if it was measured vertically
+    pub fn height(&self) -> Option<u32> {
+        self.axes.vertical().then(|| self.limits.height())
+    }
+}
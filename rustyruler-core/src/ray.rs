@@ -0,0 +1,144 @@
+//! Edge detection along lines at arbitrary angles
+//!
+//! Rays are stepped with a DDA: every step moves one pixel along the major
+//! axis and a fraction of a pixel along the other. Colors between pixel
+//! centers are sampled bilinearly so diagonal edges are found where they are,
+//! not where the nearest pixel happens to be.
+
+use crate::color;
+use crate::detect::validate_coordinates;
+use crate::geometry::Point;
+use crate::measure::{Measurement, Shape};
+use image::{Rgb, RgbImage};
+
+/// Steps an edge is followed past its detection while the colors are still blending
+const MAX_BLEND_STEPS: usize = 8;
+
+/// Iterations used to place a boundary between two samples, enough for 1/100 px
+const BISECTION_STEPS: usize = 12;
+
+/// Color of the image at a point between pixel centers
+/// The point must be inside the image
+pub fn sample(img: &RgbImage, point: Point) -> Rgb<u8> {
+    let (width, height) = img.dimensions();
+    let x0 = (point.x.floor().max(0.0) as u32).min(width - 1);
+    let y0 = (point.y.floor().max(0.0) as u32).min(height - 1);
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let fx = (point.x - f64::from(x0)).clamp(0.0, 1.0);
+    let fy = (point.y - f64::from(y0)).clamp(0.0, 1.0);
+
+    let (top_left, top_right) = (img.get_pixel(x0, y0), img.get_pixel(x1, y0));
+    let (bottom_left, bottom_right) = (img.get_pixel(x0, y1), img.get_pixel(x1, y1));
+
+    Rgb(std::array::from_fn(|channel| {
+        let top = f64::from(top_left[channel]) * (1.0 - fx) + f64::from(top_right[channel]) * fx;
+        let bottom =
+            f64::from(bottom_left[channel]) * (1.0 - fx) + f64::from(bottom_right[channel]) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }))
+}
+
+/// Walks from `origin` in the given direction, in radians with y pointing down,
+/// and returns where the color changes by more than the threshold
+///
+/// Bilinear sampling spreads an edge over a few steps when the ray crosses it
+/// at an angle, so the boundary is placed where the color is halfway between
+/// the element and what lies past the blend. When no change is found, the last
+/// sample inside the image is returned.
+pub fn calculate_ray_limit(
+    img: &RgbImage,
+    origin: Point,
+    direction: f64,
+    magnitude_threshold: f32,
+) -> Point {
+    let (width, height) = img.dimensions();
+    let (max_x, max_y) = (f64::from(width - 1), f64::from(height - 1));
+
+    // One pixel along the major axis per step
+    let (dx, dy) = (direction.cos(), direction.sin());
+    let major = dx.abs().max(dy.abs());
+    let step = (dx / major, dy / major);
+
+    let inside =
+        |point: &Point| point.x >= 0.0 && point.y >= 0.0 && point.x <= max_x && point.y <= max_y;
+
+    let mut last_point = origin;
+    let mut last_color = sample(img, origin);
+
+    loop {
+        let point = Point::new(last_point.x + step.0, last_point.y + step.1);
+        if !inside(&point) {
+            return last_point;
+        }
+
+        let color = sample(img, point);
+        if color::is_edge(&last_color, &color, magnitude_threshold) {
+            // Follow the blend until the color stops moving away from the element
+            let mut far_point = point;
+            let mut far_distance = color::distance(&last_color, &color);
+            for _ in 0..MAX_BLEND_STEPS {
+                let next = Point::new(far_point.x + step.0, far_point.y + step.1);
+                if !inside(&next) {
+                    break;
+                }
+                let distance = color::distance(&last_color, &sample(img, next));
+                if distance <= far_distance {
+                    break;
+                }
+                far_point = next;
+                far_distance = distance;
+            }
+
+            return halfway(img, last_point, far_point, &last_color, far_distance / 2.0);
+        }
+
+        last_point = point;
+        last_color = color;
+    }
+}
+
+/// Bisects the segment from `from` to `to` for the point whose color is
+/// `distance` away from `color`
+fn halfway(img: &RgbImage, from: Point, to: Point, color: &Rgb<u8>, distance: f32) -> Point {
+    let (mut near, mut far) = (from, to);
+    for _ in 0..BISECTION_STEPS {
+        let middle = Point::new((near.x + far.x) / 2.0, (near.y + far.y) / 2.0);
+        if color::distance(color, &sample(img, middle)) < distance {
+            near = middle;
+        } else {
+            far = middle;
+        }
+    }
+    Point::new((near.x + far.x) / 2.0, (near.y + far.y) / 2.0)
+}
+
+/// Measures the element under the given point along a line at `degrees`,
+/// counterclockwise from the horizontal
+/// Returns `None` if the point is outside the image
+pub fn measure_along(
+    img: &RgbImage,
+    x: u32,
+    y: u32,
+    degrees: f64,
+    threshold: f32,
+) -> Option<Measurement> {
+    validate_coordinates(img, x, y)?;
+
+    // The y axis points down, so counterclockwise angles are negative
+    let direction = -degrees.to_radians();
+    let origin = Point::pixel(x, y);
+    let start = calculate_ray_limit(img, origin, direction + std::f64::consts::PI, threshold);
+    let end = calculate_ray_limit(img, origin, direction, threshold);
+
+    Some(Measurement::at(
+        x,
+        y,
+        threshold,
+        Shape::Ray {
+            start,
+            end,
+            degrees,
+        },
+    ))
+}
@@ -0,0 +1,93 @@
+//! Measuring between known rectangles instead of color changes
+//!
+//! The rectangles come from outside the image, such as the window geometry a
+//! compositor reports. Their edges stand in for the color changes found by
+//! [`calculate_line_limits`](crate::calculate_line_limits), so the results can
+//! be drawn and compared like any other measurement.
+
+use crate::measure::{Limits, Measurement, Shape};
+
+/// Rectangle in image pixels, covering `x..x + width` and `y..y + height`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// First column past the rectangle
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    /// First row past the rectangle
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.right()).contains(&x) && (self.y..self.bottom()).contains(&y)
+    }
+}
+
+/// Measures the space around a point bounded by the edges of the rectangles
+///
+/// Inside a rectangle this is the rectangle itself, unless another one overlaps
+/// it, and between rectangles it is the gap separating them. Only rectangles
+/// crossing the row or column of the point are considered, and the borders of
+/// the image close the space otherwise. The limits sit on the first pixel past
+/// each edge, like those found by color changes.
+///
+/// Returns `None` if the point is outside the image.
+pub fn measure_among(
+    rects: &[Rect],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Option<Measurement> {
+    if x >= width || y >= height {
+        return None;
+    }
+
+    let across_row = rects
+        .iter()
+        .filter(|rect| (rect.y..rect.bottom()).contains(&y))
+        .flat_map(|rect| [rect.x, rect.right()]);
+    let (left, right) = enclosing(across_row, x, width);
+
+    let across_column = rects
+        .iter()
+        .filter(|rect| (rect.x..rect.right()).contains(&x))
+        .flat_map(|rect| [rect.y, rect.bottom()]);
+    let (top, bottom) = enclosing(across_column, y, height);
+
+    let mut measurement = Measurement::at(x, y, 0.0, Shape::Extent);
+    measurement.limits = Limits {
+        top: top.saturating_sub(1),
+        bottom: bottom.min(height - 1),
+        left: left.saturating_sub(1),
+        right: right.min(width - 1),
+    };
+    measurement.container = Limits {
+        top: 0,
+        bottom: height - 1,
+        left: 0,
+        right: width - 1,
+    };
+    Some(measurement)
+}
+
+/// Closest edges on both sides of `position`, with an edge at `0` and at `length`
+fn enclosing(edges: impl Iterator<Item = u32>, position: u32, length: u32) -> (u32, u32) {
+    edges.fold((0, length), |(before, after), edge| {
+        if edge <= position {
+            (before.max(edge), after)
+        } else {
+            (before, after.min(edge))
+        }
+    })
+}
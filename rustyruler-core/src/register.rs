@@ -0,0 +1,225 @@
+//! Lining a reference image up with a capture by matching their edges
+//!
+//! Colors rarely match between a mockup and the screen, but the edges of the
+//! elements do. Both images are reduced to maps of edge pixels, and the offset
+//! where most edges of the reference fall on edges of the capture wins. The
+//! search first runs on shrunken maps, then refines the best coarse offset at
+//! full resolution.
+
+use crate::color;
+use image::RgbImage;
+use std::ops::RangeInclusive;
+
+/// How many pixels along each side are merged into one in the coarse search
+const COARSE_FACTOR: u32 = 4;
+
+/// Pixels of an image that differ from their right or bottom neighbor
+struct EdgeMap {
+    width: u32,
+    height: u32,
+    edges: Vec<bool>,
+}
+
+impl EdgeMap {
+    fn new(img: &RgbImage, threshold: f32) -> Self {
+        let (width, height) = img.dimensions();
+        let mut edges = vec![false; (width * height) as usize];
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let right = x + 1 < width && color::is_edge(pixel, img.get_pixel(x + 1, y), threshold);
+            let below = y + 1 < height && color::is_edge(pixel, img.get_pixel(x, y + 1), threshold);
+            edges[(y * width + x) as usize] = right || below;
+        }
+        EdgeMap {
+            width,
+            height,
+            edges,
+        }
+    }
+
+    /// Map where each pixel stands for a `factor` by `factor` block of this one,
+    /// marked when any pixel of the block is
+    fn shrink(&self, factor: u32) -> Self {
+        let (width, height) = (self.width.div_ceil(factor), self.height.div_ceil(factor));
+        let mut edges = vec![false; (width * height) as usize];
+        for (x, y) in self.points() {
+            edges[((y / factor) * width + x / factor) as usize] = true;
+        }
+        EdgeMap {
+            width,
+            height,
+            edges,
+        }
+    }
+
+    /// Whether the pixel is an edge, `false` outside the map
+    fn get(&self, x: i64, y: i64) -> bool {
+        (0..i64::from(self.width)).contains(&x)
+            && (0..i64::from(self.height)).contains(&y)
+            && self.edges[(y * i64::from(self.width) + x) as usize]
+    }
+
+    fn points(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.edges
+            .iter()
+            .enumerate()
+            .filter(|(_, edge)| **edge)
+            .map(|(index, _)| (index as u32 % self.width, index as u32 / self.width))
+    }
+}
+
+/// Finds the offset of the reference that lines its edges up with those of the image
+///
+/// Offsets up to `max_shift` pixels away from `around` are tried in each
+/// direction, and the one where the most edge pixels of both images coincide
+/// is returned, the closest to `around` on a tie. Edges are found with the
+/// same threshold as [`color::is_edge`]. Returns `None` when no offset lines
+/// up a single edge.
+pub fn align(
+    img: &RgbImage,
+    reference: &RgbImage,
+    around: (i32, i32),
+    max_shift: u32,
+    threshold: f32,
+) -> Option<(i32, i32)> {
+    let img_edges = EdgeMap::new(img, threshold);
+    let reference_edges = EdgeMap::new(reference, threshold);
+
+    // Offsets in the coarse maps are in blocks, the exact one is found around the best block
+    let factor = i64::from(COARSE_FACTOR);
+    let coarse_img = img_edges.shrink(COARSE_FACTOR);
+    let coarse_reference: Vec<_> = reference_edges.shrink(COARSE_FACTOR).points().collect();
+    let coarse_around = (
+        i64::from(around.0).div_euclid(factor),
+        i64::from(around.1).div_euclid(factor),
+    );
+    let coarse = best_offset(
+        &coarse_img,
+        &coarse_reference,
+        coarse_around,
+        window(coarse_around, i64::from(max_shift.div_ceil(COARSE_FACTOR))),
+    )?;
+
+    let around = (i64::from(around.0), i64::from(around.1));
+    let allowed = window(around, i64::from(max_shift));
+    let near_coarse = window((coarse.0 * factor, coarse.1 * factor), factor);
+    let fine = (
+        intersect(&near_coarse.0, &allowed.0),
+        intersect(&near_coarse.1, &allowed.1),
+    );
+    let reference_points: Vec<_> = reference_edges.points().collect();
+    let (x, y) = best_offset(&img_edges, &reference_points, around, fine)?;
+
+    Some((x as i32, y as i32))
+}
+
+/// Offsets tried along the x and y axes
+type Window = (RangeInclusive<i64>, RangeInclusive<i64>);
+
+/// Offsets up to `radius` away from `center` in each direction
+fn window(center: (i64, i64), radius: i64) -> Window {
+    (
+        center.0 - radius..=center.0 + radius,
+        center.1 - radius..=center.1 + radius,
+    )
+}
+
+fn intersect(a: &RangeInclusive<i64>, b: &RangeInclusive<i64>) -> RangeInclusive<i64> {
+    *a.start().max(b.start())..=*a.end().min(b.end())
+}
+
+/// Offset in the window where the most reference points land on image edges,
+/// closest to `center` on a tie, `None` if no point ever does
+fn best_offset(
+    img: &EdgeMap,
+    reference: &[(u32, u32)],
+    center: (i64, i64),
+    (x_range, y_range): Window,
+) -> Option<(i64, i64)> {
+    let distance = |(x, y): (i64, i64)| (x - center.0).abs() + (y - center.1).abs();
+    let mut best: Option<((i64, i64), usize)> = None;
+
+    for dy in y_range {
+        for dx in x_range.clone() {
+            let score = reference
+                .iter()
+                .filter(|&&(x, y)| img.get(i64::from(x) + dx, i64::from(y) + dy))
+                .count();
+            let better = match best {
+                None => score > 0,
+                Some((offset, best_score)) => {
+                    score > best_score
+                        || (score == best_score && distance((dx, dy)) < distance(offset))
+                }
+            };
+            if better {
+                best = Some(((dx, dy), score));
+            }
+        }
+    }
+
+    best.map(|(offset, _)| offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgb};
+
+    /// Cards and buttons of different colors on a white page
+    fn page() -> RgbImage {
+        let mut img = RgbImage::from_pixel(200, 150, Rgb([255, 255, 255]));
+        let cards = [
+            (20, 15, 70, 40, [220, 220, 220]),
+            (30, 25, 25, 10, [40, 90, 200]),
+            (110, 20, 60, 90, [230, 230, 240]),
+            (120, 70, 40, 12, [200, 60, 60]),
+            (25, 80, 50, 50, [30, 30, 30]),
+            (140, 120, 45, 20, [90, 180, 90]),
+        ];
+        for (x, y, width, height, rgb) in cards {
+            for row in y..y + height {
+                for column in x..x + width {
+                    img.put_pixel(column, row, Rgb(rgb));
+                }
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn align_finds_where_a_crop_was_taken() {
+        let img = page();
+        let reference = img.view(40, 30, 100, 80).to_image();
+
+        assert_eq!(align(&img, &reference, (0, 0), 60, 20.0), Some((40, 30)));
+        assert_eq!(align(&img, &reference, (35, 33), 8, 20.0), Some((40, 30)));
+    }
+
+    #[test]
+    fn align_finds_negative_offsets() {
+        let img = page();
+        // The page moved 12 pixels right and 7 down in the reference
+        let mut reference = RgbImage::from_pixel(220, 170, Rgb([255, 255, 255]));
+        image::imageops::replace(&mut reference, &img, 12, 7);
+
+        assert_eq!(align(&img, &reference, (0, 0), 20, 20.0), Some((-12, -7)));
+    }
+
+    #[test]
+    fn align_keeps_to_the_allowed_shift() {
+        let img = page();
+        let reference = img.view(40, 30, 100, 80).to_image();
+
+        // The crop is 40 pixels away, out of reach
+        assert!(
+            align(&img, &reference, (0, 0), 10, 20.0)
+                .is_none_or(|(x, y)| x.abs() <= 10 && y.abs() <= 10)
+        );
+    }
+
+    #[test]
+    fn align_gives_up_without_edges() {
+        let blank = RgbImage::from_pixel(50, 50, Rgb([255, 255, 255]));
+        assert_eq!(align(&page(), &blank, (0, 0), 10, 20.0), None);
+    }
+}
@@ -0,0 +1,400 @@
+//! Metrics of lines of text, read from how much ink each row holds
+//!
+//! The background is the most common color around the measured point, and
+//! ink is every pixel differing from it by more than the threshold, the rule
+//! [`color::is_edge`] applies. The rows holding ink around the point make up
+//! the text line, which spreads sideways over the words on either side.
+//!
+//! Within the line, the count of ink pixels per row tells the glyphs apart:
+//! it drops sharply at the baseline, where all but the descenders end, and
+//! rises sharply at the top of the lowercase letters. The top of the ink is
+//! the height of the capitals and ascenders.
+
+use crate::color;
+use crate::detect::validate_coordinates;
+use crate::measure::{Limits, Measurement, Shape};
+use crate::rects::Rect;
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+
+/// Half the size of the square around the point the background is taken from,
+/// and of the rows first searched for the line
+pub const SEARCH_RADIUS: u32 = 48;
+
+/// Blank rows allowed inside a line, such as between the dot and the stem of an `i`
+const ROW_GAP: u32 = 1;
+
+/// How far below a line the next one is searched, relative to the height of its ink
+const NEXT_LINE_DISTANCE: u32 = 3;
+
+/// Share of the fullest row the ink must rise by on average at the top of the
+/// lowercase letters, below which every glyph is taken to have the same height
+const X_HEIGHT_RISE: f32 = 0.25;
+
+/// A line of text found in an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextLine {
+    /// Smallest rectangle holding all the ink of the line
+    pub ink: Rect,
+    /// First row below the bodies of the glyphs, where letters without descenders end
+    pub baseline: u32,
+    /// Height of the lowercase letters above the baseline, `None` when all the
+    /// glyphs reach the same height, as in a line of capitals
+    pub x_height: Option<u32>,
+    /// Height of the capitals and ascenders above the baseline
+    pub cap_height: u32,
+}
+
+/// Finds the line of text at the given point and the next line below it
+/// Returns `None` if the point is outside the image or no text is near it
+pub fn measure_text(img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+    let (width, height) = validate_coordinates(img, x, y)?;
+    let background = background(img, x, y);
+    let line = text_line_at(img, &background, x, y, threshold)?;
+    // A line found above this one, where ink of both runs together, is no line below
+    let below = next_line(img, &background, &line, threshold).and_then(|next| {
+        Some((
+            next.baseline.checked_sub(line.baseline)?,
+            next.ink.y.checked_sub(line.ink.bottom())?,
+        ))
+    });
+
+    let mut measurement = Measurement::at(
+        x,
+        y,
+        threshold,
+        Shape::Text {
+            baseline: line.baseline,
+            x_height: line.x_height,
+            cap_height: line.cap_height,
+            line_height: below.map(|(line_height, _)| line_height),
+            spacing: below.map(|(_, spacing)| spacing),
+        },
+    );
+    // Like the limits found by color changes, these sit on the first pixel past each edge
+    measurement.limits = Limits {
+        top: line.ink.y.saturating_sub(1),
+        bottom: line.ink.bottom().min(height - 1),
+        left: line.ink.x.saturating_sub(1),
+        right: line.ink.right().min(width - 1),
+    };
+    Some(measurement)
+}
+
+/// Finds the line of text holding ink on or right next to the row of the point
+pub fn text_line_at(
+    img: &RgbImage,
+    background: &Rgb<u8>,
+    x: u32,
+    y: u32,
+    threshold: f32,
+) -> Option<TextLine> {
+    let (width, _) = validate_coordinates(img, x, y)?;
+    let is_ink = |x: u32, y: u32| color::is_edge(background, img.get_pixel(x, y), threshold);
+
+    // The line is first searched in the columns around the point, then
+    // spread over the words on either side and searched again in those
+    let mut columns = (
+        x.saturating_sub(SEARCH_RADIUS),
+        (x + SEARCH_RADIUS).min(width),
+    );
+    let mut rows = None;
+    for _ in 0..2 {
+        let (top, bottom) = line_rows(img, background, columns, y, threshold)?;
+        rows = Some((top, bottom));
+
+        // Words are spaced by less than the height of the line, columns of text by more
+        let inked_column = |column: u32| (top..bottom).any(|row| is_ink(column, row));
+        let gap = bottom - top;
+        let nearest = (columns.0..columns.1)
+            .filter(|&column| inked_column(column))
+            .min_by_key(|&column| column.abs_diff(x))?;
+        let (mut left, mut right) = (nearest, nearest + 1);
+        let mut blank = 0;
+        while left > 0 && blank <= gap {
+            left -= 1;
+            blank = if inked_column(left) { 0 } else { blank + 1 };
+        }
+        left += blank;
+        blank = 0;
+        while right < width && blank <= gap {
+            blank = if inked_column(right) { 0 } else { blank + 1 };
+            right += 1;
+        }
+        right -= blank;
+        columns = (left, right);
+    }
+
+    let (top, bottom) = rows?;
+    let ink = row_histogram(img, background, columns, (top, bottom), threshold);
+    let (left, right) = columns;
+    Some(metrics(
+        &ink,
+        Rect {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        },
+    ))
+}
+
+/// Finds the next line of text below the given one, over the same columns
+pub fn next_line(
+    img: &RgbImage,
+    background: &Rgb<u8>,
+    line: &TextLine,
+    threshold: f32,
+) -> Option<TextLine> {
+    let (_, height) = img.dimensions();
+    let end = (line.ink.bottom() + NEXT_LINE_DISTANCE * line.ink.height).min(height);
+    let columns = (line.ink.x, line.ink.right());
+
+    let ink = row_histogram(
+        img,
+        background,
+        columns,
+        (line.ink.bottom(), end),
+        threshold,
+    );
+    let offset = ink.iter().position(|&count| count > 0)? as u32;
+    let row = line.ink.bottom() + offset;
+
+    // Any inked pixel of the row leads to the line, whose words may start elsewhere
+    let column = (line.ink.x..line.ink.right())
+        .find(|&column| color::is_edge(background, img.get_pixel(column, row), threshold))?;
+    text_line_at(img, background, column, row, threshold)
+}
+
+/// Most common color in the square around the point, clipped to the image
+pub fn background(img: &RgbImage, x: u32, y: u32) -> Rgb<u8> {
+    let (width, height) = img.dimensions();
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for row in y.saturating_sub(SEARCH_RADIUS)..(y + SEARCH_RADIUS).min(height) {
+        for column in x.saturating_sub(SEARCH_RADIUS)..(x + SEARCH_RADIUS).min(width) {
+            *counts.entry(img.get_pixel(column, row).0).or_default() += 1;
+        }
+    }
+
+    // Ties go to the lowest color value, so the result doesn't depend on the hash order
+    counts
+        .into_iter()
+        .max_by_key(|&(rgb, count)| (count, std::cmp::Reverse(rgb)))
+        .map(|(rgb, _)| Rgb(rgb))
+        .unwrap_or(Rgb([0, 0, 0]))
+}
+
+/// Rows of the line holding ink on or right next to row `y`, over the given columns
+/// Returns the first row and the first row past the line
+///
+/// Only the rows within [`SEARCH_RADIUS`] of `y` are counted at first, and
+/// twice as many each time the ink may run on past them.
+fn line_rows(
+    img: &RgbImage,
+    background: &Rgb<u8>,
+    columns: (u32, u32),
+    y: u32,
+    threshold: f32,
+) -> Option<(u32, u32)> {
+    let (_, height) = img.dimensions();
+    let mut radius = SEARCH_RADIUS;
+    loop {
+        let start = y.saturating_sub(radius);
+        let end = y.saturating_add(radius).saturating_add(1).min(height);
+        let ink = row_histogram(img, background, columns, (start, end), threshold);
+        let inked = |row: u32| {
+            row.checked_sub(start)
+                .and_then(|row| ink.get(row as usize))
+                .is_some_and(|&count| count > 0)
+        };
+        let seed = (0..=ROW_GAP + 1)
+            .flat_map(|distance| [y.checked_sub(distance), Some(y + distance)])
+            .flatten()
+            .find(|&row| inked(row))?;
+        let (top, bottom) = ink_run(&ink, seed - start);
+
+        // Ink within a gap of the edge of the rows counted may go on past it
+        let open_above = start > 0 && top <= ROW_GAP;
+        let open_below = end < height && bottom + ROW_GAP >= ink.len() as u32;
+        if !open_above && !open_below {
+            return Some((start + top, start + bottom));
+        }
+        radius = radius.saturating_mul(2);
+    }
+}
+
+/// Count of ink pixels in each row from `rows.0` to `rows.1`, over the columns
+/// from `columns.0` to `columns.1`
+fn row_histogram(
+    img: &RgbImage,
+    background: &Rgb<u8>,
+    columns: (u32, u32),
+    rows: (u32, u32),
+    threshold: f32,
+) -> Vec<u32> {
+    (rows.0..rows.1)
+        .map(|row| {
+            (columns.0..columns.1)
+                .filter(|&column| color::is_edge(background, img.get_pixel(column, row), threshold))
+                .count() as u32
+        })
+        .collect()
+}
+
+/// Rows around `seed` holding ink, allowing gaps of [`ROW_GAP`] blank rows
+/// Returns the first row and the first row past the run
+fn ink_run(ink: &[u32], seed: u32) -> (u32, u32) {
+    let inked = |row: u32| ink.get(row as usize).is_some_and(|&count| count > 0);
+
+    let mut top = seed;
+    while let Some(above) = (1..=ROW_GAP + 1)
+        .filter_map(|distance| top.checked_sub(distance))
+        .find(|&row| inked(row))
+    {
+        top = above;
+    }
+    let mut bottom = seed;
+    while let Some(below) = (1..=ROW_GAP + 1)
+        .map(|distance| bottom + distance)
+        .find(|&row| inked(row))
+    {
+        bottom = below;
+    }
+
+    (top, bottom + 1)
+}
+
+/// Reads the baseline and the heights of a line from the ink in each of its rows
+fn metrics(ink: &[u32], bounds: Rect) -> TextLine {
+    let count = |row: usize| i64::from(ink.get(row).copied().unwrap_or(0));
+    let rows = ink.len();
+
+    // The baseline is below the row the ink drops the most after, the lowest
+    // one if several do
+    let body_end = (0..rows)
+        .max_by_key(|&row| (count(row) - count(row + 1), row))
+        .map_or(rows, |row| row + 1);
+
+    // The top of the lowercase letters splits the body into sparse rows crossed
+    // by the capitals and ascenders only, and dense rows below. Comparing the
+    // average of both sides, rather than neighboring rows, keeps the bars of
+    // letters such as H or e from passing for it. Lowercase letters are much
+    // shorter than capitals, which leaves out the antialiased top of the ink
+    let mut total = vec![0; body_end + 1];
+    for row in 0..body_end {
+        total[row + 1] = total[row] + count(row);
+    }
+    let average =
+        |start: usize, end: usize| (total[end] - total[start]) as f32 / (end - start) as f32;
+    let fullest = ink.iter().copied().max().unwrap_or(0);
+    let x_top = ((body_end / 5).max(1)..body_end)
+        .map(|row| (row, average(row, body_end) - average(0, row)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .filter(|&(_, rise)| rise >= X_HEIGHT_RISE * fullest as f32)
+        .map(|(row, _)| row);
+
+    let baseline = bounds.y + body_end as u32;
+    TextLine {
+        ink: bounds,
+        baseline,
+        x_height: x_top.map(|row| (body_end - row) as u32),
+        cap_height: body_end as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAPER: Rgb<u8> = Rgb([255, 255, 255]);
+    const INK: Rgb<u8> = Rgb([0, 0, 0]);
+
+    fn fill(img: &mut RgbImage, x: u32, y: u32, width: u32, height: u32) {
+        for row in y..y + height {
+            for column in x..x + width {
+                img.put_pixel(column, row, INK);
+            }
+        }
+    }
+
+    /// Words of letters from `left` to `right`, their bodies `x_height` tall,
+    /// and every other one an ascender reaching up to `top`
+    fn word(img: &mut RgbImage, (left, right): (u32, u32), top: u32, x_height: u32, baseline: u32) {
+        for (index, x) in (left..right).step_by(8).enumerate() {
+            fill(img, x, baseline - x_height, 6, x_height);
+            if index % 2 == 0 {
+                fill(img, x, top, 2, baseline - top);
+            }
+        }
+    }
+
+    fn text(img: &RgbImage, x: u32, y: u32) -> Shape {
+        measure_text(img, x, y, crate::DEFAULT_THRESHOLD)
+            .unwrap()
+            .shape
+    }
+
+    #[test]
+    fn stacked_lines_give_the_line_height_and_gap() {
+        let mut img = RgbImage::from_pixel(200, 120, PAPER);
+        word(&mut img, (10, 90), 20, 10, 40);
+        word(&mut img, (10, 90), 60, 10, 80);
+
+        assert_eq!(
+            text(&img, 30, 35),
+            Shape::Text {
+                baseline: 40,
+                x_height: Some(10),
+                cap_height: 20,
+                line_height: Some(40),
+                spacing: Some(20),
+            }
+        );
+    }
+
+    #[test]
+    fn a_neighbouring_word_running_past_the_line_is_no_line_below() {
+        let mut img = RgbImage::from_pixel(240, 120, PAPER);
+        word(&mut img, (10, 50), 20, 10, 40);
+        // Runs past the columns first searched, and makes the line taller
+        word(&mut img, (60, 90), 20, 10, 40);
+        fill(&mut img, 90, 20, 30, 40);
+        // Only reached over the wider gap of the taller line, and runs below it
+        fill(&mut img, 150, 20, 40, 60);
+
+        let measurement = measure_text(&img, 30, 35, crate::DEFAULT_THRESHOLD).unwrap();
+        assert_eq!(measurement.limits.bottom, 60);
+        assert_eq!(measurement.limits.right, 190);
+        assert!(matches!(
+            measurement.shape,
+            Shape::Text {
+                line_height: None,
+                spacing: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn lines_taller_than_the_search_radius_are_found_whole() {
+        let mut img = RgbImage::from_pixel(300, 400, PAPER);
+        let top = 100;
+        let baseline = top + 3 * SEARCH_RADIUS;
+        word(&mut img, (10, 200), top, 2 * SEARCH_RADIUS, baseline);
+
+        // The point is near the top of the ascenders, far from the baseline
+        let measurement = measure_text(&img, 10, top + 2, crate::DEFAULT_THRESHOLD).unwrap();
+        assert_eq!(measurement.limits.top, top - 1);
+        assert_eq!(measurement.limits.bottom, baseline);
+        assert_eq!(
+            measurement.shape,
+            Shape::Text {
+                baseline,
+                x_height: Some(2 * SEARCH_RADIUS),
+                cap_height: 3 * SEARCH_RADIUS,
+                line_height: None,
+                spacing: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Units measurements are reported in
+//!
+//! Sizes are measured in screenshot pixels, which are the physical pixels of
+//! the output. [`Units`] converts them using what is known about that output:
+//! its scale factor for logical units, its physical size for lengths in mm and
+//! inches, and the size of the screen or of the enclosing element for percents.
+
+use crate::measure::Measurement;
+use std::fmt;
+use std::str::FromStr;
+
+/// Millimeters in an inch
+const MM_PER_INCH: f64 = 25.4;
+
+/// Points per logical pixel, following CSS where 1in = 96px = 72pt
+const PT_PER_DP: f64 = 72.0 / 96.0;
+
+/// Unit used when showing a measured length to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unit {
+    /// Raw image pixels
+    #[default]
+    Px,
+    /// Density independent pixels, image pixels divided by the scale factor
+    Dp,
+    /// Typographic points, 3/4 of a dp like in CSS
+    Pt,
+    /// Multiples of the base font size, in dp
+    Rem,
+    /// Physical millimeters on the monitor
+    Mm,
+    /// Physical inches on the monitor
+    In,
+    /// Percent of the enclosing element or of the screen
+    Percent,
+}
+
+impl Unit {
+    /// All supported units, in the order they are cycled through
+    pub const ALL: [Unit; 7] = [
+        Unit::Px,
+        Unit::Dp,
+        Unit::Pt,
+        Unit::Rem,
+        Unit::Mm,
+        Unit::In,
+        Unit::Percent,
+    ];
+
+    /// Short name used in configuration files and next to values
+    pub fn label(self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Dp => "dp",
+            Unit::Pt => "pt",
+            Unit::Rem => "rem",
+            Unit::Mm => "mm",
+            Unit::In => "in",
+            Unit::Percent => "%",
+        }
+    }
+
+    /// The unit following this one in [`Unit::ALL`], wrapping around
+    pub fn next(self) -> Unit {
+        let index = Unit::ALL.iter().position(|unit| *unit == self).unwrap_or(0);
+        Unit::ALL[(index + 1) % Unit::ALL.len()]
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Error returned when parsing an unknown unit name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownUnit(pub String);
+
+impl fmt::Display for UnknownUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let known: Vec<&str> = Unit::ALL.iter().map(|unit| unit.label()).collect();
+        write!(
+            f,
+            "unknown unit \"{}\", expected one of: {}",
+            self.0,
+            known.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownUnit {}
+
+impl FromStr for Unit {
+    type Err = UnknownUnit;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("percent") {
+            return Ok(Unit::Percent);
+        }
+
+        Unit::ALL
+            .into_iter()
+            .find(|unit| unit.label().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownUnit(s.to_string()))
+    }
+}
+
+/// What percentages are relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PercentOf {
+    /// The element enclosing the measured one
+    #[default]
+    Container,
+    /// The whole screenshot
+    Screen,
+}
+
+impl FromStr for PercentOf {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "container" => Ok(PercentOf::Container),
+            "screen" => Ok(PercentOf::Screen),
+            _ => Err(format!(
+                "unknown percent reference \"{s}\", expected container or screen"
+            )),
+        }
+    }
+}
+
+/// Direction a length was measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// What is known about the output the screenshot was taken on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitContext {
+    /// Image pixels per logical pixel
+    pub scale_factor: f64,
+    /// Font size rem values are relative to, in dp
+    pub base_font_size: f64,
+    /// Image pixels per millimeter horizontally and vertically, if the
+    /// monitor reports its physical size
+    pub pixels_per_mm: Option<(f64, f64)>,
+    /// Size of the screenshot in image pixels
+    pub screen_size: (u32, u32),
+    pub percent_of: PercentOf,
+}
+
+impl Default for UnitContext {
+    fn default() -> Self {
+        UnitContext {
+            scale_factor: 1.0,
+            base_font_size: 16.0,
+            pixels_per_mm: None,
+            screen_size: (0, 0),
+            percent_of: PercentOf::default(),
+        }
+    }
+}
+
+/// Converts and formats measured lengths in the selected unit
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Units {
+    pub unit: Unit,
+    pub context: UnitContext,
+}
+
+impl Units {
+    pub fn new(unit: Unit, context: UnitContext) -> Self {
+        Units { unit, context }
+    }
+
+    /// Converts a length in image pixels, `reference` being the length
+    /// percentages are relative to
+    /// Returns `None` when the unit can't be computed, e.g. mm without a physical size
+    pub fn convert(&self, pixels: u32, axis: Axis, reference: u32) -> Option<f64> {
+        self.convert_length(f64::from(pixels), axis, reference)
+    }
+
+    /// Converts a fractional length in image pixels, like the radius of a fitted circle
+    pub fn convert_length(&self, pixels: f64, axis: Axis, reference: u32) -> Option<f64> {
+        let dp = pixels / self.context.scale_factor;
+
+        match self.unit {
+            Unit::Px => Some(pixels),
+            Unit::Dp => Some(dp),
+            Unit::Pt => Some(dp * PT_PER_DP),
+            Unit::Rem => Some(dp / self.context.base_font_size),
+            Unit::Mm | Unit::In => {
+                let (per_mm_x, per_mm_y) = self.context.pixels_per_mm?;
+                let per_mm = match axis {
+                    Axis::Horizontal => per_mm_x,
+                    Axis::Vertical => per_mm_y,
+                };
+                let mm = pixels / per_mm;
+                Some(if self.unit == Unit::In {
+                    mm / MM_PER_INCH
+                } else {
+                    mm
+                })
+            }
+            Unit::Percent => (reference > 0).then(|| pixels * 100.0 / f64::from(reference)),
+        }
+    }
+
+    /// Formats a length in image pixels without the unit label
+    pub fn format(&self, pixels: u32, axis: Axis, reference: u32) -> String {
+        self.format_length(f64::from(pixels), axis, reference)
+    }
+
+    /// Formats a fractional length in image pixels without the unit label
+    /// Whole lengths in pixels are shown without decimals
+    pub fn format_length(&self, pixels: f64, axis: Axis, reference: u32) -> String {
+        match self.convert_length(pixels, axis, reference) {
+            None => "?".to_string(),
+            Some(value) => {
+                let decimals = match self.unit {
+                    Unit::Rem | Unit::In => 3,
+                    _ => 1,
+                };
+                let text = format!("{value:.decimals$}");
+                text.trim_end_matches('0').trim_end_matches('.').to_string()
+            }
+        }
+    }
+
+    /// Formatted width of a measurement
+    pub fn width(&self, measurement: &Measurement) -> String {
+        let reference = match self.context.percent_of {
+            PercentOf::Container => measurement.container.width(),
+            PercentOf::Screen => self.context.screen_size.0,
+        };
+        self.format(measurement.limits.width(), Axis::Horizontal, reference)
+    }
+
+    /// Formatted height of a measurement
+    pub fn height(&self, measurement: &Measurement) -> String {
+        let reference = match self.context.percent_of {
+            PercentOf::Container => measurement.container.height(),
+            PercentOf::Screen => self.context.screen_size.1,
+        };
+        self.format(measurement.limits.height(), Axis::Vertical, reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measure::{Limits, Shape};
+
+    /// A HiDPI output at 1.5x, 4 px/mm wide and 5 px/mm tall
+    fn context() -> UnitContext {
+        UnitContext {
+            scale_factor: 1.5,
+            base_font_size: 16.0,
+            pixels_per_mm: Some((4.0, 5.0)),
+            screen_size: (3000, 1800),
+            percent_of: PercentOf::Container,
+        }
+    }
+
+    #[test]
+    fn converts_to_every_unit() {
+        let cases = [
+            (Unit::Px, 150.0, Axis::Horizontal, 0, Some(150.0)),
+            (Unit::Dp, 150.0, Axis::Horizontal, 0, Some(100.0)),
+            (Unit::Pt, 150.0, Axis::Vertical, 0, Some(75.0)),
+            (Unit::Rem, 150.0, Axis::Horizontal, 0, Some(6.25)),
+            (Unit::Mm, 150.0, Axis::Horizontal, 0, Some(37.5)),
+            (Unit::Mm, 150.0, Axis::Vertical, 0, Some(30.0)),
+            (Unit::In, 254.0, Axis::Horizontal, 0, Some(2.5)),
+            (Unit::In, 254.0, Axis::Vertical, 0, Some(2.0)),
+            (Unit::Percent, 150.0, Axis::Horizontal, 600, Some(25.0)),
+            (Unit::Percent, 150.0, Axis::Vertical, 0, None),
+        ];
+
+        for (unit, pixels, axis, reference, expected) in cases {
+            let value = Units::new(unit, context()).convert_length(pixels, axis, reference);
+            match (value, expected) {
+                (Some(value), Some(expected)) => {
+                    assert!((value - expected).abs() < 1e-9, "{unit}: {value}");
+                }
+                _ => assert_eq!(value, expected, "{unit}"),
+            }
+        }
+    }
+
+    #[test]
+    fn physical_units_need_the_monitor_size() {
+        let context = UnitContext {
+            pixels_per_mm: None,
+            ..context()
+        };
+
+        for unit in [Unit::Mm, Unit::In] {
+            let units = Units::new(unit, context);
+            assert_eq!(units.convert(150, Axis::Horizontal, 600), None);
+            assert_eq!(units.format(150, Axis::Horizontal, 600), "?");
+        }
+    }
+
+    #[test]
+    fn formats_with_trimmed_decimals() {
+        let cases = [
+            (Unit::Px, 150.0, 0, "150"),
+            (Unit::Px, 12.34, 0, "12.3"),
+            (Unit::Dp, 100.0, 0, "66.7"),
+            (Unit::Pt, 150.0, 0, "75"),
+            (Unit::Rem, 150.0, 0, "6.25"),
+            (Unit::Rem, 100.0, 0, "4.167"),
+            (Unit::Mm, 150.0, 0, "37.5"),
+            (Unit::In, 100.0, 0, "0.984"),
+            (Unit::Percent, 100.0, 300, "33.3"),
+            (Unit::Percent, 100.0, 0, "?"),
+        ];
+
+        for (unit, pixels, reference, expected) in cases {
+            let units = Units::new(unit, context());
+            assert_eq!(
+                units.format_length(pixels, Axis::Horizontal, reference),
+                expected,
+                "{unit}"
+            );
+        }
+    }
+
+    #[test]
+    fn fractional_scale_factor() {
+        let context = UnitContext {
+            scale_factor: 1.25,
+            ..context()
+        };
+
+        assert_eq!(
+            Units::new(Unit::Dp, context).format(10, Axis::Horizontal, 0),
+            "8"
+        );
+        assert_eq!(
+            Units::new(Unit::Dp, context).format(11, Axis::Horizontal, 0),
+            "8.8"
+        );
+        assert_eq!(
+            Units::new(Unit::Pt, context).format(10, Axis::Horizontal, 0),
+            "6"
+        );
+        assert_eq!(
+            Units::new(Unit::Rem, context).format(30, Axis::Horizontal, 0),
+            "1.5"
+        );
+    }
+
+    #[test]
+    fn percent_of_container_or_screen() {
+        // 150x90 element in a 600x360 container, on a 3000x1800 screen
+        let mut measurement = Measurement::at(100, 50, 0.0, Shape::Extent);
+        measurement.limits = Limits {
+            top: 50,
+            bottom: 139,
+            left: 100,
+            right: 249,
+        };
+        measurement.container = Limits {
+            top: 0,
+            bottom: 359,
+            left: 0,
+            right: 599,
+        };
+
+        let container = Units::new(Unit::Percent, context());
+        assert_eq!(container.width(&measurement), "25");
+        assert_eq!(container.height(&measurement), "25");
+
+        let screen = Units::new(
+            Unit::Percent,
+            UnitContext {
+                percent_of: PercentOf::Screen,
+                ..context()
+            },
+        );
+        assert_eq!(screen.width(&measurement), "5");
+        assert_eq!(screen.height(&measurement), "5");
+    }
+}
@@ -0,0 +1,640 @@
+//! Element bounds from the accessibility tree (AT-SPI)
+//!
+//! Pixel edges can't tell where a widget without a visible border ends, but
+//! applications report the extents of their widgets to assistive technologies.
+//! The element under the cursor is found by asking each application for the
+//! accessible object at that point, then asking that object again until it
+//! has no smaller child there. Everything runs on the accessibility bus, found
+//! from `AT_SPI_BUS_ADDRESS` or by asking `org.a11y.Bus` on the session bus.
+//!
+//! Points and extents are exchanged in screen coordinates, which Wayland does
+//! not give to clients. Most applications running natively on Wayland answer
+//! with positions relative to their own window, or with zero, so their
+//! elements show up offset from the pixels. Only X11 applications and those
+//! that know their window position report where they really are.
+
+use gtk4::{gio, glib};
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::rc::Rc;
+use std::time::Duration;
+
+const REGISTRY: &str = "org.a11y.atspi.Registry";
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+/// Path AT-SPI answers with when there is no object
+const NULL_PATH: &str = "/org/a11y/atspi/null";
+const ACCESSIBLE: &str = "org.a11y.atspi.Accessible";
+const COMPONENT: &str = "org.a11y.atspi.Component";
+/// Coordinates relative to the whole screen
+const COORD_TYPE_SCREEN: u32 = 0;
+/// Bit of the state set telling the window has the focus
+const STATE_ACTIVE: u32 = 1 << 1;
+/// Deepest the lookup goes, in case an application keeps answering with children
+const MAX_DEPTH: usize = 64;
+const TIMEOUT_MS: i32 = 500;
+/// Time the cursor has to rest before the element under it is looked up
+const LOOK_UP_DELAY: Duration = Duration::from_millis(100);
+
+/// Element found in the accessibility tree, extents in logical screen pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accessible {
+    pub role: String,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Object on the accessibility bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reference {
+    bus: String,
+    path: String,
+}
+
+impl Reference {
+    fn from_variant(variant: &glib::Variant) -> Option<Self> {
+        let (bus, path) = variant.get::<(String, glib::variant::ObjectPath)>()?;
+        Some(Reference {
+            bus,
+            path: path.to_string(),
+        })
+    }
+
+    fn is_null(&self) -> bool {
+        self.path == NULL_PATH
+    }
+}
+
+/// Client of the AT-SPI registry
+pub struct AtSpi {
+    connection: gio::DBusConnection,
+}
+
+impl AtSpi {
+    /// Uses an existing connection to the accessibility bus, such as a private test bus
+    pub fn with_connection(connection: gio::DBusConnection) -> Self {
+        AtSpi { connection }
+    }
+
+    /// Connects to the accessibility bus of the session
+    pub async fn connect() -> Result<Self, glib::Error> {
+        let address = match env::var("AT_SPI_BUS_ADDRESS") {
+            Ok(address) => address,
+            Err(_) => {
+                let session = gio::bus_get_future(gio::BusType::Session).await?;
+                let reply = session
+                    .call_future(
+                        Some("org.a11y.Bus"),
+                        "/org/a11y/bus",
+                        "org.a11y.Bus",
+                        "GetAddress",
+                        None,
+                        Some(glib::VariantTy::new("(s)").unwrap()),
+                        gio::DBusCallFlags::NONE,
+                        TIMEOUT_MS,
+                    )
+                    .await?;
+                reply.child_value(0).get::<String>().unwrap_or_default()
+            }
+        };
+
+        let connection = gio::DBusConnection::for_address_future(
+            &address,
+            gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None::<&gio::DBusAuthObserver>,
+        )
+        .await?;
+        Ok(AtSpi::with_connection(connection))
+    }
+
+    async fn call(
+        &self,
+        object: &Reference,
+        interface: &str,
+        method: &str,
+        parameters: Option<glib::Variant>,
+        reply_type: &str,
+    ) -> Result<glib::Variant, glib::Error> {
+        self.connection
+            .call_future(
+                Some(&object.bus),
+                &object.path,
+                interface,
+                method,
+                parameters.as_ref(),
+                glib::VariantTy::new(reply_type).ok(),
+                gio::DBusCallFlags::NONE,
+                TIMEOUT_MS,
+            )
+            .await
+    }
+
+    async fn children(&self, object: &Reference) -> Result<Vec<Reference>, glib::Error> {
+        let reply = self
+            .call(object, ACCESSIBLE, "GetChildren", None, "(a(so))")
+            .await?;
+        Ok(reply
+            .child_value(0)
+            .iter()
+            .filter_map(|child| Reference::from_variant(&child))
+            .collect())
+    }
+
+    async fn name(&self, object: &Reference) -> Result<String, glib::Error> {
+        let reply = self
+            .call(
+                object,
+                "org.freedesktop.DBus.Properties",
+                "Get",
+                Some((ACCESSIBLE, "Name").into()),
+                "(v)",
+            )
+            .await?;
+        Ok(reply
+            .child_value(0)
+            .as_variant()
+            .and_then(|name| name.get::<String>())
+            .unwrap_or_default())
+    }
+
+    async fn is_active(&self, object: &Reference) -> Result<bool, glib::Error> {
+        let reply = self
+            .call(object, ACCESSIBLE, "GetState", None, "(au)")
+            .await?;
+        let states = reply.child_value(0).get::<Vec<u32>>().unwrap_or_default();
+        Ok(states.first().is_some_and(|low| low & STATE_ACTIVE != 0))
+    }
+
+    async fn contains(&self, object: &Reference, x: i32, y: i32) -> Result<bool, glib::Error> {
+        let reply = self
+            .call(
+                object,
+                COMPONENT,
+                "Contains",
+                Some((x, y, COORD_TYPE_SCREEN).into()),
+                "(b)",
+            )
+            .await?;
+        Ok(reply.child_value(0).get::<bool>().unwrap_or(false))
+    }
+
+    async fn child_at(
+        &self,
+        object: &Reference,
+        x: i32,
+        y: i32,
+    ) -> Result<Option<Reference>, glib::Error> {
+        let reply = self
+            .call(
+                object,
+                COMPONENT,
+                "GetAccessibleAtPoint",
+                Some((x, y, COORD_TYPE_SCREEN).into()),
+                "((so))",
+            )
+            .await?;
+        Ok(Reference::from_variant(&reply.child_value(0)).filter(|child| !child.is_null()))
+    }
+
+    /// Finds the window under the point, preferring the focused one when windows overlap
+    async fn window_at(
+        &self,
+        x: i32,
+        y: i32,
+        own_name: &str,
+    ) -> Result<Option<Reference>, glib::Error> {
+        let root = Reference {
+            bus: REGISTRY.to_string(),
+            path: ROOT_PATH.to_string(),
+        };
+
+        let mut found = None;
+        for app in self.children(&root).await? {
+            // The overlay covers the whole screen and would always be found first
+            if self.name(&app).await.is_ok_and(|name| name == own_name) {
+                continue;
+            }
+            let Ok(windows) = self.children(&app).await else {
+                continue;
+            };
+            for window in windows {
+                if !self.contains(&window, x, y).await.unwrap_or(false) {
+                    continue;
+                }
+                if self.is_active(&window).await.unwrap_or(false) {
+                    return Ok(Some(window));
+                }
+                found.get_or_insert(window);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Finds the smallest element under the given point, in logical screen pixels
+    /// Elements of the application named `own_name` are skipped
+    pub async fn element_at(
+        &self,
+        x: i32,
+        y: i32,
+        own_name: &str,
+    ) -> Result<Option<Accessible>, glib::Error> {
+        let Some(mut element) = self.window_at(x, y, own_name).await? else {
+            return Ok(None);
+        };
+        for _ in 0..MAX_DEPTH {
+            match self.child_at(&element, x, y).await {
+                Ok(Some(child)) if child != element => element = child,
+                _ => break,
+            }
+        }
+
+        let extents = self
+            .call(
+                &element,
+                COMPONENT,
+                "GetExtents",
+                Some((COORD_TYPE_SCREEN,).into()),
+                "((iiii))",
+            )
+            .await?;
+        let (x, y, width, height) = extents
+            .child_value(0)
+            .get::<(i32, i32, i32, i32)>()
+            .unwrap_or_default();
+        let role = self
+            .call(&element, ACCESSIBLE, "GetRoleName", None, "(s)")
+            .await?
+            .child_value(0)
+            .get::<String>()
+            .unwrap_or_default();
+
+        Ok(Some(Accessible {
+            role,
+            name: self.name(&element).await.unwrap_or_default(),
+            x,
+            y,
+            width,
+            height,
+        }))
+    }
+}
+
+/// Looks up the element under the cursor in the background
+///
+/// Lookups take a few round trips on the bus, so they only start once the
+/// cursor rests for [`LOOK_UP_DELAY`]. A newer lookup aborts the one running,
+/// and only the answer to the latest one is kept. The bus is connected the
+/// first time the inspector is enabled.
+pub struct Inspector {
+    client: RefCell<Option<Rc<AtSpi>>>,
+    enabled: Cell<bool>,
+    /// Number of the latest lookup
+    generation: Cell<u64>,
+    /// Lookup waiting for the cursor to rest
+    pending: RefCell<Option<glib::SourceId>>,
+    /// Lookup talking to the bus
+    running: RefCell<Option<glib::JoinHandle<()>>>,
+    on_found: Box<dyn Fn(Option<Accessible>)>,
+}
+
+impl Inspector {
+    /// `on_found` receives the element of the latest lookup, or `None` once disabled
+    pub fn new(on_found: impl Fn(Option<Accessible>) + 'static) -> Rc<Self> {
+        Rc::new(Inspector {
+            client: RefCell::new(None),
+            enabled: Cell::new(false),
+            generation: Cell::new(0),
+            pending: RefCell::new(None),
+            running: RefCell::new(None),
+            on_found: Box::new(on_found),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Turns the lookups on or off
+    pub fn toggle(self: &Rc<Self>) {
+        let enabled = !self.enabled.get();
+        self.enabled.set(enabled);
+        self.cancel();
+        if !enabled {
+            (self.on_found)(None);
+            return;
+        }
+
+        if self.client.borrow().is_none() {
+            let inspector = self.clone();
+            glib::spawn_future_local(async move {
+                match AtSpi::connect().await {
+                    Ok(client) => *inspector.client.borrow_mut() = Some(Rc::new(client)),
+                    Err(err) => eprintln!("Error connecting to the accessibility bus: {err}"),
+                }
+            });
+        }
+    }
+
+    /// Looks up the element at the given point, in logical screen pixels,
+    /// once the cursor stays there
+    pub fn look_up(self: &Rc<Self>, x: i32, y: i32) {
+        if !self.enabled.get() || self.client.borrow().is_none() {
+            return;
+        }
+
+        self.cancel();
+        let inspector = Rc::downgrade(self);
+        let source = glib::timeout_add_local_once(LOOK_UP_DELAY, move || {
+            let Some(inspector) = inspector.upgrade() else {
+                return;
+            };
+            // The source is gone once it ran and must not be removed again
+            inspector.pending.take();
+            inspector.run(x, y);
+        });
+        *self.pending.borrow_mut() = Some(source);
+    }
+
+    /// Drops the lookup waiting to start and aborts the one running
+    /// Answers still on their way are never used
+    fn cancel(&self) {
+        self.generation.set(self.generation.get() + 1);
+        if let Some(source) = self.pending.take() {
+            source.remove();
+        }
+        if let Some(task) = self.running.take() {
+            task.abort();
+        }
+    }
+
+    fn run(self: &Rc<Self>, x: i32, y: i32) {
+        let Some(client) = self.client.borrow().clone() else {
+            return;
+        };
+        let generation = self.generation.get();
+
+        let inspector = self.clone();
+        let task = glib::spawn_future_local(async move {
+            let element = client
+                .element_at(x, y, &glib::prgname().unwrap_or_default())
+                .await
+                .unwrap_or_else(|err| {
+                    eprintln!("Error querying the accessibility tree: {err}");
+                    None
+                });
+            if inspector.generation.get() == generation {
+                (inspector.on_found)(element);
+            }
+        });
+        *self.running.borrow_mut() = Some(task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::private_bus;
+    use glib::variant::{ObjectPath, ToVariant};
+    use std::collections::HashMap;
+
+    const INTERFACES: &str = r#"
+        <node>
+          <interface name="org.a11y.atspi.Accessible">
+            <method name="GetChildren"><arg type="a(so)" direction="out"/></method>
+            <method name="GetState"><arg type="au" direction="out"/></method>
+            <method name="GetRoleName"><arg type="s" direction="out"/></method>
+            <property name="Name" type="s" access="read"/>
+          </interface>
+          <interface name="org.a11y.atspi.Component">
+            <method name="Contains">
+              <arg type="i" direction="in"/>
+              <arg type="i" direction="in"/>
+              <arg type="u" direction="in"/>
+              <arg type="b" direction="out"/>
+            </method>
+            <method name="GetAccessibleAtPoint">
+              <arg type="i" direction="in"/>
+              <arg type="i" direction="in"/>
+              <arg type="u" direction="in"/>
+              <arg type="(so)" direction="out"/>
+            </method>
+            <method name="GetExtents">
+              <arg type="u" direction="in"/>
+              <arg type="(iiii)" direction="out"/>
+            </method>
+          </interface>
+        </node>
+    "#;
+
+    /// Object of the mock registry, children given by path
+    struct MockObject {
+        name: &'static str,
+        role: &'static str,
+        extents: (i32, i32, i32, i32),
+        active: bool,
+        children: Vec<&'static str>,
+    }
+
+    impl MockObject {
+        fn new(name: &'static str, role: &'static str, extents: (i32, i32, i32, i32)) -> Self {
+            MockObject {
+                name,
+                role,
+                extents,
+                active: false,
+                children: Vec::new(),
+            }
+        }
+
+        fn active(mut self) -> Self {
+            self.active = true;
+            self
+        }
+
+        fn children(mut self, children: &[&'static str]) -> Self {
+            self.children = children.to_vec();
+            self
+        }
+
+        fn contains(&self, x: i32, y: i32) -> bool {
+            let (left, top, width, height) = self.extents;
+            (left..left + width).contains(&x) && (top..top + height).contains(&y)
+        }
+    }
+
+    /// Two applications, rustyruler's overlay covering the screen and an app
+    /// with a focused window in front of another, holding a button
+    fn mock_objects() -> HashMap<&'static str, MockObject> {
+        let screen = (0, 0, 1920, 1080);
+        HashMap::from([
+            (
+                ROOT_PATH,
+                MockObject::new("main", "desktop frame", screen).children(&["/overlay", "/editor"]),
+            ),
+            (
+                "/overlay",
+                MockObject::new("rustyruler", "application", screen).children(&["/overlay/window"]),
+            ),
+            (
+                "/overlay/window",
+                MockObject::new("", "window", screen).active(),
+            ),
+            (
+                "/editor",
+                MockObject::new("editor", "application", screen)
+                    .children(&["/editor/background", "/editor/dialog"]),
+            ),
+            (
+                "/editor/background",
+                MockObject::new("Untitled", "frame", screen),
+            ),
+            (
+                "/editor/dialog",
+                MockObject::new("Save", "dialog", (600, 300, 400, 200))
+                    .active()
+                    .children(&["/editor/dialog/ok"]),
+            ),
+            (
+                "/editor/dialog/ok",
+                MockObject::new("OK", "push button", (880, 440, 80, 32)),
+            ),
+        ])
+    }
+
+    /// Serves the mock objects under the registry name
+    fn serve_registry(connection: &gio::DBusConnection) {
+        let reply = connection
+            .call_sync(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "RequestName",
+                Some(&(REGISTRY, 4u32).to_variant()),
+                None,
+                gio::DBusCallFlags::NONE,
+                TIMEOUT_MS,
+                gio::Cancellable::NONE,
+            )
+            .unwrap();
+        // 1 is DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER
+        assert_eq!(reply.child_value(0).get::<u32>(), Some(1));
+
+        let owner = connection.unique_name().unwrap().to_string();
+        let reference = move |path: &str| (owner.clone(), ObjectPath::try_from(path).unwrap());
+
+        let objects = Rc::new(mock_objects());
+        let node = gio::DBusNodeInfo::for_xml(INTERFACES).unwrap();
+        for path in objects.keys() {
+            for interface in [ACCESSIBLE, COMPONENT] {
+                let info = node.lookup_interface(interface).unwrap();
+                let methods = objects.clone();
+                let properties = objects.clone();
+                let reference = reference.clone();
+                connection
+                    .register_object(path, &info)
+                    .method_call(move |_, _, path, _, method, parameters, invocation| {
+                        let object = &methods[path];
+                        let point = || parameters.get::<(i32, i32, u32)>().unwrap();
+                        let reply = match method {
+                            "GetChildren" => {
+                                let children: Vec<_> = object
+                                    .children
+                                    .iter()
+                                    .map(|child| reference(child))
+                                    .collect();
+                                (children,).to_variant()
+                            }
+                            "GetState" => {
+                                let low = if object.active { STATE_ACTIVE } else { 0 };
+                                (vec![low, 0u32],).to_variant()
+                            }
+                            "GetRoleName" => (object.role,).to_variant(),
+                            "Contains" => {
+                                let (x, y, _) = point();
+                                (object.contains(x, y),).to_variant()
+                            }
+                            "GetAccessibleAtPoint" => {
+                                let (x, y, _) = point();
+                                let child = object
+                                    .children
+                                    .iter()
+                                    .find(|child| methods[*child].contains(x, y))
+                                    .copied()
+                                    .unwrap_or(NULL_PATH);
+                                (reference(child),).to_variant()
+                            }
+                            "GetExtents" => (object.extents,).to_variant(),
+                            _ => unreachable!("{method} is not in the interface"),
+                        };
+                        invocation.return_value(Some(&reply));
+                    })
+                    .property(move |_, _, path, _, _| properties[path].name.to_variant())
+                    .build()
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn element_at_finds_the_smallest_element_in_the_focused_window() {
+        let (_guard, bus) = private_bus();
+        let address = bus.bus_address().unwrap();
+        let connect = || {
+            gio::DBusConnection::for_address_sync(
+                &address,
+                gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                    | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+                None,
+                gio::Cancellable::NONE,
+            )
+            .unwrap()
+        };
+
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                let registry = connect();
+                serve_registry(&registry);
+                let client = AtSpi::with_connection(connect());
+
+                let button = context
+                    .block_on(client.element_at(900, 450, "rustyruler"))
+                    .unwrap();
+                assert_eq!(
+                    button,
+                    Some(Accessible {
+                        role: "push button".to_string(),
+                        name: "OK".to_string(),
+                        x: 880,
+                        y: 440,
+                        width: 80,
+                        height: 32,
+                    })
+                );
+
+                // Outside the dialog only the window behind it is left
+                let background = context
+                    .block_on(client.element_at(100, 100, "rustyruler"))
+                    .unwrap();
+                assert_eq!(
+                    background.map(|element| element.name),
+                    Some("Untitled".to_string())
+                );
+
+                // Without skipping itself the overlay, which has the focus, would win
+                let overlay = context
+                    .block_on(client.element_at(100, 100, "someone-else"))
+                    .unwrap();
+                assert_eq!(
+                    overlay.map(|element| element.role),
+                    Some("window".to_string())
+                );
+            })
+            .unwrap();
+
+        bus.stop();
+    }
+}
@@ -0,0 +1,457 @@
+//! Notes drawn over the capture: text labels, arrows and highlighted areas
+//!
+//! Annotations are placed with the annotation tools, in image pixels, so they
+//! keep their place and size however the capture is scaled, and end up in the
+//! exports and sessions as they are shown. Any of them can be selected to be
+//! moved or deleted, the ends of arrows and the corners of highlights dragged,
+//! and the text of labels edited.
+
+use crate::config::AnnotationStyle;
+use gtk4::{Box, Entry, EventControllerKey, Label, cairo, gdk::Key, glib::Propagation, prelude::*};
+use rustyruler_core::Point;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+/// Length of the arrow head relative to the line width
+const HEAD_LENGTH: f64 = 5.0;
+
+/// Padding around the text of a label relative to the line width
+const TEXT_PADDING: f64 = 2.0;
+
+/// Radius of the handles on the ends of the selected annotation, in screen pixels
+const HANDLE_RADIUS: f64 = 5.0;
+
+/// What an annotation tool places
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Text,
+    Arrow,
+    Highlight,
+}
+
+/// A note drawn over the capture, in image coordinates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Annotation {
+    /// Label whose top left corner is at `at`
+    Text { at: Point, text: String },
+    /// Arrow pointing from `from` to `to`
+    Arrow { from: Point, to: Point },
+    /// Translucent rectangle between two opposite corners
+    Highlight { from: Point, to: Point },
+}
+
+/// Part of an annotation grabbed by a press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    /// The whole annotation, which moves
+    Whole,
+    /// The start of an arrow or the first corner of a highlight
+    From,
+    /// The tip of an arrow or the second corner of a highlight
+    To,
+}
+
+impl Annotation {
+    /// Starts an annotation of the given kind at a point, empty until dragged or typed
+    pub fn new(kind: Kind, at: Point) -> Self {
+        match kind {
+            Kind::Text => Annotation::Text {
+                at,
+                text: String::new(),
+            },
+            Kind::Arrow => Annotation::Arrow { from: at, to: at },
+            Kind::Highlight => Annotation::Highlight { from: at, to: at },
+        }
+    }
+
+    /// Whether there is nothing to show, a label without text or a zero length arrow
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Annotation::Text { text, .. } => text.trim().is_empty(),
+            Annotation::Arrow { from, to } => from.distance(to) < 1.0,
+            Annotation::Highlight { from, to } => {
+                (from.x - to.x).abs() < 1.0 || (from.y - to.y).abs() < 1.0
+            }
+        }
+    }
+
+    /// Moves the grabbed part to `position`, given where the press started
+    /// and where the annotation was then
+    pub fn drag(&mut self, original: &Annotation, part: Part, start: Point, position: Point) {
+        let (dx, dy) = (position.x - start.x, position.y - start.y);
+        let shift = |point: &Point| Point::new(point.x + dx, point.y + dy);
+
+        match (self, original, part) {
+            (Annotation::Text { at, .. }, Annotation::Text { at: original, .. }, _) => {
+                *at = shift(original);
+            }
+            (
+                Annotation::Arrow { from, to } | Annotation::Highlight { from, to },
+                Annotation::Arrow {
+                    from: original_from,
+                    to: original_to,
+                }
+                | Annotation::Highlight {
+                    from: original_from,
+                    to: original_to,
+                },
+                part,
+            ) => {
+                if part != Part::To {
+                    *from = shift(original_from);
+                }
+                if part != Part::From {
+                    *to = shift(original_to);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Part of the annotation under `point`, within `distance` image pixels
+    fn grab(&self, point: &Point, distance: f64, style: &AnnotationStyle) -> Option<Part> {
+        match self {
+            Annotation::Text { at, text } => {
+                let (x, y, width, height) = text_box(at, text, style);
+                let inside = (x - distance..=x + width + distance).contains(&point.x)
+                    && (y - distance..=y + height + distance).contains(&point.y);
+                inside.then_some(Part::Whole)
+            }
+            Annotation::Arrow { from, to } | Annotation::Highlight { from, to } => {
+                if point.distance(to) <= distance {
+                    return Some(Part::To);
+                }
+                if point.distance(from) <= distance {
+                    return Some(Part::From);
+                }
+                let near = match self {
+                    Annotation::Arrow { .. } => {
+                        distance_to_segment(point, from, to) <= distance + style.line_width
+                    }
+                    _ => {
+                        let (left, right) = (from.x.min(to.x), from.x.max(to.x));
+                        let (top, bottom) = (from.y.min(to.y), from.y.max(to.y));
+                        (left - distance..=right + distance).contains(&point.x)
+                            && (top - distance..=bottom + distance).contains(&point.y)
+                    }
+                };
+                near.then_some(Part::Whole)
+            }
+        }
+    }
+
+    fn draw(&self, cr: &cairo::Context, style: &AnnotationStyle) {
+        cr.set_line_width(style.line_width);
+        match self {
+            Annotation::Text { at, text } => {
+                let (x, y, width, height) = text_box(at, text, style);
+                style.background.apply(cr);
+                cr.rectangle(x, y, width, height);
+                let _ = cr.fill();
+
+                set_font(cr, style);
+                style.color.apply(cr);
+                let padding = TEXT_PADDING * style.line_width;
+                let line_height = style.font_size * 1.2;
+                for (index, line) in text.lines().enumerate() {
+                    cr.move_to(
+                        x + padding,
+                        y + padding + style.font_size + index as f64 * line_height,
+                    );
+                    let _ = cr.show_text(line);
+                }
+            }
+            Annotation::Arrow { from, to } => {
+                style.color.apply(cr);
+                let head = HEAD_LENGTH * style.line_width;
+                let direction = from.direction_to(to);
+
+                // The line stops at the base of the head, so the tip stays sharp
+                let length = from.distance(to);
+                let base = (length - head).max(0.0) / length.max(f64::EPSILON);
+                cr.move_to(from.x, from.y);
+                cr.line_to(
+                    from.x + (to.x - from.x) * base,
+                    from.y + (to.y - from.y) * base,
+                );
+                let _ = cr.stroke();
+
+                cr.move_to(to.x, to.y);
+                for side in [-1.0, 1.0] {
+                    let angle = direction + PI + side * PI / 7.0;
+                    cr.line_to(to.x + head * angle.cos(), to.y + head * angle.sin());
+                }
+                cr.close_path();
+                let _ = cr.fill();
+            }
+            Annotation::Highlight { from, to } => {
+                let (x, y) = (from.x.min(to.x), from.y.min(to.y));
+                let (width, height) = ((from.x - to.x).abs(), (from.y - to.y).abs());
+                cr.rectangle(x, y, width, height);
+                style.highlight.apply(cr);
+                let _ = cr.fill_preserve();
+                style.color.apply(cr);
+                let _ = cr.stroke();
+            }
+        }
+    }
+
+    /// Points that can be dragged on their own, marked while selected
+    fn handles(&self) -> Vec<Point> {
+        match self {
+            Annotation::Text { .. } => Vec::new(),
+            Annotation::Arrow { from, to } | Annotation::Highlight { from, to } => {
+                vec![*from, *to]
+            }
+        }
+    }
+}
+
+/// Selects the font labels are written in
+fn set_font(cr: &cairo::Context, style: &AnnotationStyle) {
+    cr.select_font_face(
+        &style.font,
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Bold,
+    );
+    cr.set_font_size(style.font_size);
+}
+
+/// Position and size of the background of a label
+/// The text is measured on a scratch surface, so this works outside of drawing
+fn text_box(at: &Point, text: &str, style: &AnnotationStyle) -> (f64, f64, f64, f64) {
+    let padding = TEXT_PADDING * style.line_width;
+    let line_height = style.font_size * 1.2;
+    let lines = text.lines().count().max(1);
+
+    let width = cairo::ImageSurface::create(cairo::Format::ARgb32, 1, 1)
+        .and_then(|surface| cairo::Context::new(&surface))
+        .map(|cr| {
+            set_font(&cr, style);
+            text.lines()
+                .filter_map(|line| cr.text_extents(line).ok())
+                .map(|extents| extents.x_advance())
+                .fold(0.0, f64::max)
+        })
+        .unwrap_or_default();
+
+    (
+        at.x,
+        at.y,
+        width + 2.0 * padding,
+        style.font_size + (lines - 1) as f64 * line_height + 2.0 * padding + style.font_size * 0.3,
+    )
+}
+
+/// Distance from a point to the segment between `start` and `end`
+fn distance_to_segment(point: &Point, start: &Point, end: &Point) -> f64 {
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return point.distance(start);
+    }
+
+    let along =
+        (((point.x - start.x) * dx + (point.y - start.y) * dy) / length_squared).clamp(0.0, 1.0);
+    point.distance(&Point::new(start.x + along * dx, start.y + along * dy))
+}
+
+/// The annotations of a capture, oldest first, and the one selected
+#[derive(Debug, Default, Clone)]
+pub struct Annotations {
+    items: Vec<Annotation>,
+    selected: Option<usize>,
+}
+
+impl Annotations {
+    /// Annotations saved earlier, with none selected
+    pub fn restore(items: Vec<Annotation>) -> Self {
+        Annotations {
+            items,
+            selected: None,
+        }
+    }
+
+    pub fn items(&self) -> &[Annotation] {
+        &self.items
+    }
+
+    /// Adds an annotation on top of the others and selects it, returning its index
+    pub fn add(&mut self, annotation: Annotation) -> usize {
+        self.items.push(annotation);
+        self.selected = Some(self.items.len() - 1);
+        self.items.len() - 1
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Annotation> {
+        self.items.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Annotation> {
+        self.items.get_mut(index)
+    }
+
+    /// Removes the annotation at the given index, and the selection if it was selected
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.items.len() {
+            return;
+        }
+        self.items.remove(index);
+        self.selected = match self.selected {
+            Some(selected) if selected == index => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            selected => selected,
+        };
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|&index| index < self.items.len());
+    }
+
+    /// Topmost annotation under the point and the part of it grabbed
+    /// The handles of the selected annotation come first, even under another one
+    pub fn grab_at(
+        &self,
+        point: &Point,
+        distance: f64,
+        style: &AnnotationStyle,
+    ) -> Option<(usize, Part)> {
+        if let Some(index) = self.selected
+            && let Some(part) = self.items[index].grab(point, distance, style)
+            && part != Part::Whole
+        {
+            return Some((index, part));
+        }
+
+        self.items
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, item)| Some((index, item.grab(point, distance, style)?)))
+    }
+
+    /// Draws every annotation, with handles on the ends of the selected one
+    pub fn draw(&self, cr: &cairo::Context, style: &AnnotationStyle, scale: f64) {
+        for (index, item) in self.items.iter().enumerate() {
+            item.draw(cr, style);
+
+            if Some(index) != self.selected {
+                continue;
+            }
+            style.selected.apply(cr);
+            cr.set_line_width(2.0 / scale);
+            match item {
+                Annotation::Text { at, text } => {
+                    let (x, y, width, height) = text_box(at, text, style);
+                    cr.rectangle(x, y, width, height);
+                    let _ = cr.stroke();
+                }
+                _ => {
+                    for handle in item.handles() {
+                        cr.new_sub_path();
+                        cr.arc(handle.x, handle.y, HANDLE_RADIUS / scale, 0.0, 2.0 * PI);
+                    }
+                    let _ = cr.fill();
+                }
+            }
+        }
+    }
+}
+
+/// Entry for the text of a label, shown at the top of the screen while editing
+pub struct TextEditor {
+    container: Box,
+    entry: Entry,
+    /// Index of the label being edited
+    editing: Cell<Option<usize>>,
+}
+
+impl TextEditor {
+    /// `on_done` receives the index of the edited label and its new text,
+    /// or `None` when the edit was cancelled with Escape
+    pub fn new(on_done: impl Fn(usize, Option<String>) + 'static) -> Rc<Self> {
+        let container = Box::builder()
+            .css_classes(vec!["annotation-editor"])
+            .orientation(gtk4::Orientation::Vertical)
+            .spacing(4)
+            .halign(gtk4::Align::Center)
+            .valign(gtk4::Align::Start)
+            .visible(false)
+            .build();
+
+        let title = Label::builder()
+            .label("Annotation · Enter to keep, Escape to cancel")
+            .css_classes(vec!["annotation-editor-title"])
+            .halign(gtk4::Align::Start)
+            .build();
+        container.append(&title);
+
+        let entry = Entry::builder().width_chars(40).build();
+        container.append(&entry);
+
+        let editor = Rc::new(TextEditor {
+            container,
+            entry,
+            editing: Cell::new(None),
+        });
+
+        let on_done = Rc::new(on_done);
+        let editor_weak = Rc::downgrade(&editor);
+        let on_done_activate = on_done.clone();
+        editor.entry.connect_activate(move |entry| {
+            if let Some(editor) = editor_weak.upgrade()
+                && let Some(index) = editor.finish()
+            {
+                on_done_activate(index, Some(entry.text().to_string()));
+            }
+        });
+
+        // Escape would otherwise reach the window and close the overlay
+        let editor_weak = Rc::downgrade(&editor);
+        let key_controller = EventControllerKey::new();
+        key_controller.connect_key_pressed(move |_, key, _, _| {
+            if key != Key::Escape {
+                return Propagation::Proceed;
+            }
+            if let Some(editor) = editor_weak.upgrade()
+                && let Some(index) = editor.finish()
+            {
+                on_done(index, None);
+            }
+            Propagation::Stop
+        });
+        editor.entry.add_controller(key_controller);
+
+        editor
+    }
+
+    pub fn widget(&self) -> &Box {
+        &self.container
+    }
+
+    /// Whether a label is being edited, which keeps the keys from the overlay
+    pub fn is_editing(&self) -> bool {
+        self.editing.get().is_some()
+    }
+
+    /// Shows the entry with the current text of the label at the given index
+    pub fn edit(&self, index: usize, text: &str) {
+        self.editing.set(Some(index));
+        self.entry.set_text(text);
+        self.container.set_visible(true);
+        self.entry.grab_focus();
+    }
+
+    /// Hides the entry, returning the index of the label that was edited
+    fn finish(&self) -> Option<usize> {
+        self.container.set_visible(false);
+        self.editing.take()
+    }
+}
@@ -0,0 +1,105 @@
+//! Stylesheet and icons bundled into the binary
+//!
+//! Each asset can be overridden by a file with the same name in a `rustyruler`
+//! directory of the search path: the XDG data directories, then the user's
+//! config directory. Later directories take precedence over earlier ones.
+
+use gtk4::gdk;
+use std::path::PathBuf;
+
+/// Assets compiled into the binary, used when no override is found
+const EMBEDDED: &[(&str, &[u8])] = &[
+    ("style.css", include_bytes!("../assets/style.css")),
+    ("cross.png", include_bytes!("../assets/cross.png")),
+    ("line.png", include_bytes!("../assets/line.png")),
+    ("angle.png", include_bytes!("../assets/angle.png")),
+    ("ray.png", include_bytes!("../assets/ray.png")),
+    ("circle.png", include_bytes!("../assets/circle.png")),
+    ("color.png", include_bytes!("../assets/color.png")),
+    ("windows.png", include_bytes!("../assets/windows.png")),
+    ("components.png", include_bytes!("../assets/components.png")),
+    ("typography.png", include_bytes!("../assets/typography.png")),
+    ("text.png", include_bytes!("../assets/text.png")),
+    ("arrow.png", include_bytes!("../assets/arrow.png")),
+    ("highlight.png", include_bytes!("../assets/highlight.png")),
+    ("redline.png", include_bytes!("../assets/redline.png")),
+];
+
+/// Directories searched for overrides, from lowest to highest precedence
+pub fn search_path() -> Vec<PathBuf> {
+    // XDG_DATA_DIRS is ordered by importance, so it is walked in reverse
+    let mut dirs: Vec<PathBuf> = glib::system_data_dirs().into_iter().rev().collect();
+    dirs.push(glib::user_data_dir());
+    dirs.push(glib::user_config_dir());
+
+    dirs.into_iter().map(|dir| dir.join("rustyruler")).collect()
+}
+
+/// Returns the bundled copy of an asset
+fn embedded(name: &str) -> &'static [u8] {
+    EMBEDDED
+        .iter()
+        .find(|(asset, _)| *asset == name)
+        .map(|(_, bytes)| *bytes)
+        .unwrap_or_else(|| panic!("{name} is not a bundled asset"))
+}
+
+/// Finds every override of an asset, from lowest to highest precedence
+fn overrides(name: &str) -> Vec<PathBuf> {
+    search_path()
+        .into_iter()
+        .map(|dir| dir.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Stylesheets to apply, starting with the bundled one followed by every override
+/// Later sheets are meant to be added with a higher priority so they win
+pub fn stylesheets() -> Vec<String> {
+    let mut sheets = vec![String::from_utf8_lossy(embedded("style.css")).into_owned()];
+
+    for path in overrides("style.css") {
+        match std::fs::read_to_string(&path) {
+            Ok(css) => sheets.push(css),
+            Err(err) => eprintln!("Error reading {}: {}", path.display(), err),
+        }
+    }
+
+    sheets
+}
+
+/// Loads an image asset, preferring the override with the highest precedence
+/// Overrides that can't be decoded are reported and skipped
+pub fn image(name: &str) -> image::DynamicImage {
+    for path in overrides(name).iter().rev() {
+        match image::open(path) {
+            Ok(img) => return img,
+            Err(err) => eprintln!("Error loading {}: {}", path.display(), err),
+        }
+    }
+
+    image::load_from_memory(embedded(name))
+        .unwrap_or_else(|err| panic!("Bundled asset {name} is not a valid image: {err}"))
+}
+
+/// Loads an image asset as a texture ready to be shown by GTK
+pub fn texture(name: &str) -> gdk::MemoryTexture {
+    texture_from_image(&image(name))
+}
+
+/// Converts an image into a GDK memory texture
+pub fn texture_from_image(img: &image::DynamicImage) -> gdk::MemoryTexture {
+    // Get RGBA data from the image
+    let rgba = img.to_rgba8();
+    let width = rgba.width();
+    let height = rgba.height();
+
+    // Create a GDK memory texture from the RGBA data
+    gdk::MemoryTexture::new(
+        width as i32,
+        height as i32,
+        gdk::MemoryFormat::R8g8b8a8,
+        &glib::Bytes::from(rgba.as_raw()),
+        width as usize * 4,
+    )
+}
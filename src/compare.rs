@@ -0,0 +1,353 @@
+//! Comparing the capture against a reference image, such as a mockup or an earlier capture
+//!
+//! The reference is drawn faded over the capture, as the difference of both
+//! images, or next to the capture split at a movable position. The regions
+//! where the two differ are outlined in every view.
+//!
+//! The reference is lined up with the capture by nudging or dragging it, or
+//! automatically by matching the edges of both. While it is measured, the
+//! tools see it in place of the capture.
+
+use crate::config::Config;
+use gtk4::{cairo, gdk_pixbuf::Pixbuf, prelude::*};
+use image::RgbImage;
+use rustyruler_core::units::{Axis, Units};
+use rustyruler_core::{Rect, align, changed_regions};
+use std::str::FromStr;
+
+/// How much the opacity or the split changes with each step
+const STEP: f64 = 0.1;
+
+/// Pixels the reference moves by when nudged with Shift held
+pub const LARGE_NUDGE: i32 = 10;
+
+/// How the reference is shown over the capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// The reference faded over the capture
+    Onion,
+    /// Black where both images match, bright where they differ
+    Difference,
+    /// The reference left of the split and the capture right of it
+    Split,
+}
+
+/// How the faded reference is combined with the capture under it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Blend {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+}
+
+impl Blend {
+    // The difference of both images is a view of its own, see `View::Difference`
+    const ALL: [Blend; 4] = [
+        Blend::Normal,
+        Blend::Multiply,
+        Blend::Screen,
+        Blend::Overlay,
+    ];
+
+    /// Name used in the configuration and shown in the tooltip
+    pub fn name(self) -> &'static str {
+        match self {
+            Blend::Normal => "normal",
+            Blend::Multiply => "multiply",
+            Blend::Screen => "screen",
+            Blend::Overlay => "overlay",
+        }
+    }
+
+    fn next(self) -> Blend {
+        let index = Blend::ALL
+            .iter()
+            .position(|&blend| blend == self)
+            .unwrap_or(0);
+        Blend::ALL[(index + 1) % Blend::ALL.len()]
+    }
+
+    fn operator(self) -> cairo::Operator {
+        match self {
+            Blend::Normal => cairo::Operator::Over,
+            Blend::Multiply => cairo::Operator::Multiply,
+            Blend::Screen => cairo::Operator::Screen,
+            Blend::Overlay => cairo::Operator::Overlay,
+        }
+    }
+}
+
+impl FromStr for Blend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Blend::ALL
+            .into_iter()
+            .find(|blend| blend.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Blend::ALL.iter().map(|blend| blend.name()).collect();
+                format!(
+                    "unknown blend mode \"{name}\", expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl View {
+    /// The view shown after `view`, hiding the reference after the last one
+    fn next(view: Option<View>) -> Option<View> {
+        match view {
+            None => Some(View::Onion),
+            Some(View::Onion) => Some(View::Difference),
+            Some(View::Difference) => Some(View::Split),
+            Some(View::Split) => None,
+        }
+    }
+}
+
+/// A reference image and how it is compared with the capture
+#[derive(Clone)]
+pub struct Compare {
+    reference: RgbImage,
+    pixbuf: Pixbuf,
+    /// Position of the top left corner of the reference in the capture
+    offset: (i32, i32),
+    /// How the reference is shown, hidden while `None`
+    view: Option<View>,
+    /// Opacity of the reference in the onion view
+    opacity: f64,
+    /// How the reference is combined with the capture in the onion view
+    blend: Blend,
+    /// Fraction of the width covered by the reference in the split view
+    split: f64,
+    /// Regions that differ from the capture, in image pixels
+    regions: Vec<Rect>,
+    /// The capture with the reference pasted over it, while the tools measure the reference
+    measured: Option<RgbImage>,
+}
+
+impl Compare {
+    /// Starts comparing with the reference in the onion view
+    pub fn new(reference: RgbImage, pixbuf: Pixbuf, config: &Config) -> Self {
+        Compare {
+            reference,
+            pixbuf,
+            offset: (0, 0),
+            view: Some(View::Onion),
+            opacity: config.compare.opacity,
+            blend: config.compare.blend,
+            split: 0.5,
+            regions: Vec::new(),
+            measured: None,
+        }
+    }
+
+    /// Swaps the reference for another one, keeping the view
+    pub fn replace(&mut self, reference: RgbImage, pixbuf: Pixbuf) {
+        self.reference = reference;
+        self.pixbuf = pixbuf;
+        self.offset = (0, 0);
+        self.view.get_or_insert(View::Onion);
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.view.is_some()
+    }
+
+    /// Shows the next view, or hides the reference after the last one
+    pub fn cycle(&mut self) {
+        self.view = View::next(self.view);
+        if self.view.is_none() {
+            self.measured = None;
+        }
+    }
+
+    /// Switches the faded view to the next blend mode
+    pub fn cycle_blend(&mut self) {
+        self.blend = self.blend.next();
+        self.view = Some(View::Onion);
+    }
+
+    pub fn offset(&self) -> (i32, i32) {
+        self.offset
+    }
+
+    /// Moves the reference to the given position, for example while it is dragged
+    /// The changed regions are cleared until the next update
+    pub fn set_offset(&mut self, offset: (i32, i32)) {
+        self.offset = offset;
+        self.regions.clear();
+    }
+
+    /// Moves the reference by the given number of pixels
+    pub fn nudge(&mut self, dx: i32, dy: i32) {
+        self.set_offset((
+            self.offset.0.saturating_add(dx),
+            self.offset.1.saturating_add(dy),
+        ));
+    }
+
+    /// Moves the reference to where its edges line up with those of the capture,
+    /// searching up to `max_shift` pixels away from where it is
+    /// Returns false when no position lines up any edge
+    pub fn align(&mut self, img: &RgbImage, max_shift: u32, threshold: f32) -> bool {
+        match align(img, &self.reference, self.offset, max_shift, threshold) {
+            Some(offset) => {
+                self.set_offset(offset);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the tools measure the reference instead of the capture
+    pub fn is_measured(&self) -> bool {
+        self.measured.is_some()
+    }
+
+    /// Starts or stops measuring the reference, which is shown fully opaque meanwhile
+    pub fn toggle_measured(&mut self, img: &RgbImage) {
+        if self.measured.take().is_none() {
+            self.view.get_or_insert(View::Onion);
+            self.measured = Some(self.paste_over(img));
+        }
+    }
+
+    /// Image the tools measure, the capture with the reference over it while it is measured
+    pub fn measured_image(&self) -> Option<&RgbImage> {
+        self.measured.as_ref()
+    }
+
+    /// Copy of the capture with the reference pasted at its position
+    fn paste_over(&self, img: &RgbImage) -> RgbImage {
+        let mut pasted = img.clone();
+        image::imageops::replace(
+            &mut pasted,
+            &self.reference,
+            i64::from(self.offset.0),
+            i64::from(self.offset.1),
+        );
+        pasted
+    }
+
+    /// Shows more or less of the reference, through its opacity or the split
+    pub fn step(&mut self, increase: bool) {
+        let step = if increase { STEP } else { -STEP };
+        match self.view {
+            Some(View::Onion) => self.opacity = (self.opacity + step).clamp(0.0, 1.0),
+            Some(View::Split) => self.split = (self.split + step).clamp(0.0, 1.0),
+            Some(View::Difference) | None => {}
+        }
+    }
+
+    /// Finds the changed regions again, once the capture, the threshold or
+    /// the position of the reference changed
+    /// Nothing is compared while the reference is hidden
+    pub fn update(&mut self, img: &RgbImage, threshold: f32) {
+        if self.is_measured() {
+            self.measured = Some(self.paste_over(img));
+        }
+        self.regions = if self.is_shown() {
+            changed_regions(img, &self.reference, self.offset, threshold)
+        } else {
+            Vec::new()
+        };
+    }
+
+    /// Draws the reference over the capture already painted, and outlines the changed regions
+    pub fn draw(
+        &self,
+        cr: &cairo::Context,
+        config: &Config,
+        scale: f64,
+        img_width: u32,
+        img_height: u32,
+    ) {
+        let Some(view) = self.view else {
+            return;
+        };
+        let (x, y) = (f64::from(self.offset.0), f64::from(self.offset.1));
+        let split = (f64::from(img_width) * self.split).round();
+
+        cr.save().unwrap();
+        cr.set_source_pixbuf(&self.pixbuf, x, y);
+        match view {
+            // What is measured is shown as it is
+            _ if self.is_measured() => {
+                let _ = cr.paint();
+            }
+            View::Onion => {
+                cr.set_operator(self.blend.operator());
+                let _ = cr.paint_with_alpha(self.opacity);
+            }
+            View::Difference => {
+                cr.set_operator(cairo::Operator::Difference);
+                let _ = cr.paint();
+            }
+            View::Split => {
+                cr.rectangle(0.0, 0.0, split, f64::from(img_height));
+                cr.clip();
+                let _ = cr.paint();
+            }
+        }
+        cr.restore().unwrap();
+
+        config.compare.color.apply(cr);
+        cr.set_line_width(1.0 / scale);
+        for region in &self.regions {
+            cr.rectangle(
+                f64::from(region.x),
+                f64::from(region.y),
+                f64::from(region.width),
+                f64::from(region.height),
+            );
+        }
+        let _ = cr.stroke();
+
+        if view == View::Split {
+            cr.set_line_width(2.0 / scale);
+            cr.move_to(split, 0.0);
+            cr.line_to(split, f64::from(img_height));
+            let _ = cr.stroke();
+        }
+    }
+
+    /// Number of changed regions and the size of the one at the given pixel,
+    /// e.g. `Changed: 3 regions · 120 × 40 px here`
+    pub fn report(&self, x: u32, y: u32, units: &Units) -> String {
+        let mut text = match self.regions.len() {
+            0 => "Changed: nothing".to_string(),
+            1 => "Changed: 1 region".to_string(),
+            count => format!("Changed: {count} regions"),
+        };
+
+        if self.is_measured() {
+            text.insert_str(0, "Measuring the reference · ");
+        } else if self.view == Some(View::Onion) && self.blend != Blend::Normal {
+            text.push_str(&format!(" · {}", self.blend.name()));
+        }
+
+        if let Some(region) = self.regions.iter().find(|region| region.contains(x, y)) {
+            let (screen_width, screen_height) = units.context.screen_size;
+            text.push_str(&format!(
+                " · {} × {} {} here",
+                units.format(region.width, Axis::Horizontal, screen_width),
+                units.format(region.height, Axis::Vertical, screen_height),
+                units.unit.label()
+            ));
+        }
+
+        // Nudges are always whole pixels
+        if self.offset != (0, 0) {
+            text.push_str(&format!(
+                " · moved {:+}, {:+} px",
+                self.offset.0, self.offset.1
+            ));
+        }
+
+        text
+    }
+}
@@ -0,0 +1,210 @@
+//! Hyprland IPC
+//!
+//! Every request is a new connection on the socket, the command is written as
+//! text and the reply is read until the compositor closes the connection. The
+//! `j/` prefix asks for JSON replies.
+
+use super::{CompositorError, Layout, LogicalRect, TIMEOUT};
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceRef {
+    id: i64,
+}
+
+/// Monitor, with its size in physical pixels
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Monitor {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    scale: f64,
+    active_workspace: WorkspaceRef,
+    special_workspace: WorkspaceRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct Client {
+    at: [f64; 2],
+    size: [f64; 2],
+    mapped: bool,
+    hidden: bool,
+    workspace: WorkspaceRef,
+}
+
+/// Focused window, `{}` when no window has the focus
+#[derive(Debug, Deserialize)]
+struct ActiveWindow {
+    #[serde(default)]
+    class: String,
+}
+
+/// Sends a command and returns the whole reply
+fn request(socket: &Path, command: &str) -> Result<Vec<u8>, CompositorError> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(command.as_bytes())?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply)?;
+    Ok(reply)
+}
+
+/// Reads the monitors and the windows on the workspaces they show
+pub fn layout(socket: &Path) -> Result<Layout, CompositorError> {
+    let monitors: Vec<Monitor> = serde_json::from_slice(&request(socket, "j/monitors")?)?;
+    let clients: Vec<Client> = serde_json::from_slice(&request(socket, "j/clients")?)?;
+
+    // A special workspace id of 0 means none is open on the monitor
+    let shown: Vec<i64> = monitors
+        .iter()
+        .flat_map(|monitor| [monitor.active_workspace.id, monitor.special_workspace.id])
+        .filter(|&id| id != 0)
+        .collect();
+
+    Ok(Layout {
+        outputs: monitors
+            .iter()
+            .map(|monitor| LogicalRect {
+                x: monitor.x,
+                y: monitor.y,
+                width: monitor.width / monitor.scale,
+                height: monitor.height / monitor.scale,
+            })
+            .collect(),
+        windows: clients
+            .iter()
+            .filter(|client| {
+                client.mapped && !client.hidden && shown.contains(&client.workspace.id)
+            })
+            .map(|client| LogicalRect {
+                x: client.at[0],
+                y: client.at[1],
+                width: client.size[0],
+                height: client.size[1],
+            })
+            .collect(),
+    })
+}
+
+/// Reads the class of the focused window
+pub fn focused_app(socket: &Path) -> Result<Option<String>, CompositorError> {
+    let active: ActiveWindow = serde_json::from_slice(&request(socket, "j/activewindow")?)?;
+    Ok(Some(active.class).filter(|class| !class.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::tests::fake_socket;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    const MONITORS: &str = r#"[
+        {"name": "eDP-1", "x": 0, "y": 0, "width": 2880, "height": 1800, "scale": 2.0,
+         "activeWorkspace": {"id": 1, "name": "1"}, "specialWorkspace": {"id": 0, "name": ""}},
+        {"name": "DP-2", "x": 1440, "y": 0, "width": 1920, "height": 1080, "scale": 1.0,
+         "activeWorkspace": {"id": 4, "name": "4"}, "specialWorkspace": {"id": -98, "name": "special"}}
+    ]"#;
+
+    const CLIENTS: &str = r#"[
+        {"class": "kitty", "at": [10, 10], "size": [700, 880], "mapped": true, "hidden": false,
+         "workspace": {"id": 1, "name": "1"}},
+        {"class": "firefox", "at": [1450, 10], "size": [1900, 1060], "mapped": true, "hidden": false,
+         "workspace": {"id": 4, "name": "4"}},
+        {"class": "btop", "at": [1600, 200], "size": [800, 600], "mapped": true, "hidden": false,
+         "workspace": {"id": -98, "name": "special"}},
+        {"class": "discord", "at": [10, 10], "size": [700, 880], "mapped": true, "hidden": false,
+         "workspace": {"id": 2, "name": "2"}},
+        {"class": "mpv", "at": [20, 20], "size": [640, 360], "mapped": true, "hidden": true,
+         "workspace": {"id": 1, "name": "1"}}
+    ]"#;
+
+    const ACTIVE_WINDOW: &str = r#"{"class": "firefox", "title": "Mozilla Firefox"}"#;
+
+    /// Answers the given number of requests, one per connection like Hyprland
+    fn serve(listener: UnixListener, requests: usize) {
+        for _ in 0..requests {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut command = [0u8; 64];
+            let length = stream.read(&mut command).unwrap();
+
+            let reply = match &command[..length] {
+                b"j/monitors" => MONITORS,
+                b"j/clients" => CLIENTS,
+                b"j/activewindow" => ACTIVE_WINDOW,
+                _ => "unknown request",
+            };
+            stream.write_all(reply.as_bytes()).unwrap();
+        }
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> LogicalRect {
+        LogicalRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn layout_reads_monitors_and_windows_on_shown_workspaces() {
+        let socket = fake_socket("hyprland");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || serve(listener, 2));
+
+        let layout = layout(&socket).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(
+            layout,
+            Layout {
+                outputs: vec![
+                    rect(0.0, 0.0, 1440.0, 900.0),
+                    rect(1440.0, 0.0, 1920.0, 1080.0),
+                ],
+                windows: vec![
+                    rect(10.0, 10.0, 700.0, 880.0),
+                    rect(1450.0, 10.0, 1900.0, 1060.0),
+                    rect(1600.0, 200.0, 800.0, 600.0),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn focused_app_asks_for_the_active_window_only() {
+        let socket = fake_socket("hyprland-active");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || serve(listener, 1));
+
+        let app = focused_app(&socket).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(app.as_deref(), Some("firefox"));
+    }
+
+    #[test]
+    fn layout_reports_replies_that_are_not_json() {
+        let socket = fake_socket("hyprland-text");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut command = [0u8; b"j/monitors".len()];
+            stream.read_exact(&mut command).unwrap();
+            stream.write_all(b"unknown request").unwrap();
+        });
+
+        let result = layout(&socket);
+        server.join().unwrap();
+
+        assert!(matches!(result, Err(CompositorError::Reply(_))));
+    }
+}
@@ -0,0 +1,240 @@
+//! Window geometry read from the compositor over its IPC socket
+//!
+//! Sway and Hyprland are supported. Both report positions in the layout of all
+//! outputs, in logical pixels, which [`Layout::window_rects`] maps onto the
+//! captured screenshot. The clients take the socket path so they can be pointed
+//! at any server speaking the same protocol.
+//!
+//! The layout is read on a worker thread, and every read and write on the
+//! socket gives up after [`TIMEOUT`] so a hung compositor doesn't hold it forever.
+
+mod hyprland;
+mod sway;
+
+use rustyruler_core::Rect;
+use std::env;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Longest wait for the compositor to accept or answer a request
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Rectangle in the compositor layout, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogicalRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Outputs and the windows visible on them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout {
+    pub outputs: Vec<LogicalRect>,
+    pub windows: Vec<LogicalRect>,
+}
+
+impl Layout {
+    /// Window rectangles in pixels of a screenshot of every output
+    /// The screenshot is assumed to cover the bounding box of the outputs
+    pub fn window_rects(&self, img_width: u32, img_height: u32) -> Vec<Rect> {
+        let Some(bounds) = self.outputs.iter().copied().reduce(|a, b| {
+            let x = a.x.min(b.x);
+            let y = a.y.min(b.y);
+            LogicalRect {
+                x,
+                y,
+                width: (a.x + a.width).max(b.x + b.width) - x,
+                height: (a.y + a.height).max(b.y + b.height) - y,
+            }
+        }) else {
+            return Vec::new();
+        };
+
+        let scale = f64::from(img_width) / bounds.width;
+        let to_pixels = |value: f64, origin: f64, max: u32| {
+            (((value - origin) * scale).round().max(0.0) as u32).min(max)
+        };
+
+        self.windows
+            .iter()
+            .map(|window| {
+                let x = to_pixels(window.x, bounds.x, img_width);
+                let y = to_pixels(window.y, bounds.y, img_height);
+                Rect {
+                    x,
+                    y,
+                    width: to_pixels(window.x + window.width, bounds.x, img_width) - x,
+                    height: to_pixels(window.y + window.height, bounds.y, img_height) - y,
+                }
+            })
+            .filter(|rect| rect.width > 0 && rect.height > 0)
+            .collect()
+    }
+}
+
+/// Error raised while talking to the compositor
+#[derive(Debug)]
+pub enum CompositorError {
+    /// Neither Sway nor Hyprland is running
+    Unsupported,
+    Io(io::Error),
+    /// The reply could not be understood
+    Reply(String),
+}
+
+impl fmt::Display for CompositorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompositorError::Unsupported => {
+                write!(
+                    f,
+                    "No supported compositor found, Sway or Hyprland is needed"
+                )
+            }
+            CompositorError::Io(err) => write!(f, "Compositor IPC failed: {}", err),
+            CompositorError::Reply(err) => write!(f, "Unexpected compositor reply: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CompositorError {}
+
+impl From<io::Error> for CompositorError {
+    fn from(err: io::Error) -> Self {
+        CompositorError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for CompositorError {
+    fn from(err: serde_json::Error) -> Self {
+        CompositorError::Reply(err.to_string())
+    }
+}
+
+/// Compositor found from the environment, with the path of its IPC socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compositor {
+    Sway(PathBuf),
+    Hyprland(PathBuf),
+}
+
+impl Compositor {
+    /// Finds the running compositor from the variables it sets for its clients
+    pub fn detect() -> Option<Self> {
+        if let Some(socket) = env::var_os("SWAYSOCK") {
+            return Some(Compositor::Sway(PathBuf::from(socket)));
+        }
+
+        let signature = env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+        // Recent versions keep the socket in the runtime directory, older ones in /tmp
+        let runtime_dir = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+        let socket = [runtime_dir, Some(PathBuf::from("/tmp"))]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join("hypr").join(&signature).join(".socket.sock"))
+            .find(|path| path.exists())?;
+        Some(Compositor::Hyprland(socket))
+    }
+
+    /// Reads the outputs and the visible windows
+    pub fn layout(&self) -> Result<Layout, CompositorError> {
+        match self {
+            Compositor::Sway(socket) => sway::layout(socket),
+            Compositor::Hyprland(socket) => hyprland::layout(socket),
+        }
+    }
+
+    /// App of the focused window, its app id on Sway and its class on Hyprland
+    /// `None` when no window has the focus
+    pub fn focused_app(&self) -> Result<Option<String>, CompositorError> {
+        match self {
+            Compositor::Sway(socket) => sway::focused_app(socket),
+            Compositor::Hyprland(socket) => hyprland::focused_app(socket),
+        }
+    }
+}
+
+/// Reads the layout of the running compositor
+pub fn layout() -> Result<Layout, CompositorError> {
+    Compositor::detect()
+        .ok_or(CompositorError::Unsupported)?
+        .layout()
+}
+
+/// App of the focused window, `None` without a supported compositor
+/// Only the focused window is asked for, rather than the whole layout
+pub fn focused_app() -> Option<String> {
+    let result = Compositor::detect()
+        .ok_or(CompositorError::Unsupported)
+        .and_then(|compositor| compositor.focused_app());
+    match result {
+        Ok(app) => app,
+        Err(CompositorError::Unsupported) => None,
+        Err(err) => {
+            eprintln!("{err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Path of the socket a fake compositor listens on, in a fresh directory
+    pub(super) fn fake_socket(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rustyruler-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("ipc.sock")
+    }
+
+    #[test]
+    fn window_rects_scale_the_layout_onto_the_capture() {
+        let layout = Layout {
+            outputs: vec![
+                LogicalRect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 1280.0,
+                    height: 720.0,
+                },
+                LogicalRect {
+                    x: 1280.0,
+                    y: 0.0,
+                    width: 1280.0,
+                    height: 720.0,
+                },
+            ],
+            windows: vec![
+                LogicalRect {
+                    x: 1290.0,
+                    y: 10.0,
+                    width: 600.0,
+                    height: 700.0,
+                },
+                // Off every output, clipped away
+                LogicalRect {
+                    x: -500.0,
+                    y: 0.0,
+                    width: 100.0,
+                    height: 100.0,
+                },
+            ],
+        };
+
+        assert_eq!(
+            layout.window_rects(5120, 1440),
+            vec![Rect {
+                x: 2580,
+                y: 20,
+                width: 1200,
+                height: 1400,
+            }]
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Sway IPC, also spoken by i3
+//!
+//! Messages are framed as the `i3-ipc` magic string, the payload length and
+//! the message type, both as native endian 32-bit integers, then the payload.
+
+use super::{CompositorError, Layout, LogicalRect, TIMEOUT};
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+
+#[derive(Debug, Deserialize)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl From<&Rect> for LogicalRect {
+    fn from(rect: &Rect) -> Self {
+        LogicalRect {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
+
+/// X11 properties of windows running through Xwayland
+#[derive(Debug, Deserialize)]
+struct WindowProperties {
+    class: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Output {
+    active: bool,
+    rect: Rect,
+}
+
+/// Container of the tree, only the fields needed to find visible windows
+#[derive(Debug, Deserialize)]
+struct Node {
+    #[serde(rename = "type")]
+    kind: String,
+    rect: Rect,
+    #[serde(default)]
+    visible: Option<bool>,
+    #[serde(default)]
+    focused: bool,
+    /// Wayland app id, `None` for Xwayland windows
+    #[serde(default)]
+    app_id: Option<String>,
+    #[serde(default)]
+    window_properties: Option<WindowProperties>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    floating_nodes: Vec<Node>,
+}
+
+impl Node {
+    /// Collects the visible windows, which are the leaves of the tree
+    fn collect_windows(&self, windows: &mut Vec<LogicalRect>) {
+        let is_window = matches!(self.kind.as_str(), "con" | "floating_con")
+            && self.nodes.is_empty()
+            && self.floating_nodes.is_empty();
+        if is_window && self.visible == Some(true) {
+            windows.push(LogicalRect::from(&self.rect));
+        }
+
+        for child in self.nodes.iter().chain(&self.floating_nodes) {
+            child.collect_windows(windows);
+        }
+    }
+
+    /// App of the focused window, if the focus is on a window
+    fn focused_app(&self) -> Option<String> {
+        if self.focused {
+            let class = self
+                .window_properties
+                .as_ref()
+                .and_then(|properties| properties.class.clone());
+            return self.app_id.clone().or(class);
+        }
+
+        self.nodes
+            .iter()
+            .chain(&self.floating_nodes)
+            .find_map(Node::focused_app)
+    }
+}
+
+/// Sends a message and returns the payload of the reply
+fn request(stream: &mut UnixStream, kind: u32) -> Result<Vec<u8>, CompositorError> {
+    let mut message = MAGIC.to_vec();
+    message.extend_from_slice(&0u32.to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    stream.write_all(&message)?;
+
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        return Err(CompositorError::Reply("missing i3-ipc header".to_string()));
+    }
+    let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+
+    let mut payload = vec![0u8; length as usize];
+    stream.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Connects to the socket, giving up on requests after [`TIMEOUT`]
+fn connect(socket: &Path) -> Result<UnixStream, CompositorError> {
+    let stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Reads the active outputs and the windows visible on them
+pub fn layout(socket: &Path) -> Result<Layout, CompositorError> {
+    let mut stream = connect(socket)?;
+
+    let outputs: Vec<Output> = serde_json::from_slice(&request(&mut stream, GET_OUTPUTS)?)?;
+    let tree: Node = serde_json::from_slice(&request(&mut stream, GET_TREE)?)?;
+
+    let mut windows = Vec::new();
+    tree.collect_windows(&mut windows);
+
+    Ok(Layout {
+        outputs: outputs
+            .iter()
+            .filter(|output| output.active)
+            .map(|output| LogicalRect::from(&output.rect))
+            .collect(),
+        windows,
+    })
+}
+
+/// Reads the app of the focused window from the tree, the only message that has it
+pub fn focused_app(socket: &Path) -> Result<Option<String>, CompositorError> {
+    let mut stream = connect(socket)?;
+    let tree: Node = serde_json::from_slice(&request(&mut stream, GET_TREE)?)?;
+    Ok(tree.focused_app())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::tests::fake_socket;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+    use std::time::Instant;
+
+    const OUTPUTS: &str = r#"[
+        {"name": "DP-1", "active": true, "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}},
+        {"name": "HDMI-A-1", "active": false, "rect": {"x": 0, "y": 0, "width": 0, "height": 0}}
+    ]"#;
+
+    const TREE: &str = r#"{
+        "type": "root", "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+        "nodes": [{
+            "type": "output", "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+            "nodes": [{
+                "type": "workspace", "rect": {"x": 0, "y": 30, "width": 1920, "height": 1050},
+                "nodes": [
+                    {"type": "con", "visible": true, "focused": true, "app_id": "foot",
+                     "rect": {"x": 10, "y": 40, "width": 940, "height": 1030}},
+                    {"type": "con", "visible": false, "app_id": "firefox",
+                     "rect": {"x": 970, "y": 40, "width": 940, "height": 1030}}
+                ],
+                "floating_nodes": [
+                    {"type": "floating_con", "visible": true, "app_id": "pavucontrol",
+                     "rect": {"x": 700, "y": 300, "width": 500, "height": 400}}
+                ]
+            }]
+        }]
+    }"#;
+
+    /// Answers every message with the canned reply for its type, like Sway would
+    fn serve(listener: UnixListener) {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0u8; 14];
+        while stream.read_exact(&mut header).is_ok() {
+            assert_eq!(&header[..6], MAGIC);
+            let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+            let kind = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+            let mut payload = vec![0u8; length as usize];
+            stream.read_exact(&mut payload).unwrap();
+
+            let reply = match kind {
+                GET_OUTPUTS => OUTPUTS,
+                GET_TREE => TREE,
+                _ => "[]",
+            };
+            let mut message = MAGIC.to_vec();
+            message.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+            message.extend_from_slice(&kind.to_ne_bytes());
+            message.extend_from_slice(reply.as_bytes());
+            stream.write_all(&message).unwrap();
+        }
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> LogicalRect {
+        LogicalRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn layout_reads_active_outputs_and_visible_windows() {
+        let socket = fake_socket("sway");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || serve(listener));
+
+        let layout = layout(&socket).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(
+            layout,
+            Layout {
+                outputs: vec![rect(0.0, 0.0, 1920.0, 1080.0)],
+                windows: vec![
+                    rect(10.0, 40.0, 940.0, 1030.0),
+                    rect(700.0, 300.0, 500.0, 400.0),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn focused_app_reads_the_focused_node_of_the_tree() {
+        let socket = fake_socket("sway-focused");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || serve(listener));
+
+        let app = focused_app(&socket).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(app.as_deref(), Some("foot"));
+    }
+
+    #[test]
+    fn focused_app_falls_back_to_the_x11_class() {
+        let tree: Node = serde_json::from_str(
+            r#"{
+                "type": "workspace", "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+                "nodes": [
+                    {"type": "con", "visible": true, "focused": false, "app_id": "foot",
+                     "rect": {"x": 0, "y": 0, "width": 960, "height": 1080}},
+                    {"type": "con", "visible": true, "focused": true, "app_id": null,
+                     "window_properties": {"class": "Gimp"},
+                     "rect": {"x": 960, "y": 0, "width": 960, "height": 1080}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(tree.focused_app().as_deref(), Some("Gimp"));
+    }
+
+    #[test]
+    fn layout_gives_up_on_a_compositor_that_never_answers() {
+        let socket = fake_socket("sway-hung");
+        // Connections are queued by the kernel but never accepted nor answered
+        let _listener = UnixListener::bind(&socket).unwrap();
+
+        let start = Instant::now();
+        let result = layout(&socket);
+
+        assert!(matches!(result, Err(CompositorError::Io(_))));
+        assert!(start.elapsed() < TIMEOUT * 4);
+    }
+}
@@ -0,0 +1,852 @@
+//! User configuration loaded from `$XDG_CONFIG_HOME/rustyruler/config.toml`
+//!
+//! Every setting is optional and falls back to the built-in defaults. The tool
+//! and threshold in use when the app closes are written back to the same file,
+//! leaving the rest of it (comments included) untouched. Guides are saved per
+//! app next to it, in `guides.toml`.
+
+use crate::compare::Blend;
+use gtk4::gdk::{Key, ModifierType};
+use rustyruler_core::guides::{self, Guide, Orientation};
+use rustyruler_core::units::PercentOf;
+use rustyruler_core::{DEFAULT_THRESHOLD, Unit};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file inside the config directory
+const CONFIG_FILE: &str = "config.toml";
+
+/// Name of the file guides are saved to, one table per app, inside the config directory
+const GUIDES_FILE: &str = "guides.toml";
+
+/// Range accepted for the magnitude threshold, matching what scrolling allows
+const THRESHOLD_RANGE: std::ops::RangeInclusive<f32> = 1.0..=255.0;
+
+/// Longest accepted time between two live captures, one hour
+const MAX_LIVE_INTERVAL: f64 = 3600.0;
+
+/// Directory holding rustyruler's configuration, usually `~/.config/rustyruler`
+pub fn config_dir() -> PathBuf {
+    glib::user_config_dir().join("rustyruler")
+}
+
+/// Full path of the configuration file
+pub fn config_path() -> PathBuf {
+    config_dir().join(CONFIG_FILE)
+}
+
+/// Full path of the file guides are saved to
+pub fn guides_path() -> PathBuf {
+    config_dir().join(GUIDES_FILE)
+}
+
+/// Error raised while reading, parsing or validating the configuration
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Edit(PathBuf, toml_edit::TomlError),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => {
+                write!(f, "Failed to parse {}:\n{}", path.display(), err)
+            }
+            ConfigError::Edit(path, err) => {
+                write!(f, "Failed to update {}:\n{}", path.display(), err)
+            }
+            ConfigError::Invalid(path, msg) => {
+                write!(f, "Invalid configuration in {}: {}", path.display(), msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// An RGBA color written as `#rrggbb` or `#rrggbbaa` in the configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Color {
+    pub const fn rgba(r: f64, g: f64, b: f64, a: f64) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Sets this color as the source of the given cairo context
+    pub fn apply(&self, cr: &gtk4::cairo::Context) {
+        cr.set_source_rgba(self.r, self.g, self.b, self.a);
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid color \"{value}\", expected #rrggbb or #rrggbbaa");
+
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map(|c| c as f64 / 255.0)
+                .map_err(|_| invalid())
+        };
+        let alpha = if hex.len() == 8 { channel(6)? } else { 1.0 };
+
+        Ok(Color::rgba(channel(0)?, channel(2)?, channel(4)?, alpha))
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Color::try_from(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A key with optional modifiers, written in GTK accelerator syntax
+/// in the configuration, e.g. `Escape`, `c` or `<Control>z`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accel {
+    pub key: Key,
+    pub mods: ModifierType,
+}
+
+impl Accel {
+    pub const fn new(key: Key, mods: ModifierType) -> Self {
+        Accel { key, mods }
+    }
+
+    const fn plain(key: Key) -> Self {
+        Accel::new(key, ModifierType::empty())
+    }
+
+    const fn control(key: Key) -> Self {
+        Accel::new(key, ModifierType::CONTROL_MASK)
+    }
+
+    const fn shift(key: Key) -> Self {
+        Accel::new(key, ModifierType::SHIFT_MASK)
+    }
+
+    const fn control_shift(key: Key) -> Self {
+        Accel::new(
+            key,
+            ModifierType::CONTROL_MASK.union(ModifierType::SHIFT_MASK),
+        )
+    }
+
+    const fn alt(key: Key) -> Self {
+        Accel::new(key, ModifierType::ALT_MASK)
+    }
+}
+
+impl TryFrom<&str> for Accel {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        gtk4::accelerator_parse(name)
+            .map(|(key, mods)| Accel::new(key, mods))
+            .ok_or_else(|| format!("invalid key binding \"{name}\""))
+    }
+}
+
+/// Accepts either a single accelerator or a list of them
+fn deserialize_accels<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Accel>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let names = match OneOrMany::deserialize(deserializer)
+        .map_err(|_| serde::de::Error::custom("expected a key binding or a list of key bindings"))?
+    {
+        OneOrMany::One(name) => vec![name],
+        OneOrMany::Many(names) => names,
+    };
+
+    names
+        .iter()
+        .map(|name| Accel::try_from(name.as_str()).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn deserialize_tool_accels<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Vec<Accel>>, D::Error> {
+    #[derive(Deserialize)]
+    struct Accels(#[serde(deserialize_with = "deserialize_accels")] Vec<Accel>);
+
+    let map = BTreeMap::<String, Accels>::deserialize(deserializer)?;
+    Ok(map.into_iter().map(|(id, accels)| (id, accels.0)).collect())
+}
+
+fn deserialize_unit<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Unit, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_blend<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Blend, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_percent_of<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<PercentOf, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Appearance of the measurement lines
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrosshairStyle {
+    pub color: Color,
+    /// Line width in screen pixels
+    pub line_width: f64,
+}
+
+impl Default for CrosshairStyle {
+    fn default() -> Self {
+        CrosshairStyle {
+            color: Color::rgba(1.0, 0.0, 0.0, 1.0),
+            line_width: 1.0,
+        }
+    }
+}
+
+/// Appearance and placement of the tooltip next to the cursor
+/// Sizes and offsets are in screen pixels
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TooltipStyle {
+    pub font: String,
+    pub font_size: f64,
+    pub text_color: Color,
+    pub background: Color,
+    pub border: Color,
+    pub border_width: f64,
+    pub padding: f64,
+    pub radius: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+impl Default for TooltipStyle {
+    fn default() -> Self {
+        TooltipStyle {
+            font: "Sans".to_string(),
+            font_size: 20.0,
+            text_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+            background: Color::rgba(0.1, 0.1, 0.1, 0.9),
+            border: Color::rgba(0.2, 0.2, 0.2, 1.0),
+            border_width: 2.0,
+            padding: 8.0,
+            radius: 8.0,
+            offset_x: 25.0,
+            offset_y: 25.0,
+        }
+    }
+}
+
+/// Baseline grid drawn over the screenshot, spacing and offsets in dp
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridStyle {
+    /// Whether the grid and columns are shown at startup
+    pub visible: bool,
+    pub spacing: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub color: Color,
+}
+
+impl Default for GridStyle {
+    fn default() -> Self {
+        GridStyle {
+            visible: false,
+            spacing: 8.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            color: Color::rgba(0.0, 0.8, 1.0, 0.25),
+        }
+    }
+}
+
+/// Layout columns drawn with the grid, gutter and margin in dp
+/// A count of 0 hides them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnsStyle {
+    pub count: u32,
+    pub gutter: f64,
+    pub margin: f64,
+    pub color: Color,
+}
+
+impl Default for ColumnsStyle {
+    fn default() -> Self {
+        ColumnsStyle {
+            count: 12,
+            gutter: 24.0,
+            margin: 0.0,
+            color: Color::rgba(1.0, 0.0, 0.6, 0.12),
+        }
+    }
+}
+
+/// Guides dragged from the edges of the screen
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuidesStyle {
+    pub color: Color,
+}
+
+impl Default for GuidesStyle {
+    fn default() -> Self {
+        GuidesStyle {
+            color: Color::rgba(0.0, 0.9, 0.9, 1.0),
+        }
+    }
+}
+
+/// Element bounds read from the accessibility tree
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessibilityStyle {
+    /// Whether the element under the cursor is looked up from startup
+    pub enabled: bool,
+    pub color: Color,
+}
+
+impl Default for AccessibilityStyle {
+    fn default() -> Self {
+        AccessibilityStyle {
+            enabled: false,
+            color: Color::rgba(0.3, 0.9, 0.3, 1.0),
+        }
+    }
+}
+
+/// Side panel listing the pinned measurements
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryStyle {
+    /// Whether the panel is shown at startup
+    pub visible: bool,
+    /// Color of the pin selected in the panel
+    pub highlight: Color,
+}
+
+impl Default for HistoryStyle {
+    fn default() -> Self {
+        HistoryStyle {
+            visible: false,
+            highlight: Color::rgba(1.0, 0.8, 0.0, 1.0),
+        }
+    }
+}
+
+/// Appearance of the text labels, arrows and highlights drawn over the capture
+/// Sizes are in image pixels, so annotations are exported as they are shown
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnnotationStyle {
+    /// Color of the text, arrows and highlight borders
+    pub color: Color,
+    /// Fill behind the text of labels
+    pub background: Color,
+    /// Fill of highlighted areas
+    pub highlight: Color,
+    /// Handles and outline of the selected annotation
+    pub selected: Color,
+    pub line_width: f64,
+    pub font: String,
+    pub font_size: f64,
+}
+
+impl Default for AnnotationStyle {
+    fn default() -> Self {
+        AnnotationStyle {
+            color: Color::rgba(1.0, 0.18, 0.33, 1.0),
+            background: Color::rgba(1.0, 1.0, 1.0, 0.9),
+            highlight: Color::rgba(1.0, 0.85, 0.0, 0.3),
+            selected: Color::rgba(0.0, 0.6, 1.0, 1.0),
+            line_width: 3.0,
+            font: "Sans".to_string(),
+            font_size: 24.0,
+        }
+    }
+}
+
+/// Appearance of the redline laid out over a selected region
+/// Sizes are in image pixels, like those of annotations
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedlineStyle {
+    /// Color of the element outlines and spacing lines
+    pub color: Color,
+    pub text_color: Color,
+    /// Fill behind the sizes
+    pub background: Color,
+    pub line_width: f64,
+    pub font: String,
+    pub font_size: f64,
+}
+
+impl Default for RedlineStyle {
+    fn default() -> Self {
+        RedlineStyle {
+            color: Color::rgba(1.0, 0.18, 0.33, 1.0),
+            text_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+            background: Color::rgba(1.0, 0.18, 0.33, 0.9),
+            line_width: 1.0,
+            font: "Sans".to_string(),
+            font_size: 12.0,
+        }
+    }
+}
+
+/// Comparing the capture against a reference image
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompareStyle {
+    /// Opacity of the reference drawn over the capture, from 0 to 1
+    pub opacity: f64,
+    /// How the faded reference is combined with the capture
+    #[serde(deserialize_with = "deserialize_blend")]
+    pub blend: Blend,
+    /// How far in pixels the reference may move when aligned automatically
+    pub align_distance: u32,
+    /// Outline of the regions that differ from the reference
+    pub color: Color,
+}
+
+impl Default for CompareStyle {
+    fn default() -> Self {
+        CompareStyle {
+            opacity: 0.5,
+            blend: Blend::Normal,
+            align_distance: 64,
+            color: Color::rgba(1.0, 0.6, 0.0, 1.0),
+        }
+    }
+}
+
+/// Capturing the screen again while the overlay is open
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LiveMode {
+    /// Whether the screen is captured again at every interval from startup
+    pub enabled: bool,
+    /// Seconds between two captures
+    pub interval: f64,
+}
+
+impl Default for LiveMode {
+    fn default() -> Self {
+        LiveMode {
+            enabled: false,
+            interval: 1.0,
+        }
+    }
+}
+
+/// Keys bound to the app's actions, each one accepting a single key or a list
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    /// Closes the app
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub quit: Vec<Accel>,
+    /// Shows the command center, while held for modifier keys
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub command_center: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub copy: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub pin: Vec<Accel>,
+    /// Removes the last pinned measurement
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub undo: Vec<Accel>,
+    /// Puts back the last measurement removed by undo
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub redo: Vec<Accel>,
+    /// Shows or hides the list of pinned measurements
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub toggle_history: Vec<Accel>,
+    /// Saves the pinned measurements as CSV and JSON
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub export_history: Vec<Accel>,
+    /// Saves the annotated screenshot as PNG and SVG
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub export: Vec<Accel>,
+    /// Saves the capture with its pins and settings as a session file
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub save_session: Vec<Accel>,
+    /// Changes the text of the selected label
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub edit_annotation: Vec<Accel>,
+    /// Removes the selected annotation
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub delete_annotation: Vec<Accel>,
+    /// Switches to the next measurement unit
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub cycle_units: Vec<Accel>,
+    /// Measures the component enclosing the measured one
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub select_parent: Vec<Accel>,
+    /// Measures the component the parent was selected from again
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub select_child: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub increase_threshold: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub decrease_threshold: Vec<Accel>,
+    /// Shows or hides the grid and columns
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub toggle_grid: Vec<Accel>,
+    /// Starts or stops capturing the screen at every interval
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub toggle_live: Vec<Accel>,
+    /// Captures the screen once more, keeping pins and settings
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub recapture: Vec<Accel>,
+    /// Shows or hides the accessible element under the cursor
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub toggle_accessibility: Vec<Accel>,
+    /// Shows the reference faded, as a difference, split with the capture, then hides it
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub cycle_compare: Vec<Accel>,
+    /// Makes the current capture the reference later captures are compared against
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub keep_reference: Vec<Accel>,
+    /// Shows more of the reference, more opaque or with the split further right
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub increase_reference: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub decrease_reference: Vec<Accel>,
+    /// Switches the faded reference to the next blend mode
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub cycle_blend: Vec<Accel>,
+    /// Moves the reference to where its edges line up with the capture
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub align_reference: Vec<Accel>,
+    /// Makes the tools measure the reference instead of the capture
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub measure_reference: Vec<Accel>,
+    /// Move the reference by one pixel, or ten with Shift
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub nudge_reference_left: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub nudge_reference_right: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub nudge_reference_up: Vec<Accel>,
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub nudge_reference_down: Vec<Accel>,
+    /// Shows the list of key bindings
+    #[serde(deserialize_with = "deserialize_accels")]
+    pub help: Vec<Accel>,
+    /// Replaces the keys selecting a tool, by tool id
+    #[serde(deserialize_with = "deserialize_tool_accels")]
+    pub tools: BTreeMap<String, Vec<Accel>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            quit: vec![Accel::plain(Key::Escape)],
+            command_center: vec![Accel::plain(Key::Control_L), Accel::plain(Key::Control_R)],
+            copy: vec![Accel::control(Key::c)],
+            pin: vec![Accel::plain(Key::p)],
+            undo: vec![Accel::control(Key::z)],
+            redo: vec![Accel::control_shift(Key::z), Accel::control(Key::y)],
+            toggle_history: vec![Accel::control(Key::h)],
+            export_history: vec![Accel::control_shift(Key::s)],
+            export: vec![Accel::control(Key::s)],
+            save_session: vec![Accel::alt(Key::s)],
+            edit_annotation: vec![Accel::plain(Key::Return), Accel::plain(Key::KP_Enter)],
+            delete_annotation: vec![Accel::plain(Key::Delete), Accel::plain(Key::BackSpace)],
+            cycle_units: vec![Accel::plain(Key::u)],
+            select_parent: vec![Accel::plain(Key::Tab)],
+            // Shift turns Tab into ISO_Left_Tab
+            select_child: vec![Accel::shift(Key::ISO_Left_Tab)],
+            increase_threshold: vec![Accel::plain(Key::plus), Accel::plain(Key::KP_Add)],
+            decrease_threshold: vec![Accel::plain(Key::minus), Accel::plain(Key::KP_Subtract)],
+            toggle_grid: vec![Accel::plain(Key::g)],
+            toggle_live: vec![Accel::plain(Key::l)],
+            recapture: vec![Accel::plain(Key::F5)],
+            toggle_accessibility: vec![Accel::plain(Key::e)],
+            cycle_compare: vec![Accel::plain(Key::d)],
+            keep_reference: vec![Accel::control(Key::r)],
+            increase_reference: vec![Accel::plain(Key::bracketright)],
+            decrease_reference: vec![Accel::plain(Key::bracketleft)],
+            cycle_blend: vec![Accel::alt(Key::b)],
+            align_reference: vec![Accel::alt(Key::a)],
+            measure_reference: vec![Accel::alt(Key::m)],
+            nudge_reference_left: vec![Accel::alt(Key::Left)],
+            nudge_reference_right: vec![Accel::alt(Key::Right)],
+            nudge_reference_up: vec![Accel::alt(Key::Up)],
+            nudge_reference_down: vec![Accel::alt(Key::Down)],
+            help: vec![Accel::plain(Key::question)],
+            tools: BTreeMap::new(),
+        }
+    }
+}
+
+/// Complete user configuration
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Id of the tool selected at startup
+    pub tool: String,
+    /// Color distance above which an edge is detected
+    pub magnitude_threshold: f32,
+    /// Unit selected at startup
+    #[serde(deserialize_with = "deserialize_unit")]
+    pub units: Unit,
+    /// Font size in dp that rem values are relative to
+    pub base_font_size: f64,
+    /// Whether percentages are relative to the enclosing element or the screen
+    #[serde(deserialize_with = "deserialize_percent_of")]
+    pub percent_of: PercentOf,
+    pub crosshair: CrosshairStyle,
+    pub tooltip: TooltipStyle,
+    pub grid: GridStyle,
+    pub columns: ColumnsStyle,
+    pub guides: GuidesStyle,
+    pub live: LiveMode,
+    pub accessibility: AccessibilityStyle,
+    pub history: HistoryStyle,
+    pub compare: CompareStyle,
+    pub annotations: AnnotationStyle,
+    pub redline: RedlineStyle,
+    pub keys: KeyBindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            tool: "cross".to_string(),
+            magnitude_threshold: DEFAULT_THRESHOLD,
+            units: Unit::default(),
+            base_font_size: 16.0,
+            percent_of: PercentOf::default(),
+            crosshair: CrosshairStyle::default(),
+            tooltip: TooltipStyle::default(),
+            grid: GridStyle::default(),
+            columns: ColumnsStyle::default(),
+            guides: GuidesStyle::default(),
+            live: LiveMode::default(),
+            accessibility: AccessibilityStyle::default(),
+            history: HistoryStyle::default(),
+            compare: CompareStyle::default(),
+            annotations: AnnotationStyle::default(),
+            redline: RedlineStyle::default(),
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Checks the values that parse correctly but make no sense
+    fn validate(&self) -> Result<(), String> {
+        if !THRESHOLD_RANGE.contains(&self.magnitude_threshold) {
+            return Err(format!(
+                "magnitude_threshold must be between {} and {}, got {}",
+                THRESHOLD_RANGE.start(),
+                THRESHOLD_RANGE.end(),
+                self.magnitude_threshold
+            ));
+        }
+
+        let positive = [
+            ("base_font_size", self.base_font_size),
+            ("crosshair.line_width", self.crosshair.line_width),
+            ("tooltip.font_size", self.tooltip.font_size),
+            ("grid.spacing", self.grid.spacing),
+            ("live.interval", self.live.interval),
+            ("annotations.line_width", self.annotations.line_width),
+            ("annotations.font_size", self.annotations.font_size),
+            ("redline.line_width", self.redline.line_width),
+            ("redline.font_size", self.redline.font_size),
+        ];
+        for (name, value) in positive {
+            if value.is_nan() || value <= 0.0 {
+                return Err(format!("{name} must be greater than 0, got {value}"));
+            }
+        }
+
+        let non_negative = [
+            ("tooltip.border_width", self.tooltip.border_width),
+            ("tooltip.padding", self.tooltip.padding),
+            ("tooltip.radius", self.tooltip.radius),
+            ("columns.gutter", self.columns.gutter),
+            ("columns.margin", self.columns.margin),
+        ];
+        for (name, value) in non_negative {
+            if value.is_nan() || value < 0.0 {
+                return Err(format!("{name} must not be negative, got {value}"));
+            }
+        }
+
+        if self.live.interval > MAX_LIVE_INTERVAL {
+            return Err(format!(
+                "live.interval must be at most {MAX_LIVE_INTERVAL} seconds, got {}",
+                self.live.interval
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.compare.opacity) {
+            return Err(format!(
+                "compare.opacity must be between 0 and 1, got {}",
+                self.compare.opacity
+            ));
+        }
+
+        if self.tooltip.font.trim().is_empty() {
+            return Err("tooltip.font must not be empty".to_string());
+        }
+        if self.annotations.font.trim().is_empty() {
+            return Err("annotations.font must not be empty".to_string());
+        }
+        if self.redline.font.trim().is_empty() {
+            return Err("redline.font must not be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads the configuration file, returning the defaults if it does not exist
+pub fn load() -> Result<Config, ConfigError> {
+    let path = config_path();
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(ConfigError::Io(path, err)),
+    };
+
+    let config: Config =
+        toml::from_str(&contents).map_err(|err| ConfigError::Parse(path.clone(), err))?;
+    config
+        .validate()
+        .map_err(|msg| ConfigError::Invalid(path, msg))?;
+
+    Ok(config)
+}
+
+/// Writes the last used tool and threshold back to the configuration file
+/// Creates the file if needed and keeps every other setting as it was
+pub fn save_session(tool: &str, magnitude_threshold: f32) -> Result<(), ConfigError> {
+    let path = config_path();
+    let mut document = read_document(&path)?;
+
+    // Round to keep the file readable, f32 values don't survive the trip to f64 cleanly
+    let threshold = (f64::from(magnitude_threshold) * 100.0).round() / 100.0;
+    document["tool"] = toml_edit::value(tool);
+    document["magnitude_threshold"] = toml_edit::value(threshold);
+
+    write_document(&path, &document)
+}
+
+/// Guides saved for one app, positions in image pixels
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SavedGuides {
+    /// Y coordinates of the horizontal guides
+    horizontal: Vec<u32>,
+    /// X coordinates of the vertical guides
+    vertical: Vec<u32>,
+}
+
+/// Loads the guides saved for an app, none if it has no guides yet
+pub fn load_guides(app: &str) -> Result<Vec<Guide>, ConfigError> {
+    let path = guides_path();
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(ConfigError::Io(path, err)),
+    };
+
+    let mut apps: BTreeMap<String, SavedGuides> =
+        toml::from_str(&contents).map_err(|err| ConfigError::Parse(path, err))?;
+    let saved = apps.remove(app).unwrap_or_default();
+
+    let horizontal = saved
+        .horizontal
+        .into_iter()
+        .map(|position| Guide::new(Orientation::Horizontal, position));
+    let vertical = saved
+        .vertical
+        .into_iter()
+        .map(|position| Guide::new(Orientation::Vertical, position));
+    Ok(horizontal.chain(vertical).collect())
+}
+
+/// Writes the guides of an app to the guides file, leaving other apps as they were
+/// An app without guides is removed from the file
+pub fn save_guides(app: &str, guides: &[Guide]) -> Result<(), ConfigError> {
+    let path = guides_path();
+    let mut document = read_document(&path)?;
+
+    if guides.is_empty() {
+        document.remove(app);
+    } else {
+        let positions = |orientation| {
+            guides::positions(guides, orientation)
+                .into_iter()
+                .map(i64::from)
+                .collect::<toml_edit::Array>()
+        };
+        let mut table = toml_edit::Table::new();
+        table["horizontal"] = toml_edit::value(positions(Orientation::Horizontal));
+        table["vertical"] = toml_edit::value(positions(Orientation::Vertical));
+        document[app] = toml_edit::Item::Table(table);
+    }
+
+    write_document(&path, &document)
+}
+
+/// Reads a TOML file to be edited, an empty document if it does not exist
+fn read_document(path: &Path) -> Result<toml_edit::DocumentMut, ConfigError> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(ConfigError::Io(path.to_path_buf(), err)),
+    };
+
+    contents
+        .parse()
+        .map_err(|err| ConfigError::Edit(path.to_path_buf(), err))
+}
+
+/// Writes an edited TOML file, creating the config directory if needed
+fn write_document(path: &Path, document: &toml_edit::DocumentMut) -> Result<(), ConfigError> {
+    let io_error = |err| ConfigError::Io(path.to_path_buf(), err);
+    std::fs::create_dir_all(config_dir()).map_err(io_error)?;
+    std::fs::write(path, document.to_string()).map_err(io_error)
+}
@@ -0,0 +1,133 @@
+//! Waiting before the capture, so menus and tooltips can be opened first
+//!
+//! The wait either lasts a number of seconds, counted down in a small badge in
+//! the corner of the screen, or until `--capture-now` is run. The badge never
+//! takes the keyboard so open menus stay open, and is gone before the capture.
+
+use crate::screenshot;
+use gtk4::{Application, ApplicationWindow, Label, gio, glib, prelude::*};
+use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Run once the wait is over, to capture the screen and show the overlay
+type OnDone = Box<dyn FnOnce(&Application)>;
+
+/// A capture waiting to happen
+pub struct Countdown {
+    app: Application,
+    badge: ApplicationWindow,
+    label: Label,
+    /// Seconds left, `None` when waiting for `--capture-now`
+    remaining: Cell<Option<u32>>,
+    timer: RefCell<Option<glib::SourceId>>,
+    /// Keeps the app running between closing the badge and showing the overlay
+    hold: RefCell<Option<gio::ApplicationHoldGuard>>,
+    on_done: RefCell<Option<OnDone>>,
+}
+
+impl Countdown {
+    /// Starts waiting, then calls `on_done` once the badge is off the screen
+    pub fn start(
+        app: &Application,
+        seconds: Option<u32>,
+        on_done: impl FnOnce(&Application) + 'static,
+    ) -> Rc<Self> {
+        let label = Label::new(None);
+        label.add_css_class("countdown");
+        let badge = create_badge(app, &label);
+
+        let countdown = Rc::new(Countdown {
+            app: app.clone(),
+            badge,
+            label,
+            remaining: Cell::new(seconds),
+            timer: RefCell::new(None),
+            hold: RefCell::new(Some(app.hold())),
+            on_done: RefCell::new(Some(Box::new(on_done))),
+        });
+        countdown.update_label();
+
+        if seconds.is_some() {
+            let weak = Rc::downgrade(&countdown);
+            let timer = glib::timeout_add_local(Duration::from_secs(1), move || {
+                let Some(countdown) = weak.upgrade() else {
+                    return glib::ControlFlow::Break;
+                };
+                countdown.tick()
+            });
+            *countdown.timer.borrow_mut() = Some(timer);
+        }
+
+        countdown.badge.present();
+        countdown
+    }
+
+    /// Whether the capture is still to happen
+    pub fn is_waiting(&self) -> bool {
+        self.on_done.borrow().is_some()
+    }
+
+    /// Captures right away, returning false if the capture already happened
+    pub fn finish(&self) -> bool {
+        let Some(on_done) = self.on_done.take() else {
+            return false;
+        };
+        if let Some(timer) = self.timer.take() {
+            timer.remove();
+        }
+        self.badge.close();
+
+        // The hold guard is released once the overlay has its own window
+        let app = self.app.clone();
+        let hold = self.hold.take();
+        glib::timeout_add_local_once(screenshot::HIDE_DELAY, move || {
+            on_done(&app);
+            drop(hold);
+        });
+        true
+    }
+
+    /// Counts one second down, capturing when none are left
+    fn tick(&self) -> glib::ControlFlow {
+        let remaining = self.remaining.get().unwrap_or(0).saturating_sub(1);
+        self.remaining.set(Some(remaining));
+        if remaining > 0 {
+            self.update_label();
+            return glib::ControlFlow::Continue;
+        }
+
+        // The timer is dropped by returning Break rather than removed in finish
+        self.timer.take();
+        self.finish();
+        glib::ControlFlow::Break
+    }
+
+    fn update_label(&self) {
+        match self.remaining.get() {
+            Some(seconds) => self.label.set_label(&format!("Capturing in {seconds}")),
+            None => self.label.set_label("Waiting for --capture-now"),
+        }
+    }
+}
+
+/// Small window in the top right corner of the screen, leaving the keyboard alone
+fn create_badge(app: &Application, label: &Label) -> ApplicationWindow {
+    let badge = ApplicationWindow::builder()
+        .application(app)
+        .child(label)
+        .can_target(false)
+        .build();
+    badge.add_css_class("countdown-window");
+
+    badge.init_layer_shell();
+    badge.set_layer(Layer::Overlay);
+    badge.set_keyboard_mode(KeyboardMode::None);
+    badge.set_anchor(Edge::Top, true);
+    badge.set_anchor(Edge::Right, true);
+    badge.set_margin(Edge::Top, 16);
+    badge.set_margin(Edge::Right, 16);
+
+    badge
+}
@@ -0,0 +1,143 @@
+//! Saving the annotated screenshot as PNG and SVG files, the measurements as CSV and JSON,
+//! and redlines as JSON specs
+//!
+//! The images are rendered at the native resolution of the capture by
+//! replaying the same drawing code used on screen. In the SVG the screenshot is
+//! embedded as a bitmap and the measurements stay vector paths on top of it.
+
+use gtk4::cairo;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+/// Error raised while rendering or writing an export
+#[derive(Debug)]
+pub enum ExportError {
+    Cairo(cairo::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Cairo(err) => write!(f, "Rendering failed: {}", err),
+            ExportError::Io(err) => write!(f, "Writing failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<cairo::Error> for ExportError {
+    fn from(err: cairo::Error) -> Self {
+        ExportError::Cairo(err)
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(err: io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+impl From<cairo::IoError> for ExportError {
+    fn from(err: cairo::IoError) -> Self {
+        match err {
+            cairo::IoError::Cairo(err) => ExportError::Cairo(err),
+            cairo::IoError::Io(err) => ExportError::Io(err),
+        }
+    }
+}
+
+/// Directory exports are written to, the user's pictures folder if there is one
+pub fn export_dir() -> PathBuf {
+    glib::user_special_dir(glib::UserDirectory::Pictures).unwrap_or_else(glib::home_dir)
+}
+
+/// Renders the scene into a PNG file of the given size
+pub fn export_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    draw: impl Fn(&cairo::Context),
+) -> Result<(), ExportError> {
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width as i32, height as i32)?;
+    let cr = cairo::Context::new(&surface)?;
+    draw(&cr);
+    drop(cr);
+
+    let mut file = File::create(path)?;
+    surface.write_to_png(&mut file)?;
+
+    Ok(())
+}
+
+/// Renders the scene into an SVG file of the given size
+pub fn export_svg(
+    path: &Path,
+    width: u32,
+    height: u32,
+    draw: impl Fn(&cairo::Context),
+) -> Result<(), ExportError> {
+    let mut surface = cairo::SvgSurface::new(width as f64, height as f64, Some(path))?;
+    surface.set_document_unit(cairo::SvgUnit::Px);
+    let cr = cairo::Context::new(&surface)?;
+    draw(&cr);
+    drop(cr);
+
+    surface.finish();
+    surface.status()?;
+
+    Ok(())
+}
+
+/// Exports the scene as both PNG and SVG with a timestamped name
+/// Returns the paths of the written files
+pub fn export_annotated(
+    width: u32,
+    height: u32,
+    draw: impl Fn(&cairo::Context),
+) -> Result<Vec<PathBuf>, ExportError> {
+    let dir = export_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp_nanos();
+    let png_path = dir.join(format!("rustyruler_{}.png", timestamp));
+    let svg_path = dir.join(format!("rustyruler_{}.svg", timestamp));
+
+    export_png(&png_path, width, height, &draw)?;
+    export_svg(&svg_path, width, height, &draw)?;
+
+    Ok(vec![png_path, svg_path])
+}
+
+/// Writes the pinned measurements as CSV and JSON with a timestamped name
+/// Returns the paths of the written files
+pub fn export_measurements(csv: &str, json: &str) -> Result<Vec<PathBuf>, ExportError> {
+    let dir = export_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp_nanos();
+    let csv_path = dir.join(format!("rustyruler_{}.csv", timestamp));
+    let json_path = dir.join(format!("rustyruler_{}.json", timestamp));
+
+    std::fs::write(&csv_path, csv)?;
+    std::fs::write(&json_path, json)?;
+
+    Ok(vec![csv_path, json_path])
+}
+
+/// Writes the spec of a redline as JSON with a timestamped name
+/// Returns the path of the written file
+pub fn export_spec(json: &str) -> Result<PathBuf, ExportError> {
+    let dir = export_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp_nanos();
+    let path = dir.join(format!("rustyruler_{}_spec.json", timestamp));
+    std::fs::write(&path, json)?;
+
+    Ok(path)
+}
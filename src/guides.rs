@@ -0,0 +1,178 @@
+//! Guides dragged from the top and left edges of the screenshot
+//!
+//! Horizontal guides are pulled down from the top edge and vertical ones from
+//! the left edge. Dragging a guide back onto the edge it came from removes it.
+
+use crate::config::Config;
+use gtk4::cairo;
+use rustyruler_core::guides::{self, Guide, Orientation};
+use rustyruler_core::units::{Axis, Units};
+
+/// What a press at some point of the screenshot would start dragging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grab {
+    /// The guide at this index
+    Existing(usize),
+    /// A new guide pulled from an edge
+    New(Orientation),
+}
+
+/// Finds what can be dragged at the given image point
+/// `distance` is how close in image pixels the point must be to a guide or an edge
+pub fn grab_at(guides: &[Guide], x: f64, y: f64, distance: f64) -> Option<Grab> {
+    let existing = guides
+        .iter()
+        .enumerate()
+        .map(|(index, guide)| {
+            let along = match guide.orientation {
+                Orientation::Horizontal => y,
+                Orientation::Vertical => x,
+            };
+            (index, (along - f64::from(guide.position)).abs())
+        })
+        .filter(|(_, gap)| *gap <= distance)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((index, _)) = existing {
+        return Some(Grab::Existing(index));
+    }
+
+    // In the top left corner the closest edge wins
+    match (y <= distance, x <= distance) {
+        (true, true) if x < y => Some(Grab::New(Orientation::Vertical)),
+        (true, _) => Some(Grab::New(Orientation::Horizontal)),
+        (false, true) => Some(Grab::New(Orientation::Vertical)),
+        (false, false) => None,
+    }
+}
+
+/// Formats a distance between guides, or between a guide and the cursor
+fn distance_text(pixels: u32, orientation: Orientation, units: &Units) -> String {
+    // Vertical guides are spaced horizontally
+    let (axis, reference) = match orientation {
+        Orientation::Vertical => (Axis::Horizontal, units.context.screen_size.0),
+        Orientation::Horizontal => (Axis::Vertical, units.context.screen_size.1),
+    };
+    format!(
+        "{} {}",
+        units.format(pixels, axis, reference),
+        units.unit.label()
+    )
+}
+
+/// Draws a small label centered on the given point
+fn draw_label(cr: &cairo::Context, text: &str, x: f64, y: f64, config: &Config, scale: f64) {
+    let style = &config.tooltip;
+    cr.select_font_face(
+        &style.font,
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Normal,
+    );
+    cr.set_font_size(style.font_size * 0.6 / scale);
+
+    let Ok(extents) = cr.text_extents(text) else {
+        return;
+    };
+    let padding = style.padding / 2.0 / scale;
+    let (width, height) = (extents.width(), extents.height());
+
+    style.background.apply(cr);
+    cr.rectangle(
+        x - width / 2.0 - padding,
+        y - height / 2.0 - padding,
+        width + 2.0 * padding,
+        height + 2.0 * padding,
+    );
+    let _ = cr.fill();
+
+    style.text_color.apply(cr);
+    cr.move_to(
+        x - width / 2.0 - extents.x_bearing(),
+        y - height / 2.0 - extents.y_bearing(),
+    );
+    let _ = cr.show_text(text);
+}
+
+/// Draws the guides, the distance between neighboring guides along the top and
+/// left edges, and the distance from the cursor to every guide
+#[allow(clippy::too_many_arguments)]
+pub fn draw(
+    cr: &cairo::Context,
+    guides: &[Guide],
+    cursor: Option<(u32, u32)>,
+    units: &Units,
+    config: &Config,
+    scale: f64,
+    img_width: u32,
+    img_height: u32,
+) {
+    if guides.is_empty() {
+        return;
+    }
+    let (width, height) = (f64::from(img_width), f64::from(img_height));
+
+    config.guides.color.apply(cr);
+    cr.set_line_width(1.0 / scale);
+    for guide in guides {
+        let position = f64::from(guide.position);
+        match guide.orientation {
+            Orientation::Horizontal => {
+                cr.move_to(0.0, position);
+                cr.line_to(width, position);
+            }
+            Orientation::Vertical => {
+                cr.move_to(position, 0.0);
+                cr.line_to(position, height);
+            }
+        }
+    }
+    let _ = cr.stroke();
+
+    // Lines from the cursor to each guide, dashed to tell them from measurements
+    if let Some((x, y)) = cursor {
+        let (x, y) = (f64::from(x), f64::from(y));
+        cr.set_dash(&[4.0 / scale, 4.0 / scale], 0.0);
+        for guide in guides {
+            let position = f64::from(guide.position);
+            match guide.orientation {
+                Orientation::Horizontal => {
+                    cr.move_to(x, y);
+                    cr.line_to(x, position);
+                }
+                Orientation::Vertical => {
+                    cr.move_to(x, y);
+                    cr.line_to(position, y);
+                }
+            }
+        }
+        let _ = cr.stroke();
+        cr.set_dash(&[], 0.0);
+
+        for guide in guides {
+            let position = f64::from(guide.position);
+            let (along, label_x, label_y) = match guide.orientation {
+                Orientation::Horizontal => (y, x, (y + position) / 2.0),
+                Orientation::Vertical => (x, (x + position) / 2.0, y),
+            };
+            let gap = (along - position).abs() as u32;
+            if gap > 0 {
+                let text = distance_text(gap, guide.orientation, units);
+                draw_label(cr, &text, label_x, label_y, config, scale);
+            }
+        }
+    }
+
+    // Gaps between neighbors, next to the edge the guides were pulled from
+    let margin = 40.0 / scale;
+    for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+        let positions = guides::positions(guides, orientation);
+        for pair in positions.windows(2) {
+            let middle = f64::from(pair[0] + pair[1]) / 2.0;
+            let (label_x, label_y) = match orientation {
+                Orientation::Horizontal => (margin, middle),
+                Orientation::Vertical => (middle, margin),
+            };
+            let text = distance_text(pair[1] - pair[0], orientation, units);
+            draw_label(cr, &text, label_x, label_y, config, scale);
+        }
+    }
+}
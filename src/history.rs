@@ -0,0 +1,273 @@
+//! Measurements pinned during a session, with undo and redo
+//!
+//! Every pin records the tool, the measurement with its threshold, and when it
+//! was taken. The side panel lists them, highlights the one clicked on the
+//! screenshot, and the whole list can be exported as CSV and JSON.
+
+use gtk4::{Box, Label, ListBox, ScrolledWindow, glib, prelude::*};
+use rustyruler_core::Measurement;
+use serde::Serialize;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// A measurement kept on screen along with the tool that took it
+#[derive(Clone)]
+pub struct Pin {
+    /// Registry position of the tool
+    pub tool: usize,
+    pub measurement: Measurement,
+    /// Local time the measurement was pinned at
+    pub taken: glib::DateTime,
+}
+
+/// Pinned measurements, oldest first, and the ones undone since the last pin
+#[derive(Default, Clone)]
+pub struct History {
+    pins: Vec<Pin>,
+    /// Pins removed by undo, the most recently removed last
+    undone: Vec<Pin>,
+    /// Pin highlighted from the panel
+    selected: Option<usize>,
+}
+
+impl History {
+    /// History of pins saved earlier, oldest first, with nothing to redo
+    pub fn restore(pins: Vec<Pin>) -> Self {
+        History {
+            pins,
+            ..History::default()
+        }
+    }
+
+    /// Pins a measurement, which can no longer redo what was undone
+    pub fn pin(&mut self, tool: usize, measurement: Measurement) {
+        let taken = glib::DateTime::now_local()
+            .or_else(|_| glib::DateTime::now_utc())
+            .expect("The current time is available");
+        self.pins.push(Pin {
+            tool,
+            measurement,
+            taken,
+        });
+        self.undone.clear();
+    }
+
+    /// Removes the last pin, returns false if there was none
+    pub fn undo(&mut self) -> bool {
+        let Some(pin) = self.pins.pop() else {
+            return false;
+        };
+        self.undone.push(pin);
+        if self.selected >= Some(self.pins.len()) {
+            self.selected = None;
+        }
+        true
+    }
+
+    /// Puts back the last pin removed by undo, returns false if there was none
+    pub fn redo(&mut self) -> bool {
+        let Some(pin) = self.undone.pop() else {
+            return false;
+        };
+        self.pins.push(pin);
+        true
+    }
+
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Highlights the pin at the given index, or none
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|&index| index < self.pins.len());
+    }
+}
+
+/// One pinned measurement as written to the CSV and JSON exports
+/// Positions and sizes are in image pixels, the text in the unit shown on screen
+#[derive(Debug, Serialize)]
+pub struct Record {
+    pub tool: String,
+    /// Tooltip text of the measurement, with its unit
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub threshold: f32,
+    /// Time the measurement was pinned at, in ISO 8601
+    pub time: String,
+}
+
+impl Record {
+    /// Fills in the record of a pin, with the id and text of the tool that took it
+    pub fn new(pin: &Pin, tool: &str, text: String) -> Self {
+        let measurement = &pin.measurement;
+        Record {
+            tool: tool.to_string(),
+            text,
+            x: measurement.x,
+            y: measurement.y,
+            left: measurement.limits.left,
+            top: measurement.limits.top,
+            right: measurement.limits.right,
+            bottom: measurement.limits.bottom,
+            width: measurement.width(),
+            height: measurement.height(),
+            threshold: measurement.threshold,
+            time: pin
+                .taken
+                .format_iso8601()
+                .map(|time| time.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Writes the records as CSV with a header row
+pub fn to_csv(records: &[Record]) -> String {
+    let mut csv = String::from("tool,text,x,y,left,top,right,bottom,width,height,threshold,time\n");
+    let optional = |value: Option<u32>| value.map(|value| value.to_string()).unwrap_or_default();
+
+    for record in records {
+        let fields = [
+            csv_field(&record.tool),
+            csv_field(&record.text),
+            record.x.to_string(),
+            record.y.to_string(),
+            record.left.to_string(),
+            record.top.to_string(),
+            record.right.to_string(),
+            record.bottom.to_string(),
+            optional(record.width),
+            optional(record.height),
+            record.threshold.to_string(),
+            csv_field(&record.time),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Quotes a field when it contains a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes the records as a pretty-printed JSON array
+pub fn to_json(records: &[Record]) -> String {
+    serde_json::to_string_pretty(records).expect("Records serialize to JSON")
+}
+
+/// Side panel listing the pinned measurements, hidden until toggled
+pub struct HistoryPanel {
+    container: Box,
+    list: ListBox,
+    /// Set while the rows are replaced, so the selection changes it makes are not reported
+    updating: Rc<Cell<bool>>,
+}
+
+impl HistoryPanel {
+    /// `on_select` receives the index of the clicked row, or `None` once unselected
+    pub fn new(visible: bool, on_select: impl Fn(Option<usize>) + 'static) -> Rc<Self> {
+        let container = Box::builder()
+            .css_classes(vec!["history-panel"])
+            .orientation(gtk4::Orientation::Vertical)
+            .spacing(8)
+            .halign(gtk4::Align::End)
+            .valign(gtk4::Align::Center)
+            .visible(visible)
+            .build();
+
+        let title = Label::builder()
+            .label("Measurements")
+            .css_classes(vec!["history-title"])
+            .halign(gtk4::Align::Start)
+            .build();
+        container.append(&title);
+
+        // Rows never take the focus, so keys keep reaching the overlay
+        let list = ListBox::builder()
+            .css_classes(vec!["history-list"])
+            .selection_mode(gtk4::SelectionMode::Single)
+            .can_focus(false)
+            .build();
+        let updating = Rc::new(Cell::new(false));
+        let updating_clone = updating.clone();
+        list.connect_row_selected(move |_, row| {
+            if !updating_clone.get() {
+                on_select(row.map(|row| row.index() as usize));
+            }
+        });
+        let scrolled = ScrolledWindow::builder()
+            .child(&list)
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .propagate_natural_height(true)
+            .propagate_natural_width(true)
+            .max_content_height(480)
+            .build();
+        container.append(&scrolled);
+
+        Rc::new(HistoryPanel {
+            container,
+            list,
+            updating,
+        })
+    }
+
+    pub fn widget(&self) -> &Box {
+        &self.container
+    }
+
+    pub fn toggle(&self) {
+        self.container.set_visible(!self.container.is_visible());
+    }
+
+    /// Replaces the listed rows, keeping the selected one highlighted
+    pub fn update(&self, rows: &[String], selected: Option<usize>) {
+        self.updating.set(true);
+        while let Some(row) = self.list.row_at_index(0) {
+            self.list.remove(&row);
+        }
+
+        if rows.is_empty() {
+            let empty = Label::builder()
+                .label("No pinned measurements")
+                .css_classes(vec!["history-empty"])
+                .build();
+            self.list.append(&empty);
+        }
+        for text in rows {
+            let label = Label::builder()
+                .label(text)
+                .halign(gtk4::Align::Start)
+                .build();
+            self.list.append(&label);
+        }
+
+        let mut index = 0;
+        while let Some(row) = self.list.row_at_index(index) {
+            row.set_can_focus(false);
+            row.set_selectable(!rows.is_empty());
+            index += 1;
+        }
+
+        let row = selected.and_then(|index| self.list.row_at_index(index as i32));
+        self.list.select_row(row.as_ref());
+        self.updating.set(false);
+    }
+}
@@ -0,0 +1,280 @@
+//! Mapping from key presses to the app's actions
+//!
+//! Bindings come from the `[keys]` section of the configuration and use GTK
+//! accelerator syntax, e.g. `Escape`, `<Control>c` or `question`.
+
+use crate::config::{Accel, KeyBindings};
+use crate::tools::ToolRegistry;
+use gtk4::gdk::{Key, ModifierType};
+
+/// Modifiers that must match exactly for a binding to trigger
+/// Shift is left out so keys like `question` work whatever the layout needs to type them
+const STRICT_MODIFIERS: ModifierType = ModifierType::CONTROL_MASK
+    .union(ModifierType::ALT_MASK)
+    .union(ModifierType::SUPER_MASK);
+
+/// Something the user can trigger from the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Selects the tool at the given registry position
+    SwitchTool(usize),
+    /// Shows the command center, while held for modifier keys
+    ToggleCommandCenter,
+    /// Copies the current measurement to the clipboard
+    Copy,
+    /// Keeps the current measurement on screen
+    Pin,
+    /// Removes the last pinned measurement
+    Undo,
+    /// Puts back the last measurement removed by undo
+    Redo,
+    /// Shows or hides the list of pinned measurements
+    ToggleHistory,
+    /// Saves the pinned measurements as CSV and JSON
+    ExportHistory,
+    /// Saves the annotated screenshot as PNG and SVG
+    Export,
+    /// Saves the capture with its pins and settings as a session file
+    SaveSession,
+    /// Changes the text of the selected label
+    EditAnnotation,
+    /// Removes the selected annotation
+    DeleteAnnotation,
+    /// Switches to the next measurement unit
+    CycleUnits,
+    /// Measures the component enclosing the measured one
+    SelectParent,
+    /// Measures the component the parent was selected from again
+    SelectChild,
+    IncreaseThreshold,
+    DecreaseThreshold,
+    /// Shows or hides the grid and columns
+    ToggleGrid,
+    /// Starts or stops capturing the screen at every interval
+    ToggleLive,
+    /// Captures the screen once more
+    Recapture,
+    /// Shows or hides the accessible element under the cursor
+    ToggleAccessibility,
+    /// Switches to the next way of comparing the capture with the reference
+    CycleCompare,
+    /// Makes the current capture the reference
+    KeepReference,
+    /// Shows more of the reference
+    IncreaseReference,
+    /// Shows less of the reference
+    DecreaseReference,
+    /// Moves the reference by the given number of pixels
+    NudgeReference(i32, i32),
+    /// Switches the faded reference to the next blend mode
+    CycleBlend,
+    /// Lines the reference up with the capture
+    AlignReference,
+    /// Switches the tools between measuring the capture and the reference
+    MeasureReference,
+    /// Shows or hides the list of key bindings
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Description shown in the help overlay
+    pub fn describe(self, tools: &ToolRegistry) -> String {
+        match self {
+            Action::SwitchTool(index) => match tools.tools().get(index) {
+                Some(tool) => format!("{} tool", tool.name()),
+                None => format!("Tool {}", index + 1),
+            },
+            Action::ToggleCommandCenter => "Command center".to_string(),
+            Action::Copy => "Copy measurement".to_string(),
+            Action::Pin => "Pin measurement".to_string(),
+            Action::Undo => "Remove last pin".to_string(),
+            Action::Redo => "Restore removed pin".to_string(),
+            Action::ToggleHistory => "Toggle pinned measurements".to_string(),
+            Action::ExportHistory => "Export pinned measurements".to_string(),
+            Action::Export => "Export annotated screenshot".to_string(),
+            Action::SaveSession => "Save session".to_string(),
+            Action::EditAnnotation => "Edit selected label".to_string(),
+            Action::DeleteAnnotation => "Delete selected annotation".to_string(),
+            Action::CycleUnits => "Next unit".to_string(),
+            Action::SelectParent => "Select enclosing component".to_string(),
+            Action::SelectChild => "Select enclosed component".to_string(),
+            Action::IncreaseThreshold => "Increase threshold".to_string(),
+            Action::DecreaseThreshold => "Decrease threshold".to_string(),
+            Action::ToggleGrid => "Toggle grid and columns".to_string(),
+            Action::ToggleLive => "Toggle live capture".to_string(),
+            Action::Recapture => "Capture the screen again".to_string(),
+            Action::ToggleAccessibility => "Toggle accessible element bounds".to_string(),
+            Action::CycleCompare => "Next comparison with the reference".to_string(),
+            Action::KeepReference => "Use this capture as the reference".to_string(),
+            Action::IncreaseReference => "Show more of the reference".to_string(),
+            Action::DecreaseReference => "Show less of the reference".to_string(),
+            Action::CycleBlend => "Next blend mode of the reference".to_string(),
+            Action::AlignReference => "Align the reference with the capture".to_string(),
+            Action::MeasureReference => "Measure the reference".to_string(),
+            Action::NudgeReference(x, y) => {
+                let direction = match (x.signum(), y.signum()) {
+                    (-1, _) => "left",
+                    (1, _) => "right",
+                    (_, -1) => "up",
+                    _ => "down",
+                };
+                format!("Nudge reference {direction}")
+            }
+            Action::Help => "Show this help".to_string(),
+            Action::Quit => "Quit".to_string(),
+        }
+    }
+}
+
+/// Returns true for keys that only act as modifiers
+pub fn is_modifier(key: Key) -> bool {
+    matches!(
+        key,
+        Key::Control_L
+            | Key::Control_R
+            | Key::Shift_L
+            | Key::Shift_R
+            | Key::Alt_L
+            | Key::Alt_R
+            | Key::Super_L
+            | Key::Super_R
+            | Key::Meta_L
+            | Key::Meta_R
+    )
+}
+
+impl Accel {
+    /// Checks whether a key event with the given modifier state triggers this accelerator
+    pub fn matches(&self, key: Key, state: ModifierType) -> bool {
+        if self.key.to_lower() != key.to_lower() {
+            return false;
+        }
+
+        // The state of a modifier key event already contains the modifier itself
+        if is_modifier(key) {
+            return true;
+        }
+
+        (state & STRICT_MODIFIERS) == (self.mods & STRICT_MODIFIERS)
+            && (!self.mods.contains(ModifierType::SHIFT_MASK)
+                || state.contains(ModifierType::SHIFT_MASK))
+    }
+
+    /// Human readable form, e.g. `Ctrl+C`
+    pub fn label(&self) -> String {
+        gtk4::accelerator_get_label(self.key, self.mods).to_string()
+    }
+}
+
+/// Every configured binding, in the order they are listed in the help
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Accel>)>,
+}
+
+impl Keymap {
+    /// Builds the keymap from the configuration
+    /// Tools keep their own shortcut and their position in the command center
+    /// (1 to 9) unless the configuration overrides them
+    pub fn new(keys: &KeyBindings, tools: &ToolRegistry) -> Self {
+        let mut bindings = Vec::new();
+
+        for (index, tool) in tools.tools().iter().enumerate() {
+            let accels = match keys.tools.get(tool.id()) {
+                Some(accels) => accels.clone(),
+                None => {
+                    let mut accels = vec![Accel::new(tool.shortcut(), ModifierType::empty())];
+                    if index < 9
+                        && let Some(digit) = Key::from_name((index + 1).to_string())
+                    {
+                        accels.push(Accel::new(digit, ModifierType::empty()));
+                    }
+                    accels
+                }
+            };
+            bindings.push((Action::SwitchTool(index), accels));
+        }
+
+        for id in keys.tools.keys() {
+            if tools.index_of(id).is_none() {
+                eprintln!(
+                    "Unknown tool \"{id}\" in [keys.tools], expected one of: {}",
+                    tools.ids().join(", ")
+                );
+            }
+        }
+
+        bindings.extend([
+            (Action::ToggleCommandCenter, keys.command_center.clone()),
+            (Action::Copy, keys.copy.clone()),
+            (Action::Pin, keys.pin.clone()),
+            (Action::Undo, keys.undo.clone()),
+            (Action::Redo, keys.redo.clone()),
+            (Action::ToggleHistory, keys.toggle_history.clone()),
+            (Action::ExportHistory, keys.export_history.clone()),
+            (Action::Export, keys.export.clone()),
+            (Action::SaveSession, keys.save_session.clone()),
+            (Action::EditAnnotation, keys.edit_annotation.clone()),
+            (Action::DeleteAnnotation, keys.delete_annotation.clone()),
+            (Action::CycleUnits, keys.cycle_units.clone()),
+            (Action::SelectParent, keys.select_parent.clone()),
+            (Action::SelectChild, keys.select_child.clone()),
+            (Action::IncreaseThreshold, keys.increase_threshold.clone()),
+            (Action::DecreaseThreshold, keys.decrease_threshold.clone()),
+            (Action::ToggleGrid, keys.toggle_grid.clone()),
+            (Action::ToggleLive, keys.toggle_live.clone()),
+            (Action::Recapture, keys.recapture.clone()),
+            (
+                Action::ToggleAccessibility,
+                keys.toggle_accessibility.clone(),
+            ),
+            (Action::CycleCompare, keys.cycle_compare.clone()),
+            (Action::KeepReference, keys.keep_reference.clone()),
+            (Action::IncreaseReference, keys.increase_reference.clone()),
+            (Action::DecreaseReference, keys.decrease_reference.clone()),
+            (
+                Action::NudgeReference(-1, 0),
+                keys.nudge_reference_left.clone(),
+            ),
+            (
+                Action::NudgeReference(1, 0),
+                keys.nudge_reference_right.clone(),
+            ),
+            (
+                Action::NudgeReference(0, -1),
+                keys.nudge_reference_up.clone(),
+            ),
+            (
+                Action::NudgeReference(0, 1),
+                keys.nudge_reference_down.clone(),
+            ),
+            (Action::CycleBlend, keys.cycle_blend.clone()),
+            (Action::AlignReference, keys.align_reference.clone()),
+            (Action::MeasureReference, keys.measure_reference.clone()),
+            (Action::Help, keys.help.clone()),
+            (Action::Quit, keys.quit.clone()),
+        ]);
+
+        Keymap { bindings }
+    }
+
+    /// Finds the action bound to a key event
+    /// Bindings asking for Shift win over those leaving it out, so `<Control><Shift>z`
+    /// can do something else than `<Control>z`
+    pub fn lookup(&self, key: Key, state: ModifierType) -> Option<Action> {
+        self.bindings
+            .iter()
+            .flat_map(|(action, accels)| accels.iter().map(move |accel| (*action, accel)))
+            .filter(|(_, accel)| accel.matches(key, state))
+            .min_by_key(|(_, accel)| !accel.mods.contains(ModifierType::SHIFT_MASK))
+            .map(|(action, _)| action)
+    }
+
+    /// Bound actions with the labels of their keys, for the help overlay
+    pub fn entries(&self) -> impl Iterator<Item = (Action, Vec<String>)> + '_ {
+        self.bindings
+            .iter()
+            .filter(|(_, accels)| !accels.is_empty())
+            .map(|(action, accels)| (*action, accels.iter().map(Accel::label).collect()))
+    }
+}
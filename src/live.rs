@@ -0,0 +1,90 @@
+//! Live mode, capturing the screen again while the overlay is open
+//!
+//! Each capture makes the overlay transparent, waits for the compositor to
+//! show the screen without it, then runs grim on a worker thread so the
+//! overlay keeps responding. The new image is handed back on the main thread.
+
+use crate::screenshot;
+use gtk4::{ApplicationWindow, Label, gio, glib, prelude::*};
+use image::RgbImage;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Captures the screen behind the overlay, once or at a fixed interval
+pub struct LiveCapture {
+    window: ApplicationWindow,
+    /// Shown while live mode is on
+    indicator: Label,
+    interval: Duration,
+    /// Timer of the periodic captures, while live mode is on
+    timer: RefCell<Option<glib::SourceId>>,
+    /// Whether a capture is in progress, so they never overlap
+    busy: Cell<bool>,
+    on_capture: Box<dyn Fn(RgbImage)>,
+}
+
+impl LiveCapture {
+    /// `on_capture` receives every new screenshot, on the main thread
+    pub fn new(
+        window: &ApplicationWindow,
+        indicator: &Label,
+        interval: Duration,
+        on_capture: impl Fn(RgbImage) + 'static,
+    ) -> Rc<Self> {
+        Rc::new(LiveCapture {
+            window: window.clone(),
+            indicator: indicator.clone(),
+            interval,
+            timer: RefCell::new(None),
+            busy: Cell::new(false),
+            on_capture: Box::new(on_capture),
+        })
+    }
+
+    /// Starts or stops capturing at every interval
+    pub fn toggle(self: &Rc<Self>) {
+        if let Some(timer) = self.timer.take() {
+            timer.remove();
+            self.indicator.set_visible(false);
+            return;
+        }
+
+        let live = Rc::downgrade(self);
+        let timer = glib::timeout_add_local(self.interval, move || {
+            let Some(live) = live.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            live.capture();
+            glib::ControlFlow::Continue
+        });
+        *self.timer.borrow_mut() = Some(timer);
+        self.indicator.set_visible(true);
+        self.capture();
+    }
+
+    /// Captures the screen once, unless a capture is already in progress
+    pub fn capture(self: &Rc<Self>) {
+        if self.busy.replace(true) {
+            return;
+        }
+
+        // Hidden for a single frame, the overlay stays mapped and keeps its input
+        self.window.set_opacity(0.0);
+
+        let live = self.clone();
+        glib::timeout_add_local_once(screenshot::HIDE_DELAY, move || {
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(screenshot::capture_image).await;
+                live.window.set_opacity(1.0);
+                live.busy.set(false);
+
+                match result {
+                    Ok(Ok(img)) => (live.on_capture)(img),
+                    Ok(Err(err)) => eprintln!("Error capturing screenshot: {:?}", err),
+                    Err(_) => eprintln!("Error capturing screenshot: the capture thread panicked"),
+                }
+            });
+        });
+    }
+}
@@ -1,13 +1,344 @@
+mod accessibility;
+mod annotations;
+mod assets;
+mod compare;
+mod compositor;
+mod config;
+mod countdown;
+mod export;
+mod guides;
+mod history;
+mod keybindings;
+mod live;
+mod redline;
 mod screenshot;
+mod session;
+mod tools;
 mod ui;
 
-use gtk4::{Application, prelude::*};
-use ui::build_ui;
+use countdown::Countdown;
+use gtk4::{
+    Application,
+    gio::{self, ApplicationCommandLine, ApplicationFlags},
+    glib::{self, ExitCode, OptionArg, OptionFlags},
+    prelude::*,
+};
+use std::cell::{OnceCell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+use ui::{build_color_picker, build_compare, build_ui, load_stylesheets, open_session};
 
 const APP_ID: &str = "com.rodrig20.rustyruler";
 
-fn main() {
-    let app = Application::builder().application_id(APP_ID).build();
-    app.connect_activate(build_ui);
-    app.run();
+/// State shared by every command line the app receives
+#[derive(Default)]
+struct Launcher {
+    /// Capture waiting for its delay or for `--capture-now`
+    pending: RefCell<Option<Rc<Countdown>>>,
+    /// Keeps the app running without any window in daemon mode
+    daemon: OnceCell<gio::ApplicationHoldGuard>,
+}
+
+fn main() -> ExitCode {
+    let app = Application::builder()
+        .application_id(APP_ID)
+        .flags(ApplicationFlags::HANDLES_COMMAND_LINE)
+        .build();
+
+    app.add_main_option(
+        "delay",
+        glib::Char::from(b'd'),
+        OptionFlags::NONE,
+        OptionArg::Int,
+        "Wait N seconds before capturing the screen",
+        Some("N"),
+    );
+    app.add_main_option(
+        "wait",
+        glib::Char::from(b'w'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        "Wait for --capture-now before capturing the screen",
+        None,
+    );
+    app.add_main_option(
+        "capture-now",
+        glib::Char::from(b'n'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        "Capture the screen now, ending the wait of a running rustyruler",
+        None,
+    );
+    app.add_main_option(
+        "pick-color",
+        glib::Char::from(b'p'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        "Open the color picker, copying the clicked color",
+        None,
+    );
+    app.add_main_option(
+        "reference",
+        glib::Char::from(b'r'),
+        OptionFlags::NONE,
+        OptionArg::Filename,
+        "Compare the capture with an image, such as a mockup",
+        Some("FILE"),
+    );
+    app.add_main_option(
+        "open",
+        glib::Char::from(b'o'),
+        OptionFlags::NONE,
+        OptionArg::Filename,
+        "Open a saved session in a window instead of capturing the screen",
+        Some("FILE"),
+    );
+    app.add_main_option(
+        "daemon",
+        glib::Char::from(0),
+        OptionFlags::NONE,
+        OptionArg::None,
+        "Keep running in the background, so later launches show the overlay at once",
+        None,
+    );
+
+    app.connect_startup(|app| {
+        load_stylesheets();
+        add_actions(app, handle_request);
+    });
+
+    // Command lines of later launches are handled here too, by the first instance
+    let launcher = Launcher::default();
+    app.connect_command_line(move |app, command_line| {
+        handle_command_line(app, command_line, &launcher)
+    });
+
+    app.run()
+}
+
+/// What an action activated over the session bus asks for
+#[derive(Debug, Clone, PartialEq)]
+enum Request {
+    Measure,
+    PickColor,
+    /// Save a screenshot to this absolute path
+    CaptureImage(PathBuf),
+}
+
+/// Actions exported on the session bus, at /com/rodrig20/rustyruler
+/// - `measure` captures the screen and opens the overlay
+/// - `pick-color` does the same with the color picker selected
+/// - `capture-image` saves a screenshot to the given absolute path, without the overlay
+fn add_actions<A: IsA<gio::ActionMap>>(app: &A, on_request: impl Fn(&A, Request) + 'static) {
+    let on_request = Rc::new(on_request);
+
+    let measure = gio::ActionEntry::builder("measure")
+        .activate({
+            let on_request = on_request.clone();
+            move |app: &A, _, _| on_request(app, Request::Measure)
+        })
+        .build();
+    let pick_color = gio::ActionEntry::builder("pick-color")
+        .activate({
+            let on_request = on_request.clone();
+            move |app: &A, _, _| on_request(app, Request::PickColor)
+        })
+        .build();
+    let capture_image = gio::ActionEntry::builder("capture-image")
+        .parameter_type(Some(glib::VariantTy::STRING))
+        .activate(move |app: &A, _, parameter| {
+            let Some(path) = parameter.and_then(|parameter| parameter.get::<String>()) else {
+                return;
+            };
+            // A relative path would be taken from the daemon's directory, not the caller's
+            let path = PathBuf::from(path);
+            if !path.is_absolute() {
+                eprintln!(
+                    "capture-image needs an absolute path, got {}",
+                    path.display()
+                );
+                return;
+            }
+            on_request(app, Request::CaptureImage(path));
+        })
+        .build();
+
+    app.add_action_entries([measure, pick_color, capture_image]);
+}
+
+/// Runs what an action asked for
+fn handle_request(app: &Application, request: Request) {
+    match request {
+        Request::Measure => build_ui(app),
+        Request::PickColor => build_color_picker(app),
+        Request::CaptureImage(path) => capture_image(app, path),
+    }
+}
+
+/// Saves a screenshot, running grim on a worker thread so the daemon keeps responding
+fn capture_image(app: &Application, path: PathBuf) {
+    // Keeps the app running until the capture is written
+    let hold = app.hold();
+
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(move || {
+            let result = screenshot::capture_to(&path);
+            (path, result)
+        })
+        .await;
+
+        match result {
+            Ok((_, Ok(()))) => {}
+            Ok((path, Err(err))) => eprintln!("Error capturing {}: {:?}", path.display(), err),
+            Err(_) => eprintln!("Error capturing screenshot: the capture thread panicked"),
+        }
+        drop(hold);
+    });
+}
+
+/// Captures the screen right away, after a delay, or once `--capture-now` is run
+/// In daemon mode the first command line only starts the daemon
+fn handle_command_line(
+    app: &Application,
+    command_line: &ApplicationCommandLine,
+    launcher: &Launcher,
+) -> ExitCode {
+    let options = command_line.options_dict();
+    let pending = &launcher.pending;
+
+    if options.contains("daemon") {
+        if launcher.daemon.set(app.hold()).is_err() {
+            eprintln!("The daemon is already running");
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if options.contains("pick-color") {
+        build_color_picker(app);
+        return ExitCode::SUCCESS;
+    }
+
+    // Relative paths are given from where the command was run, not from the daemon
+    let path_option = |name| {
+        options
+            .lookup::<PathBuf>(name)
+            .ok()
+            .flatten()
+            .map(|path| match command_line.cwd() {
+                Some(cwd) => cwd.join(path),
+                None => path,
+            })
+    };
+
+    if let Some(path) = path_option("open") {
+        open_session(app, &path);
+        return ExitCode::SUCCESS;
+    }
+
+    let reference = path_option("reference");
+    let open = move |app: &Application| match &reference {
+        Some(path) => build_compare(app, path),
+        None => build_ui(app),
+    };
+
+    if options.contains("capture-now") {
+        // With nothing waiting this is a plain capture
+        if !pending.take().is_some_and(|countdown| countdown.finish()) {
+            open(app);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let delay = match options.lookup::<i32>("delay") {
+        Ok(delay) => delay,
+        Err(err) => {
+            eprintln!("Invalid --delay: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let seconds = match delay.map(u32::try_from) {
+        Some(Ok(seconds)) => Some(seconds).filter(|&seconds| seconds > 0),
+        Some(Err(_)) => {
+            eprintln!("--delay must not be negative");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    if seconds.is_none() && !options.contains("wait") {
+        open(app);
+        return ExitCode::SUCCESS;
+    }
+
+    let mut pending = pending.borrow_mut();
+    if pending
+        .as_ref()
+        .is_some_and(|countdown| countdown.is_waiting())
+    {
+        eprintln!("A capture is already waiting");
+        return ExitCode::FAILURE;
+    }
+    *pending = Some(Countdown::start(app, seconds, open));
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+    use std::time::{Duration, Instant};
+
+    /// Starts a private session bus
+    /// Tests take turns, as each one points the session bus address at its own bus
+    pub(crate) fn private_bus() -> (MutexGuard<'static, ()>, gio::TestDBus) {
+        static BUS: Mutex<()> = Mutex::new(());
+        let guard = BUS.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let bus = gio::TestDBus::new(gio::TestDBusFlags::NONE);
+        bus.up();
+        (guard, bus)
+    }
+
+    #[test]
+    fn actions_are_activated_over_a_private_session_bus() {
+        let (_guard, bus) = private_bus();
+
+        // A plain GApplication exports the same actions without needing a display
+        let app = gio::Application::new(Some(APP_ID), ApplicationFlags::empty());
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        add_actions(&app, {
+            let requests = requests.clone();
+            move |_, request| requests.borrow_mut().push(request)
+        });
+        app.register(gio::Cancellable::NONE).unwrap();
+
+        let connection = app.dbus_connection().unwrap();
+        let remote =
+            gio::DBusActionGroup::get(&connection, Some(APP_ID), "/com/rodrig20/rustyruler");
+        remote.activate_action("measure", None);
+        remote.activate_action("pick-color", None);
+        remote.activate_action("capture-image", Some(&"screen.png".to_variant()));
+        remote.activate_action("capture-image", Some(&"/tmp/screen.png".to_variant()));
+
+        // Activations arrive in order, so the relative path was handled once the last one is in
+        let context = glib::MainContext::default();
+        let start = Instant::now();
+        while requests.borrow().len() < 3 && start.elapsed() < Duration::from_secs(5) {
+            context.iteration(false);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(
+            *requests.borrow(),
+            vec![
+                Request::Measure,
+                Request::PickColor,
+                Request::CaptureImage(PathBuf::from("/tmp/screen.png")),
+            ]
+        );
+
+        // Sources still queued on the main context may keep the connection alive,
+        // so the bus is stopped without waiting for it to be released
+        bus.stop();
+    }
 }
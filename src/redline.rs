@@ -0,0 +1,234 @@
+//! Redlines, the sizes of the elements in a region and the spacing between them
+//!
+//! A region is selected by dragging with the redline tool. The elements placed
+//! on its background are found with [`find_elements`], then each one is
+//! outlined with its size and each pair of neighbors gets the gap between them,
+//! as in a hand written spec. The redline is drawn in image pixels, so it ends
+//! up in the exported images as shown, and is also written as a JSON spec.
+
+use crate::config::RedlineStyle;
+use gtk4::cairo;
+use image::RgbImage;
+use rustyruler_core::elements::Spacing;
+use rustyruler_core::units::{Axis, Units};
+use rustyruler_core::{Rect, find_elements, spacings};
+use serde::Serialize;
+
+/// Length of the ticks closing each spacing line relative to the line width
+const TICK_LENGTH: f64 = 4.0;
+
+/// Padding around the sizes relative to the font size
+const TEXT_PADDING: f64 = 0.25;
+
+/// A selected region with the elements found in it
+#[derive(Debug, Clone)]
+pub struct Redline {
+    region: Rect,
+    elements: Vec<Rect>,
+    spacings: Vec<Spacing>,
+}
+
+/// Redline written to a JSON spec, with the sizes as shown in the current unit
+#[derive(Serialize)]
+struct Spec<'a> {
+    region: Rect,
+    unit: &'a str,
+    elements: Vec<SpecElement>,
+    spacings: Vec<SpecSpacing>,
+}
+
+#[derive(Serialize)]
+struct SpecElement {
+    #[serde(flatten)]
+    bounds: Rect,
+    /// Size in the current unit, e.g. `120 × 40`
+    size: String,
+}
+
+#[derive(Serialize)]
+struct SpecSpacing {
+    /// Index of the element left of or above the gap
+    from: usize,
+    /// Index of the element right of or below the gap
+    to: usize,
+    axis: &'static str,
+    pixels: u32,
+    /// Length in the current unit
+    size: String,
+}
+
+impl Redline {
+    /// Starts a redline over the region, without elements until [`Redline::find`] runs
+    pub fn new(region: Rect) -> Self {
+        Redline {
+            region,
+            elements: Vec::new(),
+            spacings: Vec::new(),
+        }
+    }
+
+    pub fn region(&self) -> Rect {
+        self.region
+    }
+
+    /// Finds the elements in the region and the gaps between them again
+    pub fn find(&mut self, img: &RgbImage, threshold: f32) {
+        self.elements = find_elements(img, self.region, threshold);
+        self.spacings = spacings(&self.elements);
+    }
+
+    /// Outlines the region and its elements, with their sizes and the gaps between them
+    /// The region outline is dashed and kept to a screen pixel, it is not part of the spec
+    pub fn draw(&self, cr: &cairo::Context, units: &Units, style: &RedlineStyle, scale: f64) {
+        style.color.apply(cr);
+        cr.set_line_width(1.0 / scale);
+        cr.set_dash(&[4.0 / scale, 4.0 / scale], 0.0);
+        rectangle(cr, &self.region);
+        let _ = cr.stroke();
+        cr.set_dash(&[], 0.0);
+
+        cr.set_line_width(style.line_width);
+        for element in &self.elements {
+            rectangle(cr, element);
+        }
+        let _ = cr.stroke();
+
+        for spacing in &self.spacings {
+            let (start, end, across) = (
+                f64::from(spacing.start),
+                f64::from(spacing.end),
+                f64::from(spacing.across) + 0.5,
+            );
+            let tick = TICK_LENGTH * style.line_width;
+            match spacing.axis {
+                Axis::Horizontal => {
+                    cr.move_to(start, across);
+                    cr.line_to(end, across);
+                    for x in [start, end] {
+                        cr.move_to(x, across - tick);
+                        cr.line_to(x, across + tick);
+                    }
+                }
+                Axis::Vertical => {
+                    cr.move_to(across, start);
+                    cr.line_to(across, end);
+                    for y in [start, end] {
+                        cr.move_to(across - tick, y);
+                        cr.line_to(across + tick, y);
+                    }
+                }
+            }
+        }
+        let _ = cr.stroke();
+
+        // Sizes sit on the top edge of their element, gaps on their middle
+        for element in &self.elements {
+            let x = f64::from(element.x) + f64::from(element.width) / 2.0;
+            draw_label(
+                cr,
+                &size_text(element, units),
+                x,
+                f64::from(element.y),
+                style,
+            );
+        }
+        for spacing in &self.spacings {
+            let middle = f64::from(spacing.start + spacing.end) / 2.0;
+            let across = f64::from(spacing.across);
+            let (x, y) = match spacing.axis {
+                Axis::Horizontal => (middle, across),
+                Axis::Vertical => (across, middle),
+            };
+            draw_label(cr, &length_text(spacing, units), x, y, style);
+        }
+    }
+
+    /// The redline as a pretty printed JSON spec, with sizes in the current unit
+    pub fn spec(&self, units: &Units) -> String {
+        let spec = Spec {
+            region: self.region,
+            unit: units.unit.label(),
+            elements: self
+                .elements
+                .iter()
+                .map(|element| SpecElement {
+                    bounds: *element,
+                    size: size_text(element, units),
+                })
+                .collect(),
+            spacings: self
+                .spacings
+                .iter()
+                .map(|spacing| SpecSpacing {
+                    from: spacing.from,
+                    to: spacing.to,
+                    axis: match spacing.axis {
+                        Axis::Horizontal => "horizontal",
+                        Axis::Vertical => "vertical",
+                    },
+                    pixels: spacing.length(),
+                    size: length_text(spacing, units),
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&spec).expect("Specs serialize to JSON")
+    }
+}
+
+/// Size of an element in the current unit, e.g. `120 × 40`
+fn size_text(element: &Rect, units: &Units) -> String {
+    let (screen_width, screen_height) = units.context.screen_size;
+    format!(
+        "{} × {}",
+        units.format(element.width, Axis::Horizontal, screen_width),
+        units.format(element.height, Axis::Vertical, screen_height)
+    )
+}
+
+/// Length of a gap in the current unit
+fn length_text(spacing: &Spacing, units: &Units) -> String {
+    let reference = match spacing.axis {
+        Axis::Horizontal => units.context.screen_size.0,
+        Axis::Vertical => units.context.screen_size.1,
+    };
+    units.format(spacing.length(), spacing.axis, reference)
+}
+
+/// Adds the outline of a rectangle, on the pixels along its edges
+fn rectangle(cr: &cairo::Context, rect: &Rect) {
+    cr.rectangle(
+        f64::from(rect.x) + 0.5,
+        f64::from(rect.y) + 0.5,
+        f64::from(rect.width) - 1.0,
+        f64::from(rect.height) - 1.0,
+    );
+}
+
+/// Draws the text on its background, centered on the given point
+fn draw_label(cr: &cairo::Context, text: &str, x: f64, y: f64, style: &RedlineStyle) {
+    cr.select_font_face(
+        &style.font,
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Bold,
+    );
+    cr.set_font_size(style.font_size);
+    let Ok(extents) = cr.text_extents(text) else {
+        return;
+    };
+
+    let padding = TEXT_PADDING * style.font_size;
+    let (width, height) = (extents.x_advance(), style.font_size);
+    style.background.apply(cr);
+    cr.rectangle(
+        x - width / 2.0 - padding,
+        y - height / 2.0 - padding,
+        width + 2.0 * padding,
+        height + 2.0 * padding,
+    );
+    let _ = cr.fill();
+
+    // The baseline is placed so capitals and digits are centered vertically
+    style.text_color.apply(cr);
+    cr.move_to(x - width / 2.0, y + height * 0.35);
+    let _ = cr.show_text(text);
+}
@@ -0,0 +1,207 @@
+//! Session files, a capture saved along with everything measured on it
+//!
+//! A session is a PNG of the capture with the pins, annotations, redline, guides
+//! and settings stored as JSON in an iTXt chunk, so it still opens as a plain
+//! image anywhere else.
+//! `rustyruler --open` shows it again in a window, where it can be measured
+//! further and saved back.
+
+use crate::annotations::Annotation;
+use image::{DynamicImage, RgbImage};
+use rustyruler_core::guides::Guide;
+use rustyruler_core::{Measurement, Rect};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+/// Extension of session files
+pub const EXTENSION: &str = "rrs";
+
+/// Keyword of the iTXt chunk holding the session
+const KEYWORD: &str = "rustyruler-session";
+
+/// Version written to new sessions, older ones are still read
+const VERSION: u32 = 1;
+
+/// Error raised while reading or writing a session file
+#[derive(Debug)]
+pub enum SessionError {
+    Io(PathBuf, io::Error),
+    Encode(PathBuf, png::EncodingError),
+    Decode(PathBuf, png::DecodingError),
+    /// The file is an image without a session in it
+    Missing(PathBuf),
+    Json(PathBuf, serde_json::Error),
+    /// The session was written by a newer rustyruler
+    Version(PathBuf, u32),
+    /// The image is in a format the pixels can't be read from
+    UnsupportedFormat(PathBuf, png::ColorType, png::BitDepth),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            SessionError::Encode(path, err) => write!(f, "{}: {}", path.display(), err),
+            SessionError::Decode(path, err) => write!(f, "{}: {}", path.display(), err),
+            SessionError::Missing(path) => {
+                write!(f, "{}: not a rustyruler session", path.display())
+            }
+            SessionError::Json(path, err) => {
+                write!(f, "{}: invalid session: {}", path.display(), err)
+            }
+            SessionError::Version(path, version) => write!(
+                f,
+                "{}: session version {} is newer than the supported {}",
+                path.display(),
+                version,
+                VERSION
+            ),
+            SessionError::UnsupportedFormat(path, color_type, bit_depth) => write!(
+                f,
+                "{}: unsupported image format {:?} with {} bits per sample",
+                path.display(),
+                color_type,
+                *bit_depth as u8
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+/// Everything measured on a capture, with the settings it was measured with
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    /// Id of the selected tool
+    pub tool: String,
+    pub magnitude_threshold: f32,
+    /// Label of the unit sizes are shown in, e.g. `px` or `rem`
+    pub unit: String,
+    pub show_grid: bool,
+    pub guides: Vec<Guide>,
+    pub pins: Vec<SavedPin>,
+    /// Missing from sessions saved before annotations were added
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Region laid out with the redline tool, whose elements are found again on load
+    #[serde(default)]
+    pub redline: Option<Rect>,
+}
+
+/// A pinned measurement with the id of the tool that took it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedPin {
+    pub tool: String,
+    pub measurement: Measurement,
+    /// Time the measurement was pinned at, in ISO 8601
+    pub time: String,
+}
+
+impl Session {
+    /// Starts a session of the current version, the caller fills in the rest
+    pub fn new(tool: &str, magnitude_threshold: f32, unit: &str, show_grid: bool) -> Self {
+        Session {
+            version: VERSION,
+            tool: tool.to_string(),
+            magnitude_threshold,
+            unit: unit.to_string(),
+            show_grid,
+            guides: Vec::new(),
+            pins: Vec::new(),
+            annotations: Vec::new(),
+            redline: None,
+        }
+    }
+}
+
+/// Path a new session is saved to, timestamped next to the exports
+pub fn new_path(dir: &Path) -> PathBuf {
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp_nanos();
+    dir.join(format!("rustyruler_{}.{}", timestamp, EXTENSION))
+}
+
+/// Writes the capture and the session into a PNG file
+pub fn save(path: &Path, img: &RgbImage, session: &Session) -> Result<(), SessionError> {
+    let io_error = |err| SessionError::Io(path.to_path_buf(), err);
+    let encode_error = |err| SessionError::Encode(path.to_path_buf(), err);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let json = serde_json::to_string(session).expect("Sessions serialize to JSON");
+    let file = File::create(path).map_err(io_error)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), img.width(), img.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .add_itxt_chunk(KEYWORD.to_string(), json)
+        .map_err(encode_error)?;
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    writer
+        .write_image_data(img.as_raw())
+        .map_err(encode_error)?;
+    writer.finish().map_err(encode_error)
+}
+
+/// Reads the capture and the session back from a file written by [`save`]
+pub fn load(path: &Path) -> Result<(RgbImage, Session), SessionError> {
+    let file = File::open(path).map_err(|err| SessionError::Io(path.to_path_buf(), err))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    // Sessions are written as 8 bit RGB, but an image editor may save them back
+    // in another format, which is brought back to 8 bit color here
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|err| SessionError::Decode(path.to_path_buf(), err))?;
+
+    // The chunk is written before the pixels, so it is known once the header is read
+    let chunk = reader
+        .info()
+        .utf8_text
+        .iter()
+        .find(|chunk| chunk.keyword == KEYWORD)
+        .ok_or_else(|| SessionError::Missing(path.to_path_buf()))?;
+    let json = chunk
+        .get_text()
+        .map_err(|err| SessionError::Decode(path.to_path_buf(), err))?;
+    let session: Session =
+        serde_json::from_str(&json).map_err(|err| SessionError::Json(path.to_path_buf(), err))?;
+    if session.version > VERSION {
+        return Err(SessionError::Version(path.to_path_buf(), session.version));
+    }
+
+    let mut buffer = vec![0; reader.output_buffer_size().unwrap_or(0)];
+    let frame = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| SessionError::Decode(path.to_path_buf(), err))?;
+    buffer.truncate(frame.buffer_size());
+    let (width, height) = (frame.width, frame.height);
+    let img = match (frame.color_type, frame.bit_depth) {
+        (png::ColorType::Rgb, png::BitDepth::Eight) => RgbImage::from_raw(width, height, buffer),
+        (png::ColorType::Rgba, png::BitDepth::Eight) => {
+            image::RgbaImage::from_raw(width, height, buffer)
+                .map(|img| DynamicImage::ImageRgba8(img).to_rgb8())
+        }
+        (png::ColorType::Grayscale, png::BitDepth::Eight) => {
+            image::GrayImage::from_raw(width, height, buffer)
+                .map(|img| DynamicImage::ImageLuma8(img).to_rgb8())
+        }
+        (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight) => {
+            image::GrayAlphaImage::from_raw(width, height, buffer)
+                .map(|img| DynamicImage::ImageLumaA8(img).to_rgb8())
+        }
+        _ => None,
+    }
+    .ok_or_else(|| {
+        SessionError::UnsupportedFormat(path.to_path_buf(), frame.color_type, frame.bit_depth)
+    })?;
+
+    Ok((img, session))
+}
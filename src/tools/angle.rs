@@ -0,0 +1,203 @@
+use super::{Tool, draw_center};
+use crate::assets;
+use gtk4::{
+    cairo,
+    gdk::{Key, ModifierType},
+};
+use image::RgbImage;
+use rustyruler_core::geometry::project_onto_direction;
+use rustyruler_core::gradient::{self, DEFAULT_RADIUS};
+use rustyruler_core::{Measurement, Point, Shape, Units};
+use std::f64::consts::PI;
+
+/// Radius of the arc marking the measured angle, in screen pixels
+const ARC_RADIUS: f64 = 40.0;
+
+/// Protractor measuring the angle between two arms placed by clicking
+///
+/// The first click places the vertex and the next two the ends of the arms,
+/// a fourth click starts over. Holding Shift while placing an arm snaps it to
+/// the direction of the edge under the cursor.
+#[derive(Default)]
+pub struct AngleTool {
+    vertex: Option<Point>,
+    first: Option<Point>,
+    second: Option<Point>,
+}
+
+impl AngleTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places the end of an arm, snapping it to the edge under it if asked to
+    fn place_arm(
+        vertex: Point,
+        img: &RgbImage,
+        x: u32,
+        y: u32,
+        threshold: f32,
+        snap: bool,
+    ) -> Point {
+        let point = Point::pixel(x, y);
+        if !snap {
+            return point;
+        }
+
+        match gradient::edge_direction(img, x, y, DEFAULT_RADIUS, threshold) {
+            Some(direction) => project_onto_direction(&vertex, &point, direction),
+            None => point,
+        }
+    }
+}
+
+impl Tool for AngleTool {
+    fn id(&self) -> &'static str {
+        "angle"
+    }
+
+    fn name(&self) -> &'static str {
+        "Angle"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("angle.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::a
+    }
+
+    fn compute(&self, _img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        let cursor = Point::pixel(x, y);
+
+        let measurement = match (self.vertex, self.first, self.second) {
+            (Some(vertex), Some(first), Some(second)) => Measurement::at(
+                vertex.x.round() as u32,
+                vertex.y.round() as u32,
+                threshold,
+                Shape::Angle {
+                    vertex,
+                    first,
+                    second: Some(second),
+                },
+            ),
+            // The cursor previews the arm that is placed next
+            (Some(vertex), Some(first), None) => Measurement::at(
+                x,
+                y,
+                threshold,
+                Shape::Angle {
+                    vertex,
+                    first,
+                    second: Some(cursor),
+                },
+            ),
+            (Some(vertex), None, _) => Measurement::at(
+                x,
+                y,
+                threshold,
+                Shape::Angle {
+                    vertex,
+                    first: cursor,
+                    second: None,
+                },
+            ),
+            (None, _, _) => Measurement::at(x, y, threshold, Shape::Extent),
+        };
+
+        Some(measurement)
+    }
+
+    fn click(
+        &mut self,
+        img: &RgbImage,
+        x: u32,
+        y: u32,
+        threshold: f32,
+        state: ModifierType,
+    ) -> bool {
+        let snap = state.contains(ModifierType::SHIFT_MASK);
+
+        match (self.vertex, self.first, self.second) {
+            (Some(vertex), None, _) => {
+                self.first = Some(Self::place_arm(vertex, img, x, y, threshold, snap));
+            }
+            (Some(vertex), Some(_), None) => {
+                self.second = Some(Self::place_arm(vertex, img, x, y, threshold, snap));
+            }
+            _ => {
+                self.vertex = Some(Point::pixel(x, y));
+                self.first = None;
+                self.second = None;
+            }
+        }
+
+        true
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        let Shape::Angle {
+            vertex,
+            first,
+            second,
+        } = measurement.shape
+        else {
+            draw_center(cr, measurement, scale);
+            return;
+        };
+
+        cr.move_to(vertex.x, vertex.y);
+        cr.line_to(first.x, first.y);
+        if let Some(second) = second {
+            cr.move_to(vertex.x, vertex.y);
+            cr.line_to(second.x, second.y);
+        }
+        cr.stroke().unwrap();
+
+        for point in [Some(vertex), Some(first), second].into_iter().flatten() {
+            cr.arc(point.x, point.y, 3.0 / scale, 0.0, 2.0 * PI);
+            cr.fill().unwrap();
+        }
+
+        let Some(second) = second else {
+            return;
+        };
+
+        let radius = (ARC_RADIUS / scale)
+            .min(vertex.distance(&first))
+            .min(vertex.distance(&second));
+        if radius <= 0.0 {
+            return;
+        }
+
+        // Sweep from the first arm to the second along the shorter way around
+        let start = vertex.direction_to(&first);
+        let mut sweep = vertex.direction_to(&second) - start;
+        if sweep > PI {
+            sweep -= 2.0 * PI;
+        } else if sweep < -PI {
+            sweep += 2.0 * PI;
+        }
+
+        cr.new_sub_path();
+        if sweep >= 0.0 {
+            cr.arc(vertex.x, vertex.y, radius, start, start + sweep);
+        } else {
+            cr.arc_negative(vertex.x, vertex.y, radius, start, start + sweep);
+        }
+        cr.stroke().unwrap();
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, _units: &Units) -> String {
+        match measurement.shape {
+            Shape::Extent => "Click to place the vertex".to_string(),
+            Shape::Angle { second: None, .. } => "Click to place the first arm".to_string(),
+            shape => format!("{:.1}°", shape.degrees().unwrap_or_default()),
+        }
+    }
+
+    fn shows_unit(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,79 @@
+use super::{Tool, draw_center};
+use crate::annotations::Kind;
+use crate::assets;
+use gtk4::{cairo, gdk::Key};
+use image::RgbImage;
+use rustyruler_core::{Measurement, Shape, Units};
+
+/// Places annotations instead of measuring, see [`crate::annotations`]
+///
+/// Labels are placed by clicking and typing their text, arrows and
+/// highlights by dragging from one end to the other.
+pub struct AnnotateTool {
+    kind: Kind,
+}
+
+impl AnnotateTool {
+    pub fn new(kind: Kind) -> Self {
+        AnnotateTool { kind }
+    }
+}
+
+impl Tool for AnnotateTool {
+    fn id(&self) -> &'static str {
+        match self.kind {
+            Kind::Text => "text",
+            Kind::Arrow => "arrow",
+            Kind::Highlight => "highlight",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self.kind {
+            Kind::Text => "Text label",
+            Kind::Arrow => "Arrow",
+            Kind::Highlight => "Highlight",
+        }
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        let icon = match self.kind {
+            Kind::Text => "text.png",
+            Kind::Arrow => "arrow.png",
+            Kind::Highlight => "highlight.png",
+        };
+        gtk4::Image::from_paintable(Some(&assets::texture(icon)))
+    }
+
+    fn shortcut(&self) -> Key {
+        match self.kind {
+            Kind::Text => Key::t,
+            Kind::Arrow => Key::x,
+            Kind::Highlight => Key::m,
+        }
+    }
+
+    fn compute(&self, _img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        Some(Measurement::at(x, y, threshold, Shape::Extent))
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, _measurement: &Measurement, _units: &Units) -> String {
+        match self.kind {
+            Kind::Text => "Click to add a label".to_string(),
+            Kind::Arrow => "Drag to draw an arrow".to_string(),
+            Kind::Highlight => "Drag to highlight an area".to_string(),
+        }
+    }
+
+    fn shows_unit(&self) -> bool {
+        false
+    }
+
+    fn annotation(&self) -> Option<Kind> {
+        Some(self.kind)
+    }
+}
@@ -0,0 +1,80 @@
+use super::{Tool, draw_center};
+use crate::assets;
+use gtk4::{cairo, gdk::Key};
+use image::RgbImage;
+use rustyruler_core::units::Axis;
+use rustyruler_core::{Measurement, Point, Shape, Units};
+use std::f64::consts::PI;
+
+/// Circle fitted to the edges around the cursor, for rounded corners and avatars
+pub struct CircleTool;
+
+impl Tool for CircleTool {
+    fn id(&self) -> &'static str {
+        "circle"
+    }
+
+    fn name(&self) -> &'static str {
+        "Circle"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("circle.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::o
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        // Without a circle nearby the cursor is still shown, with nothing measured
+        Some(
+            rustyruler_core::measure_circle(img, x, y, threshold)
+                .unwrap_or_else(|| Measurement::at(x, y, threshold, Shape::Extent)),
+        )
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        if let Shape::Circle { center, radius, .. } = measurement.shape {
+            cr.new_sub_path();
+            cr.arc(center.x, center.y, radius, 0.0, 2.0 * PI);
+            let _ = cr.stroke();
+
+            // Radius from the center towards the cursor
+            let direction = center.direction_to(&Point::pixel(measurement.x, measurement.y));
+            cr.move_to(center.x, center.y);
+            cr.line_to(
+                center.x + radius * direction.cos(),
+                center.y + radius * direction.sin(),
+            );
+            let _ = cr.stroke();
+
+            cr.arc(center.x, center.y, 2.0 / scale, 0.0, 2.0 * PI);
+            cr.fill().unwrap();
+        }
+
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, units: &Units) -> String {
+        match measurement.shape {
+            Shape::Circle { radius, error, .. } => {
+                // A fitted circle has no container, percentages are of the screen width
+                let reference = units.context.screen_size.0;
+                let length = |pixels| units.format_length(pixels, Axis::Horizontal, reference);
+                format!(
+                    "r {} · ⌀ {} {} ± {}",
+                    length(radius),
+                    length(radius * 2.0),
+                    units.unit.label(),
+                    length(error)
+                )
+            }
+            _ => "No circle found".to_string(),
+        }
+    }
+
+    fn shows_unit(&self) -> bool {
+        false
+    }
+}
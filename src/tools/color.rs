@@ -0,0 +1,75 @@
+use super::{Tool, draw_center};
+use crate::assets;
+use gtk4::{cairo, gdk::Key};
+use image::RgbImage;
+use rustyruler_core::{Measurement, Shape, Units};
+
+/// Side of the swatch drawn next to the cursor, in screen pixels
+const SWATCH_SIZE: f64 = 24.0;
+
+/// Color picker showing the value of the pixel under the cursor
+pub struct ColorTool;
+
+/// Hex notation of a color, e.g. `#1a2b3c`
+pub fn hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+impl Tool for ColorTool {
+    fn id(&self) -> &'static str {
+        "color"
+    }
+
+    fn name(&self) -> &'static str {
+        "Color picker"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("color.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::i
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        let rgb = img.get_pixel_checked(x, y)?.0;
+        Some(Measurement::at(x, y, threshold, Shape::Color { rgb }))
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        if let Shape::Color { rgb } = measurement.shape {
+            // Swatch above and to the left of the cursor, away from the tooltip
+            let size = SWATCH_SIZE / scale;
+            let (x, y) = (
+                f64::from(measurement.x) - size - 4.0 / scale,
+                f64::from(measurement.y) - size - 4.0 / scale,
+            );
+            cr.save().unwrap();
+            cr.set_source_rgb(
+                f64::from(rgb[0]) / 255.0,
+                f64::from(rgb[1]) / 255.0,
+                f64::from(rgb[2]) / 255.0,
+            );
+            cr.rectangle(x, y, size, size);
+            let _ = cr.fill_preserve();
+            cr.restore().unwrap();
+            let _ = cr.stroke();
+        }
+
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, _units: &Units) -> String {
+        match measurement.shape {
+            Shape::Color { rgb } => {
+                format!("{} · rgb({}, {}, {})", hex(rgb), rgb[0], rgb[1], rgb[2])
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn shows_unit(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,245 @@
+use super::{Tool, draw_center, draw_highlight, draw_horizontal_arm, draw_vertical_arm};
+use crate::assets;
+use gtk4::{cairo, gdk::Key, gio, glib};
+use image::RgbImage;
+use rustyruler_core::{Axes, Components, Measurement, Units, find_components, measure_at};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Snaps to the smallest component under the cursor, such as a button, a card or an input
+///
+/// The components of the whole image are found on a worker thread the first
+/// time the tool measures it, and again once the image or the threshold
+/// changes. Until they are found the cursor is measured by color changes. The
+/// enclosing components can then be selected one after the other, outward up
+/// to the whole screen and back in.
+pub struct ComponentsTool {
+    /// Generation of the measured image, see [`Tool::image_changed`]
+    generation: Cell<u64>,
+    /// Components of the latest image searched, shared with the worker finding them
+    found: Rc<RefCell<Option<Found>>>,
+    /// Image and threshold the worker is searching, newer searches replace older ones
+    searching: Rc<Cell<Option<Search>>>,
+    on_ready: RefCell<Option<Rc<dyn Fn()>>>,
+    /// Components containing the last measured point, from the innermost outward
+    chain: RefCell<Vec<usize>>,
+    /// Position in the chain of the selected component, 0 for the innermost
+    depth: Cell<usize>,
+}
+
+/// Image and threshold components are searched with
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Search {
+    generation: u64,
+    threshold: f32,
+}
+
+/// Components of an image, with what they were found with
+struct Found {
+    search: Search,
+    components: Components,
+}
+
+impl ComponentsTool {
+    pub fn new() -> Self {
+        ComponentsTool {
+            generation: Cell::new(0),
+            found: Rc::new(RefCell::new(None)),
+            searching: Rc::new(Cell::new(None)),
+            on_ready: RefCell::new(None),
+            chain: RefCell::new(Vec::new()),
+            depth: Cell::new(0),
+        }
+    }
+
+    /// Starts finding the components on a worker thread, unless they already are
+    fn search(&self, img: &RgbImage, search: Search) {
+        if self.searching.get() == Some(search) {
+            return;
+        }
+        self.searching.set(Some(search));
+
+        let img = img.clone();
+        let found = self.found.clone();
+        let searching = self.searching.clone();
+        let on_ready = self.on_ready.borrow().clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || find_components(&img, search.threshold)).await;
+            if searching.get() != Some(search) {
+                return;
+            }
+            searching.set(None);
+
+            match result {
+                Ok(components) => {
+                    *found.borrow_mut() = Some(Found { search, components });
+                    if let Some(on_ready) = on_ready {
+                        on_ready();
+                    }
+                }
+                Err(_) => eprintln!("Error finding components: the worker thread panicked"),
+            }
+        });
+    }
+}
+
+impl Tool for ComponentsTool {
+    fn id(&self) -> &'static str {
+        "components"
+    }
+
+    fn name(&self) -> &'static str {
+        "Components"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("components.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::b
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        let search = Search {
+            generation: self.generation.get(),
+            threshold,
+        };
+        let found = self.found.borrow();
+        let Some(components) = found
+            .as_ref()
+            .filter(|found| found.search == search)
+            .map(|found| &found.components)
+        else {
+            drop(found);
+            self.search(img, search);
+            self.chain.borrow_mut().clear();
+            let mut measurement = measure_at(img, x, y, Axes::Both, threshold)?;
+            measurement.threshold = threshold;
+            return Some(measurement);
+        };
+
+        // The selection goes back to the innermost component once the cursor
+        // moves onto another one
+        let chain = components.chain_at(x, y);
+        if chain.first() != self.chain.borrow().first() {
+            self.depth.set(0);
+        }
+        let selected = chain.get(self.depth.get()).or(chain.last()).copied();
+        *self.chain.borrow_mut() = chain;
+
+        // Points outside any component are measured by color changes
+        let (width, height) = img.dimensions();
+        let mut measurement = match selected {
+            Some(index) => components.measure(index, x, y, width, height)?,
+            None => measure_at(img, x, y, Axes::Both, threshold)?,
+        };
+        measurement.threshold = threshold;
+        Some(measurement)
+    }
+
+    fn select_parent(&mut self, outward: bool) -> bool {
+        let depth = self.depth.get();
+        let selected = if outward {
+            (depth + 1 < self.chain.borrow().len()).then_some(depth + 1)
+        } else {
+            depth.checked_sub(1)
+        };
+        if let Some(depth) = selected {
+            self.depth.set(depth);
+        }
+        selected.is_some()
+    }
+
+    fn image_changed(&self, generation: u64) {
+        self.generation.set(generation);
+    }
+
+    fn set_on_ready(&self, on_ready: Rc<dyn Fn()>) {
+        *self.on_ready.borrow_mut() = Some(on_ready);
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        draw_highlight(cr, measurement);
+        draw_vertical_arm(cr, measurement);
+        draw_horizontal_arm(cr, measurement);
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, units: &Units) -> String {
+        format!(
+            "{} × {}",
+            units.width(measurement),
+            units.height(measurement)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+    use rustyruler_core::{DEFAULT_THRESHOLD, Limits};
+    use std::time::Duration;
+
+    /// A white screen with a gray card at the given position
+    fn screen(x: u32, y: u32) -> RgbImage {
+        RgbImage::from_fn(100, 80, |column, row| {
+            if (x..x + 30).contains(&column) && (y..y + 20).contains(&row) {
+                Rgb([200, 200, 200])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn results_of_a_search_for_an_older_image_are_ignored() {
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                let tool = ComponentsTool::new();
+                let ready = Rc::new(Cell::new(0));
+                let ready_count = ready.clone();
+                tool.set_on_ready(Rc::new(move || ready_count.set(ready_count.get() + 1)));
+
+                // The first search is replaced before it can finish
+                tool.image_changed(1);
+                tool.compute(&screen(10, 10), 20, 20, DEFAULT_THRESHOLD);
+                let (moved, generation) = (screen(50, 40), 2);
+                tool.image_changed(generation);
+                tool.compute(&moved, 60, 50, DEFAULT_THRESHOLD);
+
+                while ready.get() == 0 {
+                    context.iteration(true);
+                }
+                // Leaves the first search time to land too
+                context.block_on(glib::timeout_future(Duration::from_millis(200)));
+
+                assert_eq!(ready.get(), 1);
+                let found = tool.found.borrow();
+                assert_eq!(found.as_ref().unwrap().search.generation, generation);
+                drop(found);
+
+                let measurement = tool.compute(&moved, 60, 50, DEFAULT_THRESHOLD).unwrap();
+                assert_eq!(
+                    measurement.limits,
+                    Limits {
+                        top: 39,
+                        bottom: 60,
+                        left: 49,
+                        right: 80,
+                    }
+                );
+
+                // Components found for the first image are never used for the next one
+                tool.image_changed(generation + 1);
+                tool.compute(&moved, 60, 50, DEFAULT_THRESHOLD);
+                assert_eq!(
+                    tool.searching.get().map(|search| search.generation),
+                    Some(3)
+                );
+            })
+            .unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use super::{Tool, draw_center, draw_horizontal_arm, draw_vertical_arm};
+use crate::assets;
+use gtk4::{cairo, gdk::Key};
+use image::RgbImage;
+use rustyruler_core::{Axes, Measurement, Units};
+
+/// Full crosshair measuring both the width and the height of an element
+pub struct CrossTool;
+
+impl Tool for CrossTool {
+    fn id(&self) -> &'static str {
+        "cross"
+    }
+
+    fn name(&self) -> &'static str {
+        "Cross"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("cross.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::c
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        rustyruler_core::measure_at(img, x, y, Axes::Both, threshold)
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        draw_vertical_arm(cr, measurement);
+        draw_horizontal_arm(cr, measurement);
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, units: &Units) -> String {
+        format!(
+            "{} × {}",
+            units.width(measurement),
+            units.height(measurement)
+        )
+    }
+}
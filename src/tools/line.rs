@@ -0,0 +1,77 @@
+use super::{Tool, draw_center, draw_horizontal_arm, draw_vertical_arm};
+use crate::assets;
+use gtk4::{cairo, gdk::Key};
+use image::RgbImage;
+use rustyruler_core::{Axes, Measurement, Units};
+
+/// Single line measuring either the width or the height of an element
+pub struct LineTool {
+    axes: Axes,
+}
+
+impl LineTool {
+    /// Creates a line along the given axis, `Axes::Both` is treated as horizontal
+    pub fn new(axes: Axes) -> Self {
+        let axes = match axes {
+            Axes::Vertical => Axes::Vertical,
+            _ => Axes::Horizontal,
+        };
+        LineTool { axes }
+    }
+}
+
+impl Tool for LineTool {
+    fn id(&self) -> &'static str {
+        match self.axes {
+            Axes::Vertical => "vertical",
+            _ => "horizontal",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self.axes {
+            Axes::Vertical => "Vertical line",
+            _ => "Horizontal line",
+        }
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        let line = assets::image("line.png");
+
+        // For the vertical line, we use a 90-degree rotated version of line.png
+        let texture = if self.axes == Axes::Vertical {
+            assets::texture_from_image(&line.rotate90())
+        } else {
+            assets::texture_from_image(&line)
+        };
+
+        gtk4::Image::from_paintable(Some(&texture))
+    }
+
+    fn shortcut(&self) -> Key {
+        match self.axes {
+            Axes::Vertical => Key::v,
+            _ => Key::h,
+        }
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        rustyruler_core::measure_at(img, x, y, self.axes, threshold)
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        if self.axes == Axes::Vertical {
+            draw_vertical_arm(cr, measurement);
+        } else {
+            draw_horizontal_arm(cr, measurement);
+            draw_center(cr, measurement, scale);
+        }
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, units: &Units) -> String {
+        match self.axes {
+            Axes::Vertical => units.height(measurement),
+            _ => units.width(measurement),
+        }
+    }
+}
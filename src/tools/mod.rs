@@ -0,0 +1,301 @@
+//! Measurement tools offered by the command center
+//!
+//! Every tool implements [`Tool`] and is registered in a [`ToolRegistry`], which
+//! the command center builds its buttons from.
+
+mod angle;
+mod annotate;
+mod circle;
+mod color;
+mod components;
+mod cross;
+mod line;
+mod ray;
+mod redline;
+mod typography;
+mod windows;
+
+pub use angle::AngleTool;
+pub use annotate::AnnotateTool;
+pub use circle::CircleTool;
+pub use color::{ColorTool, hex};
+pub use components::ComponentsTool;
+pub use cross::CrossTool;
+pub use line::LineTool;
+pub use ray::RayTool;
+pub use redline::RedlineTool;
+pub use typography::TypographyTool;
+pub use windows::WindowsTool;
+
+use crate::annotations::Kind;
+use gtk4::{
+    cairo,
+    gdk::{Key, ModifierType},
+};
+use image::RgbImage;
+use rustyruler_core::{Axes, Measurement, Units};
+use std::rc::Rc;
+
+/// A measurement tool that can be selected in the command center
+pub trait Tool {
+    /// Stable identifier used in the configuration file
+    fn id(&self) -> &'static str;
+
+    /// Human readable name, used as the button tooltip
+    fn name(&self) -> &'static str;
+
+    /// Icon shown on the command center button
+    fn icon(&self) -> gtk4::Image;
+
+    /// Default key that selects this tool
+    fn shortcut(&self) -> Key;
+
+    /// Measures the element under the given image point
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement>;
+
+    /// Handles a click at the given image point, returning true if it was used
+    /// Tools that place points by clicking keep them until the next `compute`
+    fn click(
+        &mut self,
+        _img: &RgbImage,
+        _x: u32,
+        _y: u32,
+        _threshold: f32,
+        _state: ModifierType,
+    ) -> bool {
+        false
+    }
+
+    /// Handles a scroll step with the given modifiers, returning true if it was used
+    /// Scrolls no tool uses adjust the threshold instead
+    fn scroll(&mut self, _delta: f64, _state: ModifierType) -> bool {
+        false
+    }
+
+    /// Draws the measurement on the screenshot, in image coordinates
+    /// The source color and line width are already set from the user's style
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64);
+
+    /// Text shown in the tooltip next to the cursor, without the unit label
+    fn tooltip_text(&self, measurement: &Measurement, units: &Units) -> String;
+
+    /// Whether the tooltip text is a length followed by the unit label
+    fn shows_unit(&self) -> bool {
+        true
+    }
+
+    /// Kind of annotation placed with this tool, `None` for tools that measure
+    fn annotation(&self) -> Option<Kind> {
+        None
+    }
+
+    /// Whether dragging with this tool selects a region instead of measuring
+    fn selects_region(&self) -> bool {
+        false
+    }
+
+    /// Steps out to the component enclosing the measured one, or back in,
+    /// returning true if it was used
+    fn select_parent(&mut self, _outward: bool) -> bool {
+        false
+    }
+
+    /// Tells the tool the measured image changed, `generation` being different for every image
+    /// Tools analysing the whole image keep their results until it changes
+    fn image_changed(&self, _generation: u64) {}
+
+    /// Tells the tool the screen was just captured as `img`, measured as the given generation
+    /// Tools reading the desktop itself do it now, so it matches the capture
+    fn screen_captured(&self, _img: &RgbImage, _generation: u64) {}
+
+    /// Whether the tool reads the desktop rather than the image, which a
+    /// saved session no longer shows
+    fn reads_desktop(&self) -> bool {
+        false
+    }
+
+    /// Sets what to call once work the tool started in the background is done,
+    /// to measure again with its results
+    fn set_on_ready(&self, _on_ready: Rc<dyn Fn()>) {}
+}
+
+/// Ordered list of the available tools and the one currently selected
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn Tool>>,
+    active: usize,
+}
+
+impl ToolRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        ToolRegistry {
+            tools: Vec::new(),
+            active: 0,
+        }
+    }
+
+    /// Adds a tool at the end of the command center
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.push(Box::new(tool));
+    }
+
+    pub fn tools(&self) -> &[Box<dyn Tool>] {
+        &self.tools
+    }
+
+    /// The currently selected tool
+    pub fn active(&self) -> &dyn Tool {
+        self.tools[self.active].as_ref()
+    }
+
+    pub fn active_mut(&mut self) -> &mut dyn Tool {
+        self.tools[self.active].as_mut()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    /// Selects the tool at the given position, ignoring out of range indices
+    pub fn set_active(&mut self, index: usize) {
+        if index < self.tools.len() {
+            self.active = index;
+        }
+    }
+
+    /// Finds the position of the tool with the given id
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.tools.iter().position(|tool| tool.id() == id)
+    }
+
+    /// Ids of all registered tools, in command center order
+    pub fn ids(&self) -> Vec<&'static str> {
+        self.tools.iter().map(|tool| tool.id()).collect()
+    }
+
+    /// Tells every tool the measured image changed, see [`Tool::image_changed`]
+    pub fn image_changed(&self, generation: u64) {
+        for tool in &self.tools {
+            tool.image_changed(generation);
+        }
+    }
+
+    /// Tells every tool the screen was just captured, see [`Tool::screen_captured`]
+    pub fn screen_captured(&self, img: &RgbImage, generation: u64) {
+        for tool in &self.tools {
+            tool.screen_captured(img, generation);
+        }
+    }
+}
+
+impl Default for ToolRegistry {
+    /// Registry with every built-in tool, in command center order
+    fn default() -> Self {
+        let mut registry = ToolRegistry::new();
+        registry.register(CrossTool);
+        registry.register(LineTool::new(Axes::Horizontal));
+        registry.register(LineTool::new(Axes::Vertical));
+        registry.register(AngleTool::new());
+        registry.register(RayTool::new());
+        registry.register(CircleTool);
+        registry.register(ColorTool);
+        registry.register(WindowsTool::new());
+        registry.register(ComponentsTool::new());
+        registry.register(TypographyTool);
+        registry.register(AnnotateTool::new(Kind::Text));
+        registry.register(AnnotateTool::new(Kind::Arrow));
+        registry.register(AnnotateTool::new(Kind::Highlight));
+        registry.register(RedlineTool);
+        registry
+    }
+}
+
+/// Draws the vertical arm of the crosshair with a double tick at each end
+fn draw_vertical_arm(cr: &cairo::Context, measurement: &Measurement) {
+    let limits = &measurement.limits;
+    let (x, y) = (measurement.x, measurement.y);
+    let lower_x = x.saturating_sub(4);
+    let upper_x = x + 4;
+
+    // Draw vertical line above the crosshair center
+    cr.move_to(x as f64, y as f64);
+    cr.line_to(x as f64, limits.top as f64);
+    let _ = cr.stroke();
+    cr.move_to((lower_x) as f64, limits.top as f64);
+    cr.line_to((upper_x) as f64, limits.top as f64);
+    let _ = cr.stroke();
+    cr.move_to((lower_x) as f64, (limits.top + 1) as f64);
+    cr.line_to((upper_x) as f64, (limits.top + 1) as f64);
+    let _ = cr.stroke();
+
+    // Draw vertical line below the crosshair center
+    cr.move_to(x as f64, y as f64);
+    cr.line_to(x as f64, limits.bottom as f64);
+    let _ = cr.stroke();
+    cr.move_to((lower_x) as f64, limits.bottom as f64);
+    cr.line_to((upper_x) as f64, limits.bottom as f64);
+    let _ = cr.stroke();
+    cr.move_to((lower_x) as f64, (limits.bottom - 1) as f64);
+    cr.line_to((upper_x) as f64, (limits.bottom - 1) as f64);
+    let _ = cr.stroke();
+}
+
+/// Draws the horizontal arm of the crosshair with a double tick at each end
+fn draw_horizontal_arm(cr: &cairo::Context, measurement: &Measurement) {
+    let limits = &measurement.limits;
+    let (x, y) = (measurement.x, measurement.y);
+    let lower_y = y.saturating_sub(4);
+    let upper_y = y + 4;
+
+    // Draw horizontal line to the left of crosshair center
+    cr.move_to(x as f64, y as f64);
+    cr.line_to(limits.left as f64, y as f64);
+    let _ = cr.stroke();
+    cr.move_to(limits.left as f64, (lower_y) as f64);
+    cr.line_to(limits.left as f64, (upper_y) as f64);
+    let _ = cr.stroke();
+    cr.move_to((limits.left + 1) as f64, (lower_y) as f64);
+    cr.line_to((limits.left + 1) as f64, (upper_y) as f64);
+    let _ = cr.stroke();
+
+    // Draw horizontal line to the right of crosshair center
+    cr.move_to(x as f64, y as f64);
+    cr.line_to(limits.right as f64, y as f64);
+    let _ = cr.stroke();
+    cr.move_to(limits.right as f64, (lower_y) as f64);
+    cr.line_to(limits.right as f64, (upper_y) as f64);
+    let _ = cr.stroke();
+    cr.move_to((limits.right - 1) as f64, (lower_y) as f64);
+    cr.line_to((limits.right - 1) as f64, (upper_y) as f64);
+    let _ = cr.stroke();
+}
+
+/// Opacity of the fill over measured rectangles
+const HIGHLIGHT_ALPHA: f64 = 0.15;
+
+/// Tints the measured rectangle, inside the pixels past its edges
+fn draw_highlight(cr: &cairo::Context, measurement: &Measurement) {
+    let limits = &measurement.limits;
+    cr.rectangle(
+        f64::from(limits.left),
+        f64::from(limits.top),
+        f64::from(limits.right - 1 - limits.left),
+        f64::from(limits.bottom - 1 - limits.top),
+    );
+    cr.save().unwrap();
+    cr.clip();
+    let _ = cr.paint_with_alpha(HIGHLIGHT_ALPHA);
+    cr.restore().unwrap();
+}
+
+/// Draws the dot marking the measured point
+pub(crate) fn draw_center(cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+    cr.arc(
+        measurement.x as f64,
+        measurement.y as f64,
+        3.0 / scale,
+        0.0,
+        2.0 * std::f64::consts::PI,
+    );
+    cr.fill().unwrap();
+}
@@ -0,0 +1,97 @@
+use super::{Tool, draw_center};
+use crate::assets;
+use gtk4::{
+    cairo,
+    gdk::{Key, ModifierType},
+};
+use image::RgbImage;
+use rustyruler_core::{Measurement, Shape, Units};
+
+/// Degrees the ray turns per scroll step
+const ROTATION_STEP: f64 = 1.0;
+
+/// Half the length of the ticks at each end of the ray, in image pixels
+const TICK_SIZE: f64 = 4.0;
+
+/// Line at an arbitrary angle measuring the length of an element along it
+///
+/// Scrolling with Shift held rotates the line, counterclockwise when scrolling up.
+/// Ctrl is left alone, holding it shows the command center by default.
+pub struct RayTool {
+    /// Angle of the line in degrees, counterclockwise from the horizontal
+    degrees: f64,
+}
+
+impl RayTool {
+    pub fn new() -> Self {
+        RayTool { degrees: 45.0 }
+    }
+}
+
+impl Tool for RayTool {
+    fn id(&self) -> &'static str {
+        "ray"
+    }
+
+    fn name(&self) -> &'static str {
+        "Angled line"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("ray.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::r
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        rustyruler_core::measure_along(img, x, y, self.degrees, threshold)
+    }
+
+    fn scroll(&mut self, delta: f64, state: ModifierType) -> bool {
+        if !state.contains(ModifierType::SHIFT_MASK) {
+            return false;
+        }
+
+        // Lines have no direction, so angles wrap around at 180°
+        self.degrees = (self.degrees - delta * ROTATION_STEP).rem_euclid(180.0);
+        true
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        let Shape::Ray { start, end, .. } = measurement.shape else {
+            return;
+        };
+
+        cr.move_to(start.x, start.y);
+        cr.line_to(end.x, end.y);
+        let _ = cr.stroke();
+
+        // Ticks perpendicular to the line at both ends
+        let direction = start.direction_to(&end);
+        let (tick_x, tick_y) = (-direction.sin() * TICK_SIZE, direction.cos() * TICK_SIZE);
+        for point in [start, end] {
+            cr.move_to(point.x - tick_x, point.y - tick_y);
+            cr.line_to(point.x + tick_x, point.y + tick_y);
+            let _ = cr.stroke();
+        }
+
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, _units: &Units) -> String {
+        match measurement.shape {
+            Shape::Ray { degrees, .. } => format!(
+                "{:.1} px at {:.0}°",
+                measurement.shape.length().unwrap_or_default(),
+                degrees
+            ),
+            _ => String::new(),
+        }
+    }
+
+    fn shows_unit(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,48 @@
+use super::{Tool, draw_center};
+use crate::assets;
+use gtk4::{cairo, gdk::Key};
+use image::RgbImage;
+use rustyruler_core::{Measurement, Shape, Units};
+
+/// Lays out a redline over a region instead of measuring, see [`crate::redline`]
+///
+/// The region is selected by dragging, and a click without dragging clears it.
+pub struct RedlineTool;
+
+impl Tool for RedlineTool {
+    fn id(&self) -> &'static str {
+        "redline"
+    }
+
+    fn name(&self) -> &'static str {
+        "Redline"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("redline.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::s
+    }
+
+    fn compute(&self, _img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        Some(Measurement::at(x, y, threshold, Shape::Extent))
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, _measurement: &Measurement, _units: &Units) -> String {
+        "Drag to select a region".to_string()
+    }
+
+    fn shows_unit(&self) -> bool {
+        false
+    }
+
+    fn selects_region(&self) -> bool {
+        true
+    }
+}
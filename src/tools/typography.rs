@@ -0,0 +1,143 @@
+use super::{Tool, draw_center};
+use crate::assets;
+use gtk4::{cairo, gdk::Key};
+use image::RgbImage;
+use rustyruler_core::units::Axis;
+use rustyruler_core::{Measurement, Shape, Units};
+
+/// Distance in screen pixels between the ink of the line and the brackets beside it
+const BRACKET_OFFSET: f64 = 8.0;
+
+/// Metrics of the line of text under the cursor and the spacing to the next one
+///
+/// See [`rustyruler_core::typography`] for how the lines and their metrics are found.
+pub struct TypographyTool;
+
+impl Tool for TypographyTool {
+    fn id(&self) -> &'static str {
+        "typography"
+    }
+
+    fn name(&self) -> &'static str {
+        "Typography"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("typography.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::f
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, threshold: f32) -> Option<Measurement> {
+        // Away from text the cursor is still shown, with nothing measured
+        Some(
+            rustyruler_core::measure_text(img, x, y, threshold)
+                .unwrap_or_else(|| Measurement::at(x, y, threshold, Shape::Extent)),
+        )
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        let Shape::Text {
+            baseline,
+            x_height,
+            cap_height,
+            line_height,
+            spacing,
+        } = measurement.shape
+        else {
+            draw_center(cr, measurement, scale);
+            return;
+        };
+
+        // Bounds of the ink, inside the pixels past its edges
+        let limits = &measurement.limits;
+        let (left, right) = (f64::from(limits.left), f64::from(limits.right - 1));
+        let (top, bottom) = (f64::from(limits.top), f64::from(limits.bottom - 1));
+        let baseline = f64::from(baseline);
+        cr.rectangle(left, top, right - left, bottom - top);
+        let _ = cr.stroke();
+
+        // Baselines are solid, the heights above them dashed
+        let line = |y: f64| {
+            cr.move_to(left, y);
+            cr.line_to(right, y);
+        };
+        line(baseline);
+        if let Some(line_height) = line_height {
+            line(baseline + f64::from(line_height));
+        }
+        let _ = cr.stroke();
+        cr.set_dash(&[4.0 / scale, 4.0 / scale], 0.0);
+        line(baseline - f64::from(cap_height));
+        if let Some(x_height) = x_height {
+            line(baseline - f64::from(x_height));
+        }
+        let _ = cr.stroke();
+        cr.set_dash(&[], 0.0);
+
+        // The line height on the right, the space between the lines on the left
+        let offset = BRACKET_OFFSET / scale;
+        if let Some(line_height) = line_height {
+            draw_bracket(cr, right + offset, baseline, line_height, scale);
+        }
+        if let Some(spacing) = spacing {
+            draw_bracket(cr, left - offset, bottom, spacing, scale);
+        }
+
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, units: &Units) -> String {
+        let Shape::Text {
+            x_height,
+            cap_height,
+            line_height,
+            spacing,
+            ..
+        } = measurement.shape
+        else {
+            return "No text found".to_string();
+        };
+
+        let unit = units.unit.label();
+        let format = |pixels| units.format(pixels, Axis::Vertical, units.context.screen_size.1);
+        let heights = match x_height {
+            Some(x_height) => format!(
+                "Cap {} · x {} {}",
+                format(cap_height),
+                format(x_height),
+                unit
+            ),
+            None => format!("Cap {} {}", format(cap_height), unit),
+        };
+        let next = match (line_height, spacing) {
+            (Some(line_height), Some(spacing)) => format!(
+                "Line {} · gap {} {}",
+                format(line_height),
+                format(spacing),
+                unit
+            ),
+            _ => "No line below".to_string(),
+        };
+        format!("{heights}\n{next}")
+    }
+
+    fn shows_unit(&self) -> bool {
+        false
+    }
+}
+
+/// Draws a vertical span of the given length from `y` down, with a tick at each end
+fn draw_bracket(cr: &cairo::Context, x: f64, y: f64, length: u32, scale: f64) {
+    let tick = 3.0 / scale;
+    let end = y + f64::from(length);
+    cr.move_to(x, y);
+    cr.line_to(x, end);
+    for y in [y, end] {
+        cr.move_to(x - tick, y);
+        cr.line_to(x + tick, y);
+    }
+    let _ = cr.stroke();
+}
@@ -0,0 +1,149 @@
+use super::{Tool, draw_center, draw_highlight, draw_horizontal_arm, draw_vertical_arm};
+use crate::{assets, compositor};
+use gtk4::{cairo, gdk::Key, gio, glib};
+use image::RgbImage;
+use rustyruler_core::{Measurement, Rect, Units};
+use std::cell::{Cell, Ref, RefCell};
+use std::rc::Rc;
+
+/// Measures windows and the gaps between them from the compositor's window tree
+///
+/// The window geometry is read on a worker thread every time the screen is
+/// captured, so it matches the screenshot, and dropped once another image is
+/// measured. A saved session shows a desktop long gone, so nothing is read
+/// and the tool measures nothing there.
+pub struct WindowsTool {
+    /// Generation of the measured image, see [`Tool::image_changed`]
+    generation: Cell<u64>,
+    /// Windows of the capture of the given generation, shared with the worker reading them
+    windows: Rc<RefCell<Option<(u64, Vec<Rect>)>>>,
+    /// Generation of the capture the windows are read for, newer captures replace older ones
+    reading: Rc<Cell<Option<u64>>>,
+    on_ready: RefCell<Option<Rc<dyn Fn()>>>,
+}
+
+impl WindowsTool {
+    pub fn new() -> Self {
+        WindowsTool {
+            generation: Cell::new(0),
+            windows: Rc::new(RefCell::new(None)),
+            reading: Rc::new(Cell::new(None)),
+            on_ready: RefCell::new(None),
+        }
+    }
+
+    /// Windows of the measured image, once they are read
+    fn windows(&self) -> Option<Ref<'_, [Rect]>> {
+        let generation = self.generation.get();
+        Ref::filter_map(self.windows.borrow(), |windows| match windows {
+            Some((read_for, windows)) if *read_for == generation => Some(windows.as_slice()),
+            _ => None,
+        })
+        .ok()
+    }
+}
+
+impl Tool for WindowsTool {
+    fn id(&self) -> &'static str {
+        "windows"
+    }
+
+    fn name(&self) -> &'static str {
+        "Windows"
+    }
+
+    fn icon(&self) -> gtk4::Image {
+        gtk4::Image::from_paintable(Some(&assets::texture("windows.png")))
+    }
+
+    fn shortcut(&self) -> Key {
+        Key::w
+    }
+
+    fn compute(&self, img: &RgbImage, x: u32, y: u32, _threshold: f32) -> Option<Measurement> {
+        let (width, height) = img.dimensions();
+        rustyruler_core::measure_among(&self.windows()?, x, y, width, height)
+    }
+
+    fn image_changed(&self, generation: u64) {
+        self.generation.set(generation);
+        let stale = |read_for: u64| read_for != generation;
+        if self.reading.get().is_some_and(stale) {
+            self.reading.set(None);
+        }
+        let mut windows = self.windows.borrow_mut();
+        if windows
+            .as_ref()
+            .is_some_and(|&(read_for, _)| stale(read_for))
+        {
+            *windows = None;
+        }
+    }
+
+    fn screen_captured(&self, img: &RgbImage, generation: u64) {
+        *self.windows.borrow_mut() = None;
+        self.reading.set(Some(generation));
+
+        let (width, height) = img.dimensions();
+        let windows = self.windows.clone();
+        let reading = self.reading.clone();
+        let on_ready = self.on_ready.borrow().clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(compositor::layout).await;
+            if reading.get() != Some(generation) {
+                return;
+            }
+            reading.set(None);
+
+            match result {
+                Ok(Ok(layout)) => {
+                    let rects = layout.window_rects(width, height);
+                    *windows.borrow_mut() = Some((generation, rects));
+                    if let Some(on_ready) = on_ready {
+                        on_ready();
+                    }
+                }
+                Ok(Err(err)) => eprintln!("{err}"),
+                Err(_) => eprintln!("Error reading the windows: the worker thread panicked"),
+            }
+        });
+    }
+
+    fn reads_desktop(&self) -> bool {
+        true
+    }
+
+    fn set_on_ready(&self, on_ready: Rc<dyn Fn()>) {
+        *self.on_ready.borrow_mut() = Some(on_ready);
+    }
+
+    fn draw(&self, cr: &cairo::Context, measurement: &Measurement, scale: f64) {
+        // Outline of every window, dashed to tell them from the measurement
+        if let Some(windows) = self.windows() {
+            cr.set_dash(&[4.0 / scale, 4.0 / scale], 0.0);
+            for window in windows.iter() {
+                cr.rectangle(
+                    f64::from(window.x),
+                    f64::from(window.y),
+                    f64::from(window.width),
+                    f64::from(window.height),
+                );
+            }
+            let _ = cr.stroke();
+            cr.set_dash(&[], 0.0);
+        }
+
+        draw_highlight(cr, measurement);
+        draw_vertical_arm(cr, measurement);
+        draw_horizontal_arm(cr, measurement);
+        draw_center(cr, measurement, scale);
+    }
+
+    fn tooltip_text(&self, measurement: &Measurement, units: &Units) -> String {
+        format!(
+            "{} × {}",
+            units.width(measurement),
+            units.height(measurement)
+        )
+    }
+}
@@ -1,102 +1,451 @@
+use crate::accessibility::{Accessible, Inspector};
+use crate::annotations::{Annotation, Annotations, Part, TextEditor};
+use crate::assets;
+use crate::compare::{self, Compare};
+use crate::compositor;
+use crate::config::{self, Config, TooltipStyle};
+use crate::export;
+use crate::guides::{self, Grab};
+use crate::history::{self, History, HistoryPanel, Pin, Record};
+use crate::keybindings::{self, Action, Keymap};
+use crate::live::LiveCapture;
+use crate::redline::Redline;
 use crate::screenshot;
+use crate::session::{self, SavedPin, Session};
+use crate::tools::{self, Tool, ToolRegistry};
 use glib::Propagation;
+use gtk4::gio;
 use gtk4::{
     Application, ApplicationWindow, Box, CssProvider, DrawingArea, EventControllerKey,
-    EventControllerMotion, Overlay, cairo,
-    gdk::{Display, Key},
-    gdk_pixbuf::Pixbuf,
+    EventControllerMotion, EventSequenceState, GestureClick, GestureDrag, Grid, Label, Overlay,
+    ToggleButton, cairo,
+    gdk::{Display, ModifierType},
+    gdk_pixbuf::{Colorspace, Pixbuf},
     prelude::*,
 };
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
-use std::cell::RefCell;
-use std::path::PathBuf;
+use rustyruler_core::grid::{self, Columns, EdgeSnap};
+use rustyruler_core::guides::{Guide, Orientation};
+use rustyruler_core::units::{Axis, UnitContext, Units};
+use rustyruler_core::{DEFAULT_THRESHOLD, Measurement, Point, Rect, Shape};
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Duration;
+
+/// How close in screen pixels a press must be to a guide or an edge to drag it
+const GUIDE_GRAB_DISTANCE: f64 = 6.0;
+/// How far in screen pixels a dragged guide jumps to reach an edge
+const GUIDE_SNAP_DISTANCE: f64 = 8.0;
 
 #[derive(Clone)]
 struct CrosshairData {
-    x: u32,
-    y: u32,
-    top_limit: u32,
-    bottom_limit: u32,
-    left_limit: u32,
-    right_limit: u32,
-    initialized: bool,
+    /// Latest measurement, `None` until the cursor enters the window
+    measurement: Option<Measurement>,
     magnitude_threshold: f32,
+    /// Measurements kept on screen, with the ones that can be redone
+    history: History,
+    /// Unit sizes are shown in, with what is known about the monitor
+    units: Units,
+    /// Whether the grid and columns are drawn over the screenshot
+    show_grid: bool,
+    guides: Vec<Guide>,
+    /// App focused when the screen was captured, the guides are saved for it
+    app: Option<String>,
+    /// Whether a click copies the color under the cursor and closes the overlay
+    pick_color: bool,
+    /// Element under the cursor in the accessibility tree, while looked up
+    accessible: Option<Accessible>,
+    /// Reference image the capture is compared with, once one is loaded or kept
+    compare: Option<Compare>,
+    /// Session file the capture was opened from or last saved to, saved over next time
+    session: Option<PathBuf>,
+    /// Labels, arrows and highlights drawn over the capture
+    annotations: Annotations,
+    /// Bumped whenever the measured image changes, tools keep what they found in it until then
+    image_generation: u64,
+    /// Region selected with the redline tool, with the elements found in it
+    redline: Option<Redline>,
+}
+
+impl CrosshairData {
+    /// Image the tools measure, the reference while it is measured and the capture otherwise
+    fn measured_image<'a>(&'a self, capture: &'a Capture) -> &'a image::RgbImage {
+        self.compare
+            .as_ref()
+            .and_then(Compare::measured_image)
+            .unwrap_or(&capture.rgb_image)
+    }
 }
 
+/// The screenshot being measured, decoded for color analysis and for drawing
+/// Live mode replaces it with every new capture
+struct Capture {
+    rgb_image: image::RgbImage,
+    pixbuf: Pixbuf,
+}
+
+/// Captures the screen and opens the overlay to measure it
 pub fn build_ui(app: &Application) {
+    open_overlay(app, false, None);
+}
+
+/// Captures the screen and opens the overlay comparing it with a reference image
+pub fn build_compare(app: &Application, reference: &Path) {
+    open_overlay(app, false, Some(reference));
+}
+
+/// Captures the screen and opens the overlay with the color picker selected
+/// Clicking copies the color under the cursor and closes the overlay
+pub fn build_color_picker(app: &Application) {
+    open_overlay(app, true, None);
+}
+
+fn open_overlay(app: &Application, pick_color: bool, reference: Option<&Path>) {
+    let (config, save_session) = load_config();
+
     let original_screenshot_path: PathBuf = match screenshot::capture_original_screenshot() {
         Ok(path) => path,
         Err(err) => {
             eprintln!("Error capturing original screenshot: {:?}", err);
-            std::process::exit(1);
+            return;
+        }
+    };
+
+    let capture = match load_image_data(&original_screenshot_path) {
+        Ok(capture) => Rc::new(RefCell::new(capture)),
+        Err(err) => {
+            eprintln!("Error loading screenshot: {:?}", err);
+            if let Err(err) = screenshot::cleanup_screenshot(&original_screenshot_path) {
+                eprintln!("Error cleaning up screenshot: {:?}", err);
+            }
+            return;
         }
     };
 
+    // A reference that fails to load leaves the capture to be measured alone
+    let compare = reference.and_then(|path| match screenshot::load_image(path) {
+        Ok(rgb_image) => {
+            let Capture { rgb_image, pixbuf } = capture_from_image(rgb_image);
+            let mut compare = Compare::new(rgb_image, pixbuf, &config);
+            compare.update(&capture.borrow().rgb_image, config.magnitude_threshold);
+            Some(compare)
+        }
+        Err(err) => {
+            eprintln!("Error loading reference {}: {:?}", path.display(), err);
+            None
+        }
+    });
+
+    // Asked on a worker thread right away, before the overlay is mapped and takes the focus
+    let focused_app = gio::spawn_blocking(compositor::focused_app);
+
     let window = create_and_configure_window(app);
+    let crosshair_data = create_crosshair_data(&config, &capture.borrow(), pick_color, compare);
+
+    // Available tools and the currently selected one
+    let tools = Rc::new(RefCell::new(create_tool_registry(&config)));
+    if pick_color && let Some(index) = tools.borrow().index_of("color") {
+        tools.borrow_mut().set_active(index);
+    }
 
-    let provider = CssProvider::new();
-    provider.load_from_path("assets/style.css");
-    gtk4::style_context_add_provider_for_display(
-        &Display::default().expect("Could not connect to a display."),
-        &provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    build_overlay(
+        &window,
+        capture,
+        crosshair_data,
+        tools,
+        Some(original_screenshot_path),
+        Some(focused_app),
+        config,
+        // The color picker leaves the tool and guides of the last measurement alone
+        save_session && !pick_color,
     );
 
-    let screenshot_path_for_cleanup = original_screenshot_path.clone();
-    let (rgb_image, pixbuf) = load_image_data(&original_screenshot_path);
-    let img_width = pixbuf.width() as u32;
-    let img_height = pixbuf.height() as u32;
+    window.grab_focus();
+    window.fullscreen();
+    window.present();
+}
+
+/// Opens a saved session in a regular window, with its pins, guides and settings
+/// Nothing is captured, and the configuration is left as it is when the window closes
+pub fn open_session(app: &Application, path: &Path) {
+    let (config, _) = load_config();
+
+    let (rgb_image, session) = match session::load(path) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("Error opening session {err}");
+            return;
+        }
+    };
+    let capture = Rc::new(RefCell::new(capture_from_image(rgb_image)));
+
+    let window = create_viewer_window(app, path);
+    let crosshair_data = create_crosshair_data(&config, &capture.borrow(), false, None);
+    let tools = Rc::new(RefCell::new(create_tool_registry(&config)));
+    restore_session(
+        &mut crosshair_data.borrow_mut(),
+        &mut tools.borrow_mut(),
+        session,
+        path,
+    );
+    refresh_measurement(
+        &mut crosshair_data.borrow_mut(),
+        &tools.borrow(),
+        &capture.borrow(),
+    );
+
+    build_overlay(
+        &window,
+        capture,
+        crosshair_data,
+        tools,
+        None,
+        None,
+        config,
+        false,
+    );
+
+    window.maximize();
+    window.present();
+}
+
+/// Loads the configuration, and whether the session may be saved back to it
+/// A broken configuration falls back to the defaults, and is never overwritten
+fn load_config() -> (Rc<Config>, bool) {
+    let (config, save_session) = match config::load() {
+        Ok(config) => (config, true),
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("Using the default configuration");
+            (Config::default(), false)
+        }
+    };
+    (Rc::new(config), save_session)
+}
+
+/// State of a new overlay, with the settings from the configuration
+fn create_crosshair_data(
+    config: &Config,
+    capture: &Capture,
+    pick_color: bool,
+    compare: Option<Compare>,
+) -> Rc<RefCell<CrosshairData>> {
+    let (img_width, img_height) = capture.rgb_image.dimensions();
+
+    Rc::new(RefCell::new(CrosshairData {
+        measurement: None,
+        magnitude_threshold: config.magnitude_threshold,
+        history: History::default(),
+        units: Units::new(
+            config.units,
+            UnitContext {
+                base_font_size: config.base_font_size,
+                percent_of: config.percent_of,
+                screen_size: (img_width, img_height),
+                ..UnitContext::default()
+            },
+        ),
+        show_grid: config.grid.visible,
+        pick_color,
+        accessible: None,
+        compare,
+        session: None,
+        annotations: Annotations::default(),
+        redline: None,
+        image_generation: 0,
+        guides: Vec::new(),
+        app: None,
+    }))
+}
 
-    let crosshair_data = Rc::new(RefCell::new(CrosshairData {
-        x: 0,
-        y: 0,
-        top_limit: 0,
-        bottom_limit: 0,
-        left_limit: 0,
-        right_limit: 0,
-        initialized: false,
-        magnitude_threshold: 20.0,
-    }));
+/// Adds the guides saved for the app focused when the screen was captured, once it is known
+/// Guides placed meanwhile are kept, and saved for the app along with them
+fn load_app_guides(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    focused_app: gio::JoinHandle<Option<String>>,
+) {
+    let drawing_area = drawing_area.clone();
+    glib::spawn_future_local(async move {
+        let app = match focused_app.await {
+            Ok(Some(app)) => app,
+            Ok(None) => return,
+            Err(_) => {
+                eprintln!("Error finding the focused app: the worker thread panicked");
+                return;
+            }
+        };
+        let saved_guides = config::load_guides(&app).unwrap_or_else(|err| {
+            eprintln!("Error loading guides: {err}");
+            Vec::new()
+        });
+
+        let mut data = crosshair_data.borrow_mut();
+        // Guides saved from a capture of a larger screen may not fit this one
+        let (img_width, img_height) = data.units.context.screen_size;
+        data.guides.extend(
+            saved_guides
+                .into_iter()
+                .filter(|guide| match guide.orientation {
+                    Orientation::Horizontal => guide.position <= img_height,
+                    Orientation::Vertical => guide.position <= img_width,
+                }),
+        );
+        data.app = Some(app);
+        drawing_area.queue_draw();
+    });
+}
 
+/// Fills the window with the capture, the panels and the event handlers
+/// `screenshot_path` is the temporary capture deleted on close, and `focused_app`
+/// the app the guides are loaded for, both `None` for a session
+#[allow(clippy::too_many_arguments)]
+fn build_overlay(
+    window: &ApplicationWindow,
+    capture: Rc<RefCell<Capture>>,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    screenshot_path: Option<PathBuf>,
+    focused_app: Option<gio::JoinHandle<Option<String>>>,
+    config: Rc<Config>,
+    save_session: bool,
+) {
+    let (img_width, img_height) = capture.borrow().rgb_image.dimensions();
     let scale_and_offset = Rc::new(RefCell::new((1.0_f64, 0.0_f64, 0.0_f64)));
-    // Track the currently selected tool: 0 = cross, 1 = line, 2 = rotated line
-    let active_tool = Rc::new(RefCell::new(0));
+    let keymap = Rc::new(Keymap::new(&config.keys, &tools.borrow()));
 
     let drawing_area = create_drawing_area(
-        &pixbuf,
+        capture.clone(),
         img_width,
         img_height,
         crosshair_data.clone(),
         scale_and_offset.clone(),
-        active_tool.clone(),
+        tools.clone(),
+        config.clone(),
+    );
+
+    let live_indicator = Label::builder()
+        .label("Live")
+        .css_classes(vec!["live-indicator"])
+        .halign(gtk4::Align::End)
+        .valign(gtk4::Align::Start)
+        .can_target(false)
+        .visible(false)
+        .build();
+    // A session shows a saved capture, the screen is never captured again over it
+    let live = screenshot_path.is_some().then(|| {
+        create_live_capture(
+            window,
+            &live_indicator,
+            &drawing_area,
+            capture.clone(),
+            crosshair_data.clone(),
+            tools.clone(),
+            &config,
+        )
+    });
+
+    let inspector = create_inspector(&drawing_area, crosshair_data.clone(), &config);
+    if let Some(focused_app) = focused_app {
+        load_app_guides(&drawing_area, crosshair_data.clone(), focused_app);
+    }
+    setup_tool_results(
+        &drawing_area,
+        capture.clone(),
+        crosshair_data.clone(),
+        &tools,
+    );
+    // A session shows a desktop long gone, only a capture has one to read along with it
+    if screenshot_path.is_some() {
+        tools.borrow().screen_captured(
+            &capture.borrow().rgb_image,
+            crosshair_data.borrow().image_generation,
+        );
+    }
+    let history_panel = create_history_panel(
+        &drawing_area,
+        crosshair_data.clone(),
+        &tools.borrow(),
+        &config,
     );
 
-    let command_center = create_command_center(active_tool.clone());
+    let (command_center, tool_buttons) =
+        create_command_center(tools.clone(), screenshot_path.is_some());
     let overlay = Overlay::builder().child(&drawing_area).build();
     overlay.add_overlay(&command_center);
+    overlay.add_overlay(&live_indicator);
+    overlay.add_overlay(history_panel.widget());
     command_center.set_visible(false);
 
+    let text_editor = create_text_editor(&drawing_area, crosshair_data.clone());
+    overlay.add_overlay(text_editor.widget());
+
+    let help_overlay = create_help_overlay(&keymap, &tools.borrow());
+    overlay.add_overlay(&help_overlay);
+    help_overlay.set_visible(false);
+
     setup_event_handlers(
-        &window,
+        window,
         &drawing_area,
         crosshair_data.clone(),
-        rgb_image.clone(),
-        original_screenshot_path.clone(),
+        capture,
+        live,
+        inspector,
+        history_panel,
+        text_editor,
+        screenshot_path.clone(),
         scale_and_offset,
         &command_center,
-        active_tool,
+        &help_overlay,
+        tool_buttons,
+        tools.clone(),
+        keymap,
+        config,
     );
 
-    setup_cleanup(&window, screenshot_path_for_cleanup);
+    setup_unit_context(window, crosshair_data.clone());
+
+    setup_cleanup(window, screenshot_path, crosshair_data, tools, save_session);
 
     window.set_child(Some(&overlay));
-    window.grab_focus();
-    window.fullscreen();
-    window.present();
+}
+
+/// Applies the bundled stylesheet and any user overrides on top of it
+/// Done once at startup, for the overlay and for the countdown before it
+pub fn load_stylesheets() {
+    let display = Display::default().expect("Could not connect to a display.");
+
+    for (index, css) in assets::stylesheets().iter().enumerate() {
+        let provider = CssProvider::new();
+        provider.load_from_data(css);
+        gtk4::style_context_add_provider_for_display(
+            &display,
+            &provider,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION + index as u32,
+        );
+    }
+}
+
+/// Creates the tool registry with the default tool from the configuration
+fn create_tool_registry(config: &Config) -> ToolRegistry {
+    let mut registry = ToolRegistry::default();
+
+    match registry.index_of(&config.tool) {
+        Some(index) => registry.set_active(index),
+        None => eprintln!(
+            "Unknown default tool \"{}\", expected one of: {}",
+            config.tool,
+            registry.ids().join(", ")
+        ),
+    }
+
+    registry
 }
 
 /// Draws a custom command center with straight top/bottom edges and inward-curved sides
+#[allow(clippy::too_many_arguments)]
 fn draw_command_center(
     cr: &cairo::Context,
     x: f64,
@@ -153,7 +502,13 @@ fn draw_command_center(
     cr.stroke().unwrap();
 }
 
-fn create_command_center(active_tool: Rc<RefCell<i32>>) -> Box {
+/// Builds the command center with one toggle button per registered tool
+/// Returns the container and the buttons, in registry order
+/// Tools reading the desktop are disabled unless the screen was `captured`
+fn create_command_center(
+    tools: Rc<RefCell<ToolRegistry>>,
+    captured: bool,
+) -> (Box, Vec<ToggleButton>) {
     // Create a container box for the command center
     let command_center_box = Box::builder()
         .css_classes(vec!["command-center-outer"])
@@ -171,93 +526,35 @@ fn create_command_center(active_tool: Rc<RefCell<i32>>) -> Box {
     background_drawing_area.set_vexpand(true);
     background_drawing_area.set_size_request(200, 60); // Adjusted size
 
-    // Create image toggle buttons
-    let image1 = gtk4::Image::from_file("assets/cross.png");
-    image1.set_pixel_size(16); // Reduced image size to fit with less padding
-    let button1 = gtk4::ToggleButton::new();
-    button1.set_child(Some(&image1));
-
-    let image2 = gtk4::Image::from_file("assets/line.png");
-    image2.set_pixel_size(16); // Reduced image size to fit with less padding
-    let button2 = gtk4::ToggleButton::new();
-    button2.set_child(Some(&image2));
-
-    // For the third button, we'll create a 90-degree rotated version of the line.png
-    // by using the image crate to rotate the image data and then create a GDK texture
-    let rotated_texture = {
-        // Load the original image using the image crate
-        let original_img = image::open("assets/line.png").expect("Failed to load line.png");
-        let rotated_img = original_img.rotate90(); // Rotate 90 degrees clockwise
-
-        // Get RGBA data from the rotated image
-        let rgba = rotated_img.to_rgba8();
-        let width = rgba.width();
-        let height = rgba.height();
-        let raw_data = rgba.as_raw();
-
-        // Create a GDK memory texture from the RGBA data
-        gtk4::gdk::MemoryTexture::new(
-            width as i32,
-            height as i32,
-            gtk4::gdk::MemoryFormat::R8g8b8a8,
-            &glib::Bytes::from(raw_data),
-            width as usize * 4,
-        )
-    };
-
-    let image3 = gtk4::Image::from_paintable(Some(&rotated_texture));
-    image3.set_pixel_size(16); // Reduced image size to fit with less padding
-    let button3 = gtk4::ToggleButton::new();
-    button3.set_child(Some(&image3));
-
-    // Make buttons behave like radio buttons (only one selected at a time)
-    // Connect signals to ensure only one button is active at a time and track the active tool
-    button1.connect_toggled({
-        let button2_clone = button2.clone();
-        let button3_clone = button3.clone();
-        let active_tool_clone = active_tool.clone();
-        move |btn| {
-            if btn.is_active() {
-                button2_clone.set_active(false);
-                button3_clone.set_active(false);
-                *active_tool_clone.borrow_mut() = 0;
-            }
-        }
-    });
-
-    button2.connect_toggled({
-        let button1_clone = button1.clone();
-        let button3_clone = button3.clone();
-        let active_tool_clone = active_tool.clone();
-        move |btn| {
-            if btn.is_active() {
-                button1_clone.set_active(false);
-                button3_clone.set_active(false);
-                *active_tool_clone.borrow_mut() = 1;
-            }
+    // Create one image toggle button per tool, grouped so only one is active at a time
+    let mut buttons: Vec<ToggleButton> = Vec::new();
+    for (index, tool) in tools.borrow().tools().iter().enumerate() {
+        let image = tool.icon();
+        image.set_pixel_size(16); // Reduced image size to fit with less padding
+        let button = ToggleButton::new();
+        button.set_child(Some(&image));
+        button.set_tooltip_text(Some(tool.name()));
+        button.set_can_focus(true);
+        button.set_sensitive(captured || !tool.reads_desktop());
+        if let Some(first) = buttons.first() {
+            button.set_group(Some(first));
         }
-    });
 
-    button3.connect_toggled({
-        let button1_clone = button1.clone();
-        let button2_clone = button2.clone();
-        let active_tool_clone = active_tool.clone();
-        move |btn| {
+        let tools_clone = tools.clone();
+        button.connect_toggled(move |btn| {
             if btn.is_active() {
-                button1_clone.set_active(false);
-                button2_clone.set_active(false);
-                *active_tool_clone.borrow_mut() = 2;
+                tools_clone.borrow_mut().set_active(index);
             }
-        }
-    });
+        });
 
-    // Set the first button (cross tool) as active by default
-    button1.set_active(true);
+        buttons.push(button);
+    }
 
-    // Apply basic styling to toggle buttons to make them look good over the rounded background
-    button1.set_can_focus(true);
-    button2.set_can_focus(true);
-    button3.set_can_focus(true);
+    // Reflect the registry's selected tool
+    let active_index = tools.borrow().active_index();
+    if let Some(button) = buttons.get(active_index) {
+        button.set_active(true);
+    }
 
     // Create an overlay to position the buttons over the background
     let overlay = Overlay::new();
@@ -271,9 +568,9 @@ fn create_command_center(active_tool: Rc<RefCell<i32>>) -> Box {
         .valign(gtk4::Align::Center)
         .build();
 
-    button_container.append(&button1);
-    button_container.append(&button2);
-    button_container.append(&button3);
+    for button in &buttons {
+        button_container.append(button);
+    }
 
     overlay.add_overlay(&button_container);
 
@@ -296,7 +593,43 @@ fn create_command_center(active_tool: Rc<RefCell<i32>>) -> Box {
 
     command_center_box.append(&overlay);
 
-    command_center_box
+    (command_center_box, buttons)
+}
+
+/// Builds the help overlay listing every action and the keys bound to it
+fn create_help_overlay(keymap: &Keymap, tools: &ToolRegistry) -> Box {
+    let help_box = Box::builder()
+        .css_classes(vec!["help-overlay"])
+        .orientation(gtk4::Orientation::Vertical)
+        .spacing(12)
+        .halign(gtk4::Align::Center)
+        .valign(gtk4::Align::Center)
+        .can_target(false)
+        .build();
+
+    let title = Label::builder()
+        .label("Key bindings")
+        .css_classes(vec!["help-title"])
+        .build();
+    help_box.append(&title);
+
+    let grid = Grid::builder().column_spacing(24).row_spacing(6).build();
+    for (row, (action, keys)) in keymap.entries().enumerate() {
+        let action_label = Label::builder()
+            .label(action.describe(tools))
+            .halign(gtk4::Align::Start)
+            .build();
+        let keys_label = Label::builder()
+            .label(keys.join(", "))
+            .css_classes(vec!["help-keys"])
+            .halign(gtk4::Align::End)
+            .build();
+        grid.attach(&action_label, 0, row as i32, 1, 1);
+        grid.attach(&keys_label, 1, row as i32, 1, 1);
+    }
+    help_box.append(&grid);
+
+    help_box
 }
 
 fn create_and_configure_window(app: &Application) -> ApplicationWindow {
@@ -318,150 +651,89 @@ fn create_and_configure_window(app: &Application) -> ApplicationWindow {
     window
 }
 
-/// Loads image data from the captured screenshot
-fn load_image_data(original_screenshot_path: &PathBuf) -> (Rc<image::RgbImage>, Pixbuf) {
-    // Load the RGB image for color analysis
-    let rgb_image = match screenshot::load_image(original_screenshot_path) {
-        Ok(img) => Rc::new(img),
-        Err(err) => {
-            eprintln!("Error loading RGB image: {:?}", err);
-            std::process::exit(1);
-        }
-    };
-
-    // Load the image as Pixbuf for drawing
-    let pixbuf = match Pixbuf::from_file(original_screenshot_path) {
-        Ok(pb) => pb,
-        Err(err) => {
-            eprintln!("Error loading pixbuf: {:?}", err);
-            std::process::exit(1);
-        }
-    };
+/// Regular window showing a session, titled with its file name
+fn create_viewer_window(app: &Application, path: &Path) -> ApplicationWindow {
+    let name = path.file_name().unwrap_or(path.as_os_str());
 
-    (rgb_image, pixbuf)
+    ApplicationWindow::builder()
+        .application(app)
+        .title(format!("{} - rustyruler", name.to_string_lossy()))
+        .default_width(1280)
+        .default_height(800)
+        .can_focus(true)
+        .build()
 }
 
-/// Draws the crosshair lines at the current position
-/// Draws the crosshair lines at the current position based on the active tool
-/// - Tool 0 (Cross): Draws full crosshair (both vertical and horizontal lines)
-/// - Tool 1 (Horizontal line): Draws only horizontal line
-/// - Tool 2 (Vertical line): Draws only vertical line
-fn draw_crosshair(cr: &cairo::Context, data: &CrosshairData, scale: f64, active_tool: i32) {
-    // Set crosshair color to red and line width
-
-    cr.set_source_rgb(1.0, 0.0, 0.0);
-    cr.set_line_width(1.0 / scale);
+/// Loads image data from the captured screenshot
+/// The file is decoded once, the pixbuf is made from the same pixels
+fn load_image_data(original_screenshot_path: &Path) -> io::Result<Capture> {
+    screenshot::load_image(original_screenshot_path).map(capture_from_image)
+}
 
-    if active_tool == 0 || active_tool == 2 {
-        let lower_x = data.x.saturating_sub(4);
-        let upper_x = data.x + 4;
-
-        // Draw vertical line above the crosshair center
-        cr.move_to(data.x as f64, data.y as f64);
-        cr.line_to(data.x as f64, data.top_limit as f64);
-        let _ = cr.stroke();
-        cr.move_to((lower_x) as f64, data.top_limit as f64);
-        cr.line_to((upper_x) as f64, data.top_limit as f64);
-        let _ = cr.stroke();
-        cr.move_to((lower_x) as f64, (data.top_limit + 1) as f64);
-        cr.line_to((upper_x) as f64, (data.top_limit + 1) as f64);
-        let _ = cr.stroke();
-
-        // Draw vertical line below the crosshair center
-        cr.move_to(data.x as f64, data.y as f64);
-        cr.line_to(data.x as f64, data.bottom_limit as f64);
-        let _ = cr.stroke();
-        cr.move_to((lower_x) as f64, data.bottom_limit as f64);
-        cr.line_to((upper_x) as f64, data.bottom_limit as f64);
-        let _ = cr.stroke();
-        cr.move_to((lower_x) as f64, (data.bottom_limit - 1) as f64);
-        cr.line_to((upper_x) as f64, (data.bottom_limit - 1) as f64);
-        let _ = cr.stroke();
-    }
-    if active_tool == 0 || active_tool == 1 {
-        let lower_y = data.y.saturating_sub(4);
-        let upper_y = data.y + 4;
-
-        // Draw horizontal line to the left of crosshair center
-        cr.move_to(data.x as f64, data.y as f64);
-        cr.line_to(data.left_limit as f64, data.y as f64);
-        let _ = cr.stroke();
-        cr.move_to(data.left_limit as f64, (lower_y) as f64);
-        cr.line_to(data.left_limit as f64, (upper_y) as f64);
-        let _ = cr.stroke();
-        cr.move_to((data.left_limit + 1) as f64, (lower_y) as f64);
-        cr.line_to((data.left_limit + 1) as f64, (upper_y) as f64);
-        let _ = cr.stroke();
-
-        // Draw horizontal line to the right of crosshair center
-        cr.move_to(data.x as f64, data.y as f64);
-        cr.line_to(data.right_limit as f64, data.y as f64);
-        let _ = cr.stroke();
-        cr.move_to(data.right_limit as f64, (lower_y) as f64);
-        cr.line_to(data.right_limit as f64, (upper_y) as f64);
-        let _ = cr.stroke();
-        cr.move_to((data.right_limit - 1) as f64, (lower_y) as f64);
-        cr.line_to((data.right_limit - 1) as f64, (upper_y) as f64);
-        let _ = cr.stroke();
-
-        // Draw center point of the crosshair
-        cr.arc(
-            data.x as f64,
-            data.y as f64,
-            3.0 / scale,
-            0.0,
-            2.0 * std::f64::consts::PI,
-        );
-        cr.fill().unwrap();
-    }
+/// Wraps a new capture for drawing, without decoding the screenshot file again
+fn capture_from_image(rgb_image: image::RgbImage) -> Capture {
+    let (width, height) = rgb_image.dimensions();
+    let pixbuf = Pixbuf::from_bytes(
+        &glib::Bytes::from(rgb_image.as_raw().as_slice()),
+        Colorspace::Rgb,
+        false,
+        8,
+        width as i32,
+        height as i32,
+        width as i32 * 3,
+    );
+    Capture { rgb_image, pixbuf }
 }
 
-/// Draws the tooltip with dimensions at the current position based on the active tool
+/// Draws the tooltip with the given text next to the measured point
+#[allow(clippy::too_many_arguments)]
 fn draw_tooltip(
     cr: &cairo::Context,
-    data: &CrosshairData,
+    measurement: &Measurement,
+    coords: &str,
+    style: &TooltipStyle,
     scale: f64,
-    scale_and_offset: &RefCell<(f64, f64, f64)>,
+    offset: (f64, f64),
     img_width: u32,
     img_height: u32,
-    active_tool: i32,
 ) {
     // Draw text showing the dimensions of the current selection
-    cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
-    cr.set_font_size(20.0 / scale);
-    let coords;
-    if active_tool == 0 {
-        let x_size = data.right_limit - data.left_limit + 1;
-        let y_size = data.bottom_limit - data.top_limit + 1;
-        coords = format!("{x_size} × {y_size}");
-    } else if active_tool == 1 {
-        let x_size = data.right_limit - data.left_limit + 1;
-        coords = format!("{x_size}");
-    } else {
-        let y_size = data.bottom_limit - data.top_limit + 1;
-        coords = format!("{y_size}");
-    }
+    cr.select_font_face(
+        &style.font,
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Normal,
+    );
+    cr.set_font_size(style.font_size / scale);
 
     // Get text dimensions for background calculation
-    let text_extents = cr.text_extents(&coords).unwrap();
-    let text_width = text_extents.width();
-    let text_height = text_extents.height();
+    // Lines after the first one are spaced by the height of the font
+    let lines: Vec<&str> = coords.lines().collect();
+    let text_width = lines
+        .iter()
+        .map(|line| cr.text_extents(line).unwrap().width())
+        .fold(0.0, f64::max);
+    let text_height = cr
+        .text_extents(lines.first().unwrap_or(&""))
+        .unwrap()
+        .height();
+    let line_height = cr.font_extents().unwrap().height();
+    let extra_height = lines.len().saturating_sub(1) as f64 * line_height;
 
     // Calculate initial position near crosshair center
-    let text_offset_x = 25.0 / scale;
-    let text_offset_y = 25.0 / scale;
-    let mut x_pos = data.x as f64 + text_offset_x;
-    let mut y_pos = data.y as f64 + text_offset_y + text_height; // Add text height to position below cursor
+    let text_offset_x = style.offset_x / scale;
+    let text_offset_y = style.offset_y / scale;
+    let mut x_pos = measurement.x as f64 + text_offset_x;
+    let mut y_pos = measurement.y as f64 + text_offset_y + text_height; // Add text height to position below cursor
 
     // Calculate background dimensions
-    let bg_padding = 8.0 / scale;
+    let bg_padding = style.padding / scale;
     let bg_width = text_width + 2.0 * bg_padding;
-    let bg_height = text_height + 2.0 * bg_padding;
-    let radius = 8.0 / scale; // Corner radius
+    let bg_height = text_height + extra_height + 2.0 * bg_padding;
+    let radius = style.radius / scale; // Corner radius
 
     // Boundary checking to keep the box within visible area
     // Calculate the maximum visible coordinates (after scaling and offsetting)
-    let (_, offset_x, offset_y) = *scale_and_offset.borrow();
+    let (offset_x, offset_y) = offset;
     let visible_width = img_width as f64 * scale;
     let visible_height = img_height as f64 * scale;
 
@@ -469,15 +741,15 @@ fn draw_tooltip(
     if x_pos + text_width + bg_padding > offset_x + visible_width {
         // Position to the left of the cursor with space between crosshair and box
         let space_from_cursor = 15.0 / scale; // Space between cursor and box
-        x_pos = (data.x as f64 - text_width - bg_padding - space_from_cursor)
+        x_pos = (measurement.x as f64 - text_width - bg_padding - space_from_cursor)
             .max(offset_x + bg_padding); // Position to the left of cursor if needed
     }
 
     // Adjust y position if box would extend beyond bottom edge
-    if y_pos > offset_y + visible_height - bg_padding {
+    if y_pos + extra_height > offset_y + visible_height - bg_padding {
         // Position above the cursor
-        let space_from_cursor = 25.0 / scale; // Space between cursor and box
-        y_pos = data.y as f64 - space_from_cursor; // Position above cursor
+        let space_from_cursor = text_offset_y; // Space between cursor and box
+        y_pos = measurement.y as f64 - space_from_cursor - extra_height; // Position above cursor
     }
 
     // Calculate final background position
@@ -519,199 +791,1028 @@ fn draw_tooltip(
     );
     cr.close_path();
 
-    // Draw background with semi-transparent black
-    cr.set_source_rgba(0.1, 0.1, 0.1, 0.9);
+    // Draw background
+    style.background.apply(cr);
     cr.fill_preserve().unwrap(); // fill and preserve the path for the border
 
     // Draw border
-    cr.set_source_rgb(0.2, 0.2, 0.2);
-    cr.set_line_width(2.0 / scale); // Border line width
-    cr.stroke().unwrap();
+    style.border.apply(cr);
+    cr.set_line_width(style.border_width / scale); // Border line width
+    if style.border_width > 0.0 {
+        cr.stroke().unwrap();
+    } else {
+        cr.new_path();
+    }
 
-    // Draw the text with improved color (white for better contrast)
-    cr.set_source_rgb(1.0, 1.0, 1.0); // White color for better contrast
-    cr.move_to(x_pos, y_pos);
-    cr.show_text(&coords).unwrap();
+    // Draw the text
+    style.text_color.apply(cr);
+    for (index, line) in lines.iter().enumerate() {
+        cr.move_to(x_pos, y_pos + index as f64 * line_height);
+        cr.show_text(line).unwrap();
+    }
 }
 
-/// Creates the drawing area with the drawing function
-fn create_drawing_area(
+/// Draws the screenshot with the pinned measurements and the current one
+/// The context must already be transformed to image coordinates with the given scale
+fn draw_scene(
+    cr: &cairo::Context,
     pixbuf: &Pixbuf,
-    img_width: u32,
-    img_height: u32,
-    crosshair_data: Rc<RefCell<CrosshairData>>,
-    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
-    active_tool: Rc<RefCell<i32>>,
-) -> DrawingArea {
-    let drawing_area = DrawingArea::new();
-    drawing_area.set_hexpand(true);
-    drawing_area.set_vexpand(true);
-
-    // Set up the drawing function for the drawing area
-    let pixbuf_clone = pixbuf.clone();
-    let crosshair_data_clone = crosshair_data.clone();
-    let scale_and_offset_clone = scale_and_offset.clone();
-    let active_tool_clone = active_tool.clone();
+    data: &CrosshairData,
+    tools: &ToolRegistry,
+    config: &Config,
+    scale: f64,
+    offset: (f64, f64),
+) {
+    let img_width = pixbuf.width() as u32;
+    let img_height = pixbuf.height() as u32;
 
-    drawing_area.set_draw_func(move |_, cr, width, height| {
-        // Calculate scaling to fit image while maintaining aspect ratio
-        let scale_x = width as f64 / img_width as f64;
-        let scale_y = height as f64 / img_height as f64;
-        let scale = scale_x.min(scale_y);
+    // Draw the background image
+    cr.set_source_pixbuf(pixbuf, 0.0, 0.0);
+    let _ = cr.paint();
 
-        // Calculate offsets to center the image
-        let offset_x = (width as f64 - img_width as f64 * scale) / 2.0;
-        let offset_y = (height as f64 - img_height as f64 * scale) / 2.0;
+    if let Some(compare) = &data.compare {
+        compare.draw(cr, config, scale, img_width, img_height);
+    }
 
-        // Store scale and offset for coordinate conversion
-        *scale_and_offset_clone.borrow_mut() = (scale, offset_x, offset_y);
+    let layout = data
+        .show_grid
+        .then(|| layout_grid(config, data.units.context.scale_factor));
+    if let Some((grid, columns)) = &layout {
+        draw_layout_grid(cr, grid, columns, config, scale, img_width, img_height);
+    }
 
-        // Apply transformations to the drawing context
-        cr.save().unwrap();
-        cr.translate(offset_x, offset_y);
-        cr.scale(scale, scale);
+    guides::draw(
+        cr,
+        &data.guides,
+        data.measurement
+            .map(|measurement| (measurement.x, measurement.y)),
+        &data.units,
+        config,
+        scale,
+        img_width,
+        img_height,
+    );
 
-        // Draw the background image
-        cr.set_source_pixbuf(&pixbuf_clone, 0.0, 0.0);
-        let _ = cr.paint();
-
-        let data = crosshair_data_clone.borrow();
-
-        // Draw the crosshair and coordinates if initialized
-        if data.initialized {
-            let current_tool = *active_tool_clone.borrow();
-            draw_crosshair(cr, &data, scale, current_tool);
-            draw_tooltip(
-                cr,
-                &data,
-                scale,
-                &scale_and_offset_clone,
-                img_width,
-                img_height,
-                current_tool,
-            );
-        }
+    if let Some(element) = &data.accessible {
+        draw_accessible(cr, element, data.units.context.scale_factor, config, scale);
+    }
 
-        // Restore the drawing context
-        cr.restore().unwrap();
-    });
+    if let Some(redline) = &data.redline {
+        redline.draw(cr, &data.units, &config.redline, scale);
+    }
 
-    drawing_area
-}
+    // Annotations stay under the measurements, so their tooltips remain readable
+    data.annotations.draw(cr, &config.annotations, scale);
+
+    // Draw the pinned measurements with the tools that took them,
+    // then the active tool once the cursor has been seen
+    // The pin selected in the history panel stands out
+    let selected = data.history.selected();
+    let pinned = data
+        .history
+        .pins()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, pin)| {
+            let tool = tools.tools().get(pin.tool)?.as_ref();
+            Some((tool, &pin.measurement, Some(index) == selected))
+        });
+    let current = data
+        .measurement
+        .as_ref()
+        .map(|measurement| (tools.active(), measurement, false));
+
+    for (tool, measurement, highlighted) in pinned.chain(current) {
+        if highlighted {
+            config.history.highlight.apply(cr);
+            cr.set_line_width(2.0 * config.crosshair.line_width / scale);
+        } else {
+            config.crosshair.color.apply(cr);
+            cr.set_line_width(config.crosshair.line_width / scale);
+        }
+        tool.draw(cr, measurement, scale);
 
-/// Sets up keyboard and mouse event handlers
-fn setup_event_handlers(
-    window: &ApplicationWindow,
-    drawing_area: &DrawingArea,
-    crosshair_data: Rc<RefCell<CrosshairData>>,
-    rgb_image: Rc<image::RgbImage>,
-    screenshot_path: PathBuf,
-    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
-    command_center: &Box,
-    active_tool: Rc<RefCell<i32>>,
-) {
-    // Set up keyboard event handling
-    let window_clone_for_cleanup = window.clone();
-    let screenshot_path_clone = screenshot_path.clone();
-    let key_controller = EventControllerKey::new();
-    key_controller.connect_key_pressed(move |_, key, _, _| match key {
-        Key::Escape => {
-            // Clean up the temporary screenshot file before closing
-            if let Err(err) = screenshot::cleanup_screenshot(&screenshot_path_clone) {
-                eprintln!("Error cleaning up screenshot: {:?}", err);
+        // The snap report is only given for the measurement under the cursor
+        let mut text = measurement_text(tool, measurement, &data.units);
+        if let Some((grid, columns)) = &layout
+            && Some(measurement) == data.measurement.as_ref()
+        {
+            for line in snap_report(measurement, grid, columns, &data.units, img_width) {
+                text.push('\n');
+                text.push_str(&line);
             }
-            window_clone_for_cleanup.close();
-            Propagation::Stop
         }
-        _ => Propagation::Proceed,
-    });
-    window.add_controller(key_controller);
-
-    let command_center_clone_press = command_center.clone();
-    let key_controller_press = EventControllerKey::new();
-    key_controller_press.connect_key_pressed(move |_, key, _, _| {
-        if key == Key::Control_L || key == Key::Control_R {
-            command_center_clone_press.set_visible(true);
-            return Propagation::Stop;
+        if let Some(element) = &data.accessible
+            && Some(measurement) == data.measurement.as_ref()
+        {
+            text.push('\n');
+            text.push_str(&accessible_text(element, &data.units));
         }
-        Propagation::Proceed
-    });
-    window.add_controller(key_controller_press);
-
-    let command_center_clone_release = command_center.clone();
-    let key_controller_release = EventControllerKey::new();
-    key_controller_release.connect_key_released(move |_, key, _, _| {
-        if key == Key::Control_L || key == Key::Control_R {
-            command_center_clone_release.set_visible(false);
+        if let Some(compare) = &data.compare
+            && compare.is_shown()
+            && Some(measurement) == data.measurement.as_ref()
+        {
+            text.push('\n');
+            text.push_str(&compare.report(measurement.x, measurement.y, &data.units));
         }
-    });
-    window.add_controller(key_controller_release);
 
-    // Set up mouse motion event handling
-    setup_mouse_events(
-        window,
-        drawing_area,
-        crosshair_data,
-        rgb_image,
-        scale_and_offset,
-        active_tool,
-    );
+        draw_tooltip(
+            cr,
+            measurement,
+            &text,
+            &config.tooltip,
+            scale,
+            offset,
+            img_width,
+            img_height,
+        );
+    }
 }
 
-/// Sets up mouse event handling
-fn setup_mouse_events(
-    window: &ApplicationWindow,
-    drawing_area: &DrawingArea,
-    crosshair_data: Rc<RefCell<CrosshairData>>,
-    rgb_image: Rc<image::RgbImage>,
-    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
-    active_tool: Rc<RefCell<i32>>,
-) {
-    let drawing_area_clone = drawing_area.clone();
-    let crosshair_data_clone = crosshair_data.clone();
-    let rgb_image_clone = rgb_image.clone();
-    let active_tool_clone = active_tool.clone();
+/// Grid and columns from the configuration, converted from dp to image pixels
+fn layout_grid(config: &Config, scale_factor: f64) -> (grid::Grid, Columns) {
+    let grid = grid::Grid {
+        spacing: config.grid.spacing * scale_factor,
+        offset: (
+            config.grid.offset_x * scale_factor,
+            config.grid.offset_y * scale_factor,
+        ),
+    };
+    let columns = Columns {
+        count: config.columns.count,
+        gutter: config.columns.gutter * scale_factor,
+        margin: config.columns.margin * scale_factor,
+    };
+    (grid, columns)
+}
 
-    let update_crosshair = move |x: f64, y: f64| {
-        let (scale, offset_x, offset_y) = *scale_and_offset.borrow();
+/// Draws the columns as shaded bands and the grid lines on top of them
+fn draw_layout_grid(
+    cr: &cairo::Context,
+    grid: &grid::Grid,
+    columns: &Columns,
+    config: &Config,
+    scale: f64,
+    img_width: u32,
+    img_height: u32,
+) {
+    let (width, height) = (f64::from(img_width), f64::from(img_height));
 
-        let mut mouse_x = ((x - offset_x) / scale) as u32;
-        let mut mouse_y = ((y - offset_y) / scale) as u32;
+    config.columns.color.apply(cr);
+    for (start, end) in columns.spans(width) {
+        cr.rectangle(start, 0.0, end - start, height);
+    }
+    let _ = cr.fill();
 
-        let (img_width, img_height) = rgb_image_clone.dimensions();
+    // Lines closer than a few screen pixels would only tint the screenshot
+    if grid.spacing * scale < 4.0 {
+        return;
+    }
 
-        // Clamp coordinates to be within image bounds
-        if mouse_x >= img_width {
-            mouse_x = img_width - 1
-        }
-        if mouse_y >= img_height {
-            mouse_y = img_height - 1;
-        }
+    config.grid.color.apply(cr);
+    cr.set_line_width(1.0 / scale);
+    for x in grid.lines(width, false) {
+        cr.move_to(x, 0.0);
+        cr.line_to(x, height);
+    }
+    for y in grid.lines(height, true) {
+        cr.move_to(0.0, y);
+        cr.line_to(width, y);
+    }
+    let _ = cr.stroke();
+}
 
-        if screenshot::validate_coordinates(&rgb_image_clone, mouse_x, mouse_y).is_err() {
-            return;
-        }
+/// Positions of the measured edges, as the boundaries between pixels
+/// The shown limits sit on the first pixel inside the element on the left and top,
+/// and one pixel past the edge on the right and bottom
+fn measured_edges(measurement: &Measurement) -> Vec<(grid::Edge, f64)> {
+    let limits = &measurement.limits;
+    let mut edges = Vec::new();
 
-        let current_tool = *active_tool_clone.borrow();
-        let magnitude_threshold = crosshair_data_clone.borrow().magnitude_threshold;
-        let (top, bottom, left, right) = screenshot::calculate_line_limits(
-            &rgb_image_clone,
+    if measurement.axes.horizontal() {
+        edges.push((grid::Edge::Left, f64::from(limits.left)));
+        edges.push((grid::Edge::Right, f64::from(limits.right) - 1.0));
+    }
+    if measurement.axes.vertical() {
+        edges.push((grid::Edge::Top, f64::from(limits.top)));
+        edges.push((grid::Edge::Bottom, f64::from(limits.bottom) - 1.0));
+    }
+
+    edges
+}
+
+/// Lines telling whether each measured edge falls on the grid and on a column edge,
+/// and how far off it is otherwise
+fn snap_report(
+    measurement: &Measurement,
+    grid: &grid::Grid,
+    columns: &Columns,
+    units: &Units,
+    img_width: u32,
+) -> Vec<String> {
+    // Only axis-aligned measurements have edges to check
+    if measurement.shape != Shape::Extent {
+        return Vec::new();
+    }
+
+    let edges = measured_edges(measurement);
+    let describe = |snaps: Vec<EdgeSnap>| {
+        snaps
+            .iter()
+            .map(|snap| {
+                if snap.on_line() {
+                    return format!("{} ✓", snap.edge.label());
+                }
+                let axis = if snap.edge.is_horizontal() {
+                    Axis::Vertical
+                } else {
+                    Axis::Horizontal
+                };
+                let reference = match axis {
+                    Axis::Horizontal => units.context.screen_size.0,
+                    Axis::Vertical => units.context.screen_size.1,
+                };
+                format!(
+                    "{} {}{} {}",
+                    snap.edge.label(),
+                    if snap.offset > 0.0 { "+" } else { "−" },
+                    units.format(snap.offset.abs().round() as u32, axis, reference),
+                    units.unit.label()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" · ")
+    };
+
+    let mut report = Vec::new();
+    let grid_snaps = grid::snap_to_grid(grid, &edges);
+    if !grid_snaps.is_empty() {
+        report.push(format!("Grid: {}", describe(grid_snaps)));
+    }
+    let column_snaps = grid::snap_to_columns(columns, f64::from(img_width), &edges);
+    if !column_snaps.is_empty() {
+        report.push(format!("Columns: {}", describe(column_snaps)));
+    }
+
+    report
+}
+
+/// Outlines the bounds of an accessible element, given in logical pixels
+fn draw_accessible(
+    cr: &cairo::Context,
+    element: &Accessible,
+    scale_factor: f64,
+    config: &Config,
+    scale: f64,
+) {
+    config.accessibility.color.apply(cr);
+    cr.set_line_width(1.0 / scale);
+    cr.rectangle(
+        f64::from(element.x) * scale_factor,
+        f64::from(element.y) * scale_factor,
+        f64::from(element.width) * scale_factor,
+        f64::from(element.height) * scale_factor,
+    );
+    let _ = cr.stroke();
+}
+
+/// Role, name and size of an accessible element, e.g. `push button "OK" 80 × 32 px`
+fn accessible_text(element: &Accessible, units: &Units) -> String {
+    let scale_factor = units.context.scale_factor;
+    let pixels = |size: i32| (f64::from(size.max(0)) * scale_factor).round() as u32;
+    let (screen_width, screen_height) = units.context.screen_size;
+
+    let mut text = element.role.clone();
+    if !element.name.is_empty() {
+        text.push_str(&format!(" \"{}\"", element.name));
+    }
+    format!(
+        "{} {} × {} {}",
+        text,
+        units.format(pixels(element.width), Axis::Horizontal, screen_width),
+        units.format(pixels(element.height), Axis::Vertical, screen_height),
+        units.unit.label()
+    )
+}
+
+/// Text describing a measurement, followed by the unit it is shown in
+fn measurement_text(tool: &dyn Tool, measurement: &Measurement, units: &Units) -> String {
+    let text = tool.tooltip_text(measurement, units);
+    if tool.shows_unit() {
+        format!("{} {}", text, units.unit.label())
+    } else {
+        text
+    }
+}
+
+/// Exports the scene at the native resolution of the screenshot as PNG and SVG
+fn export_scene(pixbuf: &Pixbuf, data: &CrosshairData, tools: &ToolRegistry, config: &Config) {
+    let result = export::export_annotated(pixbuf.width() as u32, pixbuf.height() as u32, |cr| {
+        draw_scene(cr, pixbuf, data, tools, config, 1.0, (0.0, 0.0));
+    });
+
+    match result {
+        Ok(paths) => {
+            for path in paths {
+                println!("Exported {}", path.display());
+            }
+        }
+        Err(err) => eprintln!("Error exporting screenshot: {err}"),
+    }
+}
+
+/// Saves the redline as a JSON spec next to the exported screenshots
+fn export_spec(redline: &Redline, units: &Units) {
+    match export::export_spec(&redline.spec(units)) {
+        Ok(path) => println!("Exported {}", path.display()),
+        Err(err) => eprintln!("Error exporting spec: {err}"),
+    }
+}
+
+/// Lists the pins in the history panel, e.g. `2. Cross · 120 × 40 px · 14:02:31`
+fn update_history_panel(panel: &HistoryPanel, data: &CrosshairData, tools: &ToolRegistry) {
+    let rows: Vec<String> = data
+        .history
+        .pins()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, pin)| {
+            let tool = tools.tools().get(pin.tool)?.as_ref();
+            let time = pin.taken.format("%H:%M:%S").ok()?;
+            Some(format!(
+                "{}. {} · {} · {}",
+                index + 1,
+                tool.name(),
+                measurement_text(tool, &pin.measurement, &data.units),
+                time
+            ))
+        })
+        .collect();
+    panel.update(&rows, data.history.selected());
+}
+
+/// Saves the capture with its pins and settings, over the session it came from if any
+fn save_session(capture: &Capture, data: &mut CrosshairData, tools: &ToolRegistry) {
+    let mut session = Session::new(
+        tools.active().id(),
+        data.magnitude_threshold,
+        data.units.unit.label(),
+        data.show_grid,
+    );
+    session.guides = data.guides.clone();
+    session.annotations = data.annotations.items().to_vec();
+    session.redline = data.redline.as_ref().map(Redline::region);
+    session.pins = data
+        .history
+        .pins()
+        .iter()
+        .filter_map(|pin| {
+            Some(SavedPin {
+                tool: tools.tools().get(pin.tool)?.id().to_string(),
+                measurement: pin.measurement,
+                time: pin.taken.format_iso8601().ok()?.to_string(),
+            })
+        })
+        .collect();
+
+    let path = data
+        .session
+        .clone()
+        .unwrap_or_else(|| session::new_path(&export::export_dir()));
+    match session::save(&path, &capture.rgb_image, &session) {
+        Ok(()) => {
+            println!("Saved {}", path.display());
+            data.session = Some(path);
+        }
+        Err(err) => eprintln!("Error saving session {err}"),
+    }
+}
+
+/// Puts back the tool, settings, guides and pins of a session opened from the given path
+/// Pins of tools this version doesn't know are left out
+fn restore_session(
+    data: &mut CrosshairData,
+    tools: &mut ToolRegistry,
+    session: Session,
+    path: &Path,
+) {
+    match tools.index_of(&session.tool) {
+        Some(index) => tools.set_active(index),
+        None => eprintln!("Unknown tool \"{}\" in the session", session.tool),
+    }
+    match session.unit.parse() {
+        Ok(unit) => data.units.unit = unit,
+        Err(err) => eprintln!("Ignoring the unit of the session: {err}"),
+    }
+    data.magnitude_threshold = session.magnitude_threshold;
+    data.show_grid = session.show_grid;
+    data.guides = session.guides;
+
+    let pins = session
+        .pins
+        .into_iter()
+        .filter_map(|pin| {
+            let Some(tool) = tools.index_of(&pin.tool) else {
+                eprintln!("Unknown tool \"{}\" in the session", pin.tool);
+                return None;
+            };
+            let taken = glib::DateTime::from_iso8601(&pin.time, None)
+                .or_else(|_| glib::DateTime::now_local())
+                .ok()?;
+            Some(Pin {
+                tool,
+                measurement: pin.measurement,
+                taken,
+            })
+        })
+        .collect();
+    data.history = History::restore(pins);
+    data.annotations = Annotations::restore(session.annotations);
+    // The elements are found once the capture is known
+    data.redline = session.redline.map(Redline::new);
+    data.session = Some(path.to_path_buf());
+}
+
+/// Saves the pinned measurements as CSV and JSON next to the exported screenshots
+fn export_history(data: &CrosshairData, tools: &ToolRegistry) {
+    let records: Vec<Record> = data
+        .history
+        .pins()
+        .iter()
+        .filter_map(|pin| {
+            let tool = tools.tools().get(pin.tool)?.as_ref();
+            let text = measurement_text(tool, &pin.measurement, &data.units);
+            Some(Record::new(pin, tool.id(), text))
+        })
+        .collect();
+
+    match export::export_measurements(&history::to_csv(&records), &history::to_json(&records)) {
+        Ok(paths) => {
+            for path in paths {
+                println!("Exported {}", path.display());
+            }
+        }
+        Err(err) => eprintln!("Error exporting measurements: {err}"),
+    }
+}
+
+/// Sets up the side panel listing the pinned measurements
+/// Selecting one highlights it on the screenshot
+fn create_history_panel(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    tools: &ToolRegistry,
+    config: &Config,
+) -> Rc<HistoryPanel> {
+    let drawing_area = drawing_area.clone();
+    let crosshair_data_clone = crosshair_data.clone();
+    let panel = HistoryPanel::new(config.history.visible, move |index| {
+        crosshair_data_clone.borrow_mut().history.select(index);
+        drawing_area.queue_draw();
+    });
+
+    update_history_panel(&panel, &crosshair_data.borrow(), tools);
+    panel
+}
+
+/// Sets up capturing the screen again, keeping the tool, pins, guides and threshold
+/// Live mode starts right away when enabled in the configuration
+fn create_live_capture(
+    window: &ApplicationWindow,
+    indicator: &Label,
+    drawing_area: &DrawingArea,
+    capture: Rc<RefCell<Capture>>,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    config: &Config,
+) -> Rc<LiveCapture> {
+    let drawing_area = drawing_area.clone();
+    let interval = Duration::from_secs_f64(config.live.interval);
+    let live = LiveCapture::new(window, indicator, interval, move |rgb_image| {
+        // Measurements and guides are in pixels of the first capture
+        if rgb_image.dimensions() != capture.borrow().rgb_image.dimensions() {
+            eprintln!("The screen size changed, keeping the previous capture");
+            return;
+        }
+
+        *capture.borrow_mut() = capture_from_image(rgb_image);
+        let mut data = crosshair_data.borrow_mut();
+        data.image_generation += 1;
+        refresh_compare(&mut data, &capture.borrow().rgb_image);
+        tools
+            .borrow()
+            .screen_captured(&capture.borrow().rgb_image, data.image_generation);
+        refresh_measurement(&mut data, &tools.borrow(), &capture.borrow());
+        drawing_area.queue_draw();
+    });
+
+    if config.live.enabled {
+        live.toggle();
+    }
+    live
+}
+
+/// Sets up measuring again once a tool is done with work it started in the background
+fn setup_tool_results(
+    drawing_area: &DrawingArea,
+    capture: Rc<RefCell<Capture>>,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    tools: &Rc<RefCell<ToolRegistry>>,
+) {
+    let drawing_area = drawing_area.clone();
+    // The tools own the callback, a strong reference back to them would never be freed
+    let weak_tools = Rc::downgrade(tools);
+    let on_ready: Rc<dyn Fn()> = Rc::new(move || {
+        let Some(tools) = weak_tools.upgrade() else {
+            return;
+        };
+        refresh_measurement(
+            &mut crosshair_data.borrow_mut(),
+            &tools.borrow(),
+            &capture.borrow(),
+        );
+        drawing_area.queue_draw();
+    });
+
+    for tool in tools.borrow().tools() {
+        tool.set_on_ready(on_ready.clone());
+    }
+}
+
+/// Sets up looking up the element under the cursor in the accessibility tree
+fn create_inspector(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    config: &Config,
+) -> Rc<Inspector> {
+    let drawing_area = drawing_area.clone();
+    let inspector = Inspector::new(move |accessible| {
+        crosshair_data.borrow_mut().accessible = accessible;
+        drawing_area.queue_draw();
+    });
+
+    if config.accessibility.enabled {
+        inspector.toggle();
+    }
+    inspector
+}
+
+/// Creates the drawing area with the drawing function
+fn create_drawing_area(
+    capture: Rc<RefCell<Capture>>,
+    img_width: u32,
+    img_height: u32,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    config: Rc<Config>,
+) -> DrawingArea {
+    let drawing_area = DrawingArea::new();
+    drawing_area.set_hexpand(true);
+    drawing_area.set_vexpand(true);
+
+    // Set up the drawing function for the drawing area
+    let crosshair_data_clone = crosshair_data.clone();
+    let scale_and_offset_clone = scale_and_offset.clone();
+    let tools_clone = tools.clone();
+
+    drawing_area.set_draw_func(move |_, cr, width, height| {
+        // Calculate scaling to fit image while maintaining aspect ratio
+        let scale_x = width as f64 / img_width as f64;
+        let scale_y = height as f64 / img_height as f64;
+        let scale = scale_x.min(scale_y);
+
+        // Calculate offsets to center the image
+        let offset_x = (width as f64 - img_width as f64 * scale) / 2.0;
+        let offset_y = (height as f64 - img_height as f64 * scale) / 2.0;
+
+        // Store scale and offset for coordinate conversion
+        *scale_and_offset_clone.borrow_mut() = (scale, offset_x, offset_y);
+
+        // Apply transformations to the drawing context
+        cr.save().unwrap();
+        cr.translate(offset_x, offset_y);
+        cr.scale(scale, scale);
+
+        draw_scene(
+            cr,
+            &capture.borrow().pixbuf,
+            &crosshair_data_clone.borrow(),
+            &tools_clone.borrow(),
+            &config,
+            scale,
+            (offset_x, offset_y),
+        );
+
+        // Restore the drawing context
+        cr.restore().unwrap();
+    });
+
+    drawing_area
+}
+
+/// Sets up keyboard and mouse event handlers
+#[allow(clippy::too_many_arguments)]
+fn setup_event_handlers(
+    window: &ApplicationWindow,
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    capture: Rc<RefCell<Capture>>,
+    live: Option<Rc<LiveCapture>>,
+    inspector: Rc<Inspector>,
+    history_panel: Rc<HistoryPanel>,
+    text_editor: Rc<TextEditor>,
+    screenshot_path: Option<PathBuf>,
+    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
+    command_center: &Box,
+    help_overlay: &Box,
+    tool_buttons: Vec<ToggleButton>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    keymap: Rc<Keymap>,
+    config: Rc<Config>,
+) {
+    // Set up keyboard event handling, every key goes through the keymap
+    let window_clone = window.clone();
+    let drawing_area_clone = drawing_area.clone();
+    let crosshair_data_clone = crosshair_data.clone();
+    let capture_clone = capture.clone();
+    let inspector_clone = inspector.clone();
+    let command_center_clone = command_center.clone();
+    let help_overlay_clone = help_overlay.clone();
+    let tools_clone = tools.clone();
+    let keymap_clone = keymap.clone();
+    let text_editor_clone = text_editor.clone();
+    let config_clone = config.clone();
+    let key_controller = EventControllerKey::new();
+    key_controller.connect_key_pressed(move |_, key, _, state| {
+        // Keys typed into a label are not shortcuts
+        if text_editor_clone.is_editing() {
+            return Propagation::Proceed;
+        }
+        let Some(action) = keymap_clone.lookup(key, state) else {
+            return Propagation::Proceed;
+        };
+
+        match action {
+            Action::SwitchTool(index) => {
+                // Go through the button so the command center stays in sync
+                if let Some(button) = tool_buttons.get(index)
+                    && button.is_sensitive()
+                {
+                    button.set_active(true);
+                }
+                refresh_measurement(
+                    &mut crosshair_data_clone.borrow_mut(),
+                    &tools_clone.borrow(),
+                    &capture_clone.borrow(),
+                );
+            }
+            Action::ToggleCommandCenter => {
+                // Modifier keys show the command center only while held
+                let visible = keybindings::is_modifier(key) || !command_center_clone.is_visible();
+                command_center_clone.set_visible(visible);
+            }
+            Action::Copy => {
+                let data = crosshair_data_clone.borrow();
+                if let Some(measurement) = &data.measurement {
+                    let text =
+                        measurement_text(tools_clone.borrow().active(), measurement, &data.units);
+                    window_clone.clipboard().set_text(&text);
+                }
+            }
+            Action::Pin => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                if let Some(measurement) = data.measurement {
+                    let tool = tools_clone.borrow().active_index();
+                    data.history.pin(tool, measurement);
+                    update_history_panel(&history_panel, &data, &tools_clone.borrow());
+                }
+            }
+            Action::Undo | Action::Redo => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                let changed = if action == Action::Undo {
+                    data.history.undo()
+                } else {
+                    data.history.redo()
+                };
+                if changed {
+                    update_history_panel(&history_panel, &data, &tools_clone.borrow());
+                }
+            }
+            Action::ToggleHistory => {
+                history_panel.toggle();
+            }
+            Action::ExportHistory => {
+                export_history(&crosshair_data_clone.borrow(), &tools_clone.borrow());
+            }
+            Action::CycleUnits => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                data.units.unit = data.units.unit.next();
+                update_history_panel(&history_panel, &data, &tools_clone.borrow());
+            }
+            Action::SelectParent | Action::SelectChild => {
+                let outward = action == Action::SelectParent;
+                if tools_clone.borrow_mut().active_mut().select_parent(outward) {
+                    refresh_measurement(
+                        &mut crosshair_data_clone.borrow_mut(),
+                        &tools_clone.borrow(),
+                        &capture_clone.borrow(),
+                    );
+                }
+            }
+            Action::SaveSession => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                save_session(&capture_clone.borrow(), &mut data, &tools_clone.borrow());
+            }
+            Action::EditAnnotation => {
+                let data = crosshair_data_clone.borrow();
+                let selected = data.annotations.selected();
+                if let Some(index) = selected
+                    && let Some(Annotation::Text { text, .. }) = data.annotations.get(index)
+                {
+                    text_editor_clone.edit(index, text);
+                }
+            }
+            Action::DeleteAnnotation => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                if let Some(index) = data.annotations.selected() {
+                    data.annotations.remove(index);
+                }
+            }
+            Action::Export => {
+                // The selection handles are not part of the export
+                crosshair_data_clone.borrow_mut().annotations.select(None);
+                export_scene(
+                    &capture_clone.borrow().pixbuf,
+                    &crosshair_data_clone.borrow(),
+                    &tools_clone.borrow(),
+                    &config,
+                );
+                if let Some(redline) = &crosshair_data_clone.borrow().redline {
+                    export_spec(redline, &crosshair_data_clone.borrow().units);
+                }
+            }
+            Action::IncreaseThreshold | Action::DecreaseThreshold => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                data.magnitude_threshold = step_threshold(
+                    data.magnitude_threshold,
+                    action == Action::IncreaseThreshold,
+                );
+                refresh_compare(&mut data, &capture_clone.borrow().rgb_image);
+                refresh_measurement(&mut data, &tools_clone.borrow(), &capture_clone.borrow());
+            }
+            Action::ToggleGrid => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                data.show_grid = !data.show_grid;
+            }
+            Action::ToggleLive | Action::Recapture => match &live {
+                Some(live) if action == Action::ToggleLive => live.toggle(),
+                Some(live) => live.capture(),
+                None => eprintln!("A session is never captured again"),
+            },
+            Action::ToggleAccessibility => {
+                inspector_clone.toggle();
+            }
+            Action::CycleCompare => {
+                let mut data = crosshair_data_clone.borrow_mut();
+                let Some(compare) = &mut data.compare else {
+                    eprintln!("No reference image to compare with");
+                    return Propagation::Stop;
+                };
+                let was_measured = compare.is_measured();
+                compare.cycle();
+                // Hiding the reference goes back to measuring the capture
+                if was_measured {
+                    data.image_generation += 1;
+                }
+                refresh_compare(&mut data, &capture_clone.borrow().rgb_image);
+                refresh_measurement(&mut data, &tools_clone.borrow(), &capture_clone.borrow());
+            }
+            Action::KeepReference => {
+                let capture = capture_clone.borrow();
+                let (reference, pixbuf) = (capture.rgb_image.clone(), capture.pixbuf.clone());
+                let mut data = crosshair_data_clone.borrow_mut();
+                match &mut data.compare {
+                    Some(compare) => compare.replace(reference, pixbuf),
+                    None => {
+                        data.compare = Some(Compare::new(reference, pixbuf, &config));
+                    }
+                }
+                refresh_compare(&mut data, &capture.rgb_image);
+                refresh_measurement(&mut data, &tools_clone.borrow(), &capture);
+            }
+            Action::IncreaseReference | Action::DecreaseReference => {
+                if let Some(compare) = &mut crosshair_data_clone.borrow_mut().compare {
+                    compare.step(action == Action::IncreaseReference);
+                }
+            }
+            Action::NudgeReference(dx, dy) => {
+                let distance = if state.contains(ModifierType::SHIFT_MASK) {
+                    compare::LARGE_NUDGE
+                } else {
+                    1
+                };
+                let mut data = crosshair_data_clone.borrow_mut();
+                if let Some(compare) = &mut data.compare {
+                    compare.nudge(dx * distance, dy * distance);
+                    refresh_compare(&mut data, &capture_clone.borrow().rgb_image);
+                    refresh_measurement(&mut data, &tools_clone.borrow(), &capture_clone.borrow());
+                }
+            }
+            Action::CycleBlend => {
+                if let Some(compare) = &mut crosshair_data_clone.borrow_mut().compare {
+                    compare.cycle_blend();
+                }
+            }
+            Action::AlignReference => {
+                let capture = capture_clone.borrow();
+                let mut data = crosshair_data_clone.borrow_mut();
+                let threshold = data.magnitude_threshold;
+                if let Some(compare) = &mut data.compare {
+                    if !compare.align(&capture.rgb_image, config.compare.align_distance, threshold)
+                    {
+                        eprintln!("No edges of the reference line up with the capture");
+                    }
+                    refresh_compare(&mut data, &capture.rgb_image);
+                    refresh_measurement(&mut data, &tools_clone.borrow(), &capture);
+                }
+            }
+            Action::MeasureReference => {
+                let capture = capture_clone.borrow();
+                let mut data = crosshair_data_clone.borrow_mut();
+                if let Some(compare) = &mut data.compare {
+                    compare.toggle_measured(&capture.rgb_image);
+                    data.image_generation += 1;
+                    refresh_compare(&mut data, &capture.rgb_image);
+                    refresh_measurement(&mut data, &tools_clone.borrow(), &capture);
+                }
+            }
+            Action::Help => {
+                help_overlay_clone.set_visible(!help_overlay_clone.is_visible());
+            }
+            Action::Quit => {
+                // Clean up the temporary screenshot file before closing
+                if let Some(path) = &screenshot_path
+                    && let Err(err) = screenshot::cleanup_screenshot(path)
+                {
+                    eprintln!("Error cleaning up screenshot: {:?}", err);
+                }
+                window_clone.close();
+            }
+        }
+
+        drawing_area_clone.queue_draw();
+        Propagation::Stop
+    });
+
+    // Hide the command center again when its modifier key is released
+    let command_center_clone = command_center.clone();
+    key_controller.connect_key_released(move |_, key, _, state| {
+        if keybindings::is_modifier(key)
+            && keymap.lookup(key, state) == Some(Action::ToggleCommandCenter)
+        {
+            command_center_clone.set_visible(false);
+        }
+    });
+    window.add_controller(key_controller);
+
+    setup_guide_events(
+        drawing_area,
+        crosshair_data.clone(),
+        capture.clone(),
+        scale_and_offset.clone(),
+    );
+    setup_reference_events(
+        drawing_area,
+        crosshair_data.clone(),
+        capture.clone(),
+        scale_and_offset.clone(),
+        tools.clone(),
+    );
+    setup_annotation_events(
+        drawing_area,
+        crosshair_data.clone(),
+        scale_and_offset.clone(),
+        tools.clone(),
+        text_editor,
+        config_clone.clone(),
+    );
+    setup_region_events(
+        drawing_area,
+        crosshair_data.clone(),
+        capture.clone(),
+        scale_and_offset.clone(),
+        tools.clone(),
+        config_clone.clone(),
+    );
+
+    // Set up mouse motion event handling
+    setup_mouse_events(
+        window,
+        drawing_area,
+        crosshair_data,
+        capture,
+        scale_and_offset,
+        tools,
+        inspector,
+        config_clone,
+    );
+}
+
+/// Scales the threshold one step up or down, with larger steps for larger thresholds
+fn step_threshold(threshold: f32, increase: bool) -> f32 {
+    let scale_factor = (threshold / DEFAULT_THRESHOLD).max(0.5);
+    if increase {
+        (threshold * (1.0 + 0.05 * scale_factor)).min(255.0)
+    } else {
+        (threshold / (1.0 + 0.05 * scale_factor)).max(1.0)
+    }
+}
+
+/// Measures again at the current position and finds the elements of the redline again,
+/// after the tool, threshold or measured image changed
+fn refresh_measurement(data: &mut CrosshairData, tools: &ToolRegistry, capture: &Capture) {
+    tools.image_changed(data.image_generation);
+
+    if let Some(mut redline) = data.redline.take() {
+        redline.find(data.measured_image(capture), data.magnitude_threshold);
+        data.redline = Some(redline);
+    }
+
+    if let Some(current) = data.measurement {
+        // Update the limits with the new calculation
+        data.measurement = tools
+            .active()
+            .compute(
+                data.measured_image(capture),
+                current.x,
+                current.y,
+                data.magnitude_threshold,
+            )
+            .map(|mut measurement| {
+                measurement.limits = measurement.limits.offset(1);
+                measurement
+            });
+    }
+}
+
+/// Compares the capture with the reference again, after either or the threshold changed
+/// While the reference is measured, the image measured changes with it and counts as a new one
+fn refresh_compare(data: &mut CrosshairData, img: &image::RgbImage) {
+    let threshold = data.magnitude_threshold;
+    if let Some(compare) = &mut data.compare {
+        compare.update(img, threshold);
+        if compare.is_measured() {
+            data.image_generation += 1;
+        }
+    }
+}
+
+/// Sets up mouse event handling
+#[allow(clippy::too_many_arguments)]
+fn setup_mouse_events(
+    window: &ApplicationWindow,
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    capture: Rc<RefCell<Capture>>,
+    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    inspector: Rc<Inspector>,
+    config: Rc<Config>,
+) {
+    let drawing_area_clone = drawing_area.clone();
+    let crosshair_data_clone = crosshair_data.clone();
+    let capture_clone = capture.clone();
+    let tools_clone = tools.clone();
+
+    let scale_and_offset_clone = scale_and_offset.clone();
+    let update_crosshair = move |x: f64, y: f64| {
+        let capture = capture_clone.borrow();
+        let rgb_image = &capture.rgb_image;
+        let (mouse_x, mouse_y) =
+            image_coordinates(x, y, *scale_and_offset_clone.borrow(), rgb_image);
+
+        let mut data = crosshair_data_clone.borrow_mut();
+        let Some(mut measurement) = tools_clone.borrow().active().compute(
+            data.measured_image(&capture),
             mouse_x,
             mouse_y,
-            current_tool,
-            magnitude_threshold,
-        );
+            data.magnitude_threshold,
+        ) else {
+            return;
+        };
 
         // Update crosshair data with new position and limits
-        {
-            let mut data = crosshair_data_clone.borrow_mut();
-            data.x = mouse_x;
-            data.y = mouse_y;
-            data.top_limit = top + 1;
-            data.bottom_limit = bottom + 1;
-            data.left_limit = left + 1;
-            data.right_limit = right + 1;
-            data.initialized = true;
+        measurement.limits = measurement.limits.offset(1);
+        data.measurement = Some(measurement);
+
+        // The accessibility tree is in logical pixels
+        if inspector.is_enabled() {
+            let scale_factor = data.units.context.scale_factor;
+            inspector.look_up(
+                (f64::from(mouse_x) / scale_factor) as i32,
+                (f64::from(mouse_y) / scale_factor) as i32,
+            );
         }
 
         // Request redraw to show updated crosshair
@@ -736,44 +1837,30 @@ fn setup_mouse_events(
     // Set up scroll event handling for magnitude adjustment
     let drawing_area_scroll = drawing_area.clone();
     let crosshair_data_scroll = crosshair_data.clone();
-    let rgb_image_clone_for_scroll = rgb_image.clone();
-    let active_tool_clone_for_scroll = active_tool.clone();
+    let capture_scroll = capture.clone();
+    let tools_clone_for_scroll = tools.clone();
     let scroll_controller =
         gtk4::EventControllerScroll::new(gtk4::EventControllerScrollFlags::VERTICAL);
-    scroll_controller.connect_scroll(move |_, _, y_scroll| {
-        // Adjust magnitude threshold based on scroll direction
+    scroll_controller.connect_scroll(move |controller, _, y_scroll| {
+        let state = controller.current_event_state();
+        let used_by_tool = tools_clone_for_scroll
+            .borrow_mut()
+            .active_mut()
+            .scroll(y_scroll, state);
+
+        // Adjust magnitude threshold based on scroll direction, down increases it
         let mut data = crosshair_data_scroll.borrow_mut();
-        let mut new_magnitude = data.magnitude_threshold;
-
-        // Scrolling down
-        if y_scroll > 0.0 {
-            let scale_factor = (new_magnitude / 20.0).max(0.5);
-            new_magnitude = (new_magnitude * (1.0 + 0.05 * scale_factor)).min(255.0);
-        // Scrolling up
-        } else if y_scroll < 0.0 {
-            let scale_factor = (new_magnitude / 20.0).max(0.5);
-            new_magnitude = (new_magnitude / (1.0 + 0.05 * scale_factor)).max(1.0);
+        if !used_by_tool && y_scroll != 0.0 {
+            data.magnitude_threshold = step_threshold(data.magnitude_threshold, y_scroll > 0.0);
+            refresh_compare(&mut data, &capture_scroll.borrow().rgb_image);
         }
 
-        data.magnitude_threshold = new_magnitude;
-
         // Recalculate the limits with the new magnitude threshold using the current position
-        if data.initialized {
-            let current_tool = *active_tool_clone_for_scroll.borrow();
-            let (top, bottom, left, right) = screenshot::calculate_line_limits(
-                &rgb_image_clone_for_scroll,
-                data.x,
-                data.y,
-                current_tool,
-                new_magnitude,
-            );
-
-            // Update the limits with the new calculation
-            data.top_limit = top + 1;
-            data.bottom_limit = bottom + 1;
-            data.left_limit = left + 1;
-            data.right_limit = right + 1;
-        }
+        refresh_measurement(
+            &mut data,
+            &tools_clone_for_scroll.borrow(),
+            &capture_scroll.borrow(),
+        );
 
         // Update the crosshair to reflect the new magnitude
         drawing_area_scroll.queue_draw();
@@ -781,14 +1868,586 @@ fn setup_mouse_events(
     });
 
     drawing_area.add_controller(scroll_controller);
+
+    // Clicks let tools such as the angle place their points
+    let drawing_area_click = drawing_area.clone();
+    let window_click = window.clone();
+    let click_gesture = GestureClick::new();
+    click_gesture.set_button(gtk4::gdk::BUTTON_PRIMARY);
+    click_gesture.connect_pressed(move |gesture, _, x, y| {
+        let capture = capture.borrow();
+        let rgb_image = &capture.rgb_image;
+        let (image_x, image_y) = image_coordinates(x, y, *scale_and_offset.borrow(), rgb_image);
+        let state = gesture.current_event_state();
+
+        // The color picker is done after a single click
+        if crosshair_data.borrow().pick_color {
+            let color = tools::hex(rgb_image.get_pixel(image_x, image_y).0);
+            println!("{color}");
+            window_click.clipboard().set_text(&color);
+            window_click.close();
+            return;
+        }
+
+        // Presses with Alt held drag the reference, presses on guides and
+        // on the edges they are pulled from drag guides, and presses on
+        // annotations or with an annotation tool place or move annotations
+        if drags_reference(&crosshair_data.borrow(), state) {
+            return;
+        }
+        let (position_x, position_y) = image_position(x, y, *scale_and_offset.borrow());
+        let grab_distance = GUIDE_GRAB_DISTANCE / scale_and_offset.borrow().0;
+        if guides::grab_at(
+            &crosshair_data.borrow().guides,
+            position_x,
+            position_y,
+            grab_distance,
+        )
+        .is_some()
+        {
+            return;
+        }
+        if tools.borrow().active().annotation().is_some()
+            || crosshair_data
+                .borrow()
+                .annotations
+                .grab_at(
+                    &Point::new(position_x, position_y),
+                    grab_distance,
+                    &config.annotations,
+                )
+                .is_some()
+        {
+            return;
+        }
+
+        let mut data = crosshair_data.borrow_mut();
+        let threshold = data.magnitude_threshold;
+        let measured = data.measured_image(&capture);
+        let mut tools = tools.borrow_mut();
+        if !tools
+            .active_mut()
+            .click(measured, image_x, image_y, threshold, state)
+        {
+            return;
+        }
+
+        data.measurement = tools
+            .active()
+            .compute(data.measured_image(&capture), image_x, image_y, threshold)
+            .map(|mut measurement| {
+                measurement.limits = measurement.limits.offset(1);
+                measurement
+            });
+        drawing_area_click.queue_draw();
+    });
+
+    drawing_area.add_controller(click_gesture);
+}
+
+/// Sets up dragging guides out of the top and left edges, and moving or removing them
+fn setup_guide_events(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    capture: Rc<RefCell<Capture>>,
+    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
+) {
+    // Index of the guide being dragged
+    let dragged: Rc<Cell<Option<usize>>> = Rc::new(Cell::new(None));
+    let drag_gesture = GestureDrag::new();
+
+    let dragged_begin = dragged.clone();
+    let crosshair_data_begin = crosshair_data.clone();
+    let scale_and_offset_begin = scale_and_offset.clone();
+    drag_gesture.connect_drag_begin(move |gesture, x, y| {
+        let (scale, _, _) = *scale_and_offset_begin.borrow();
+        let (image_x, image_y) = image_position(x, y, *scale_and_offset_begin.borrow());
+
+        let mut data = crosshair_data_begin.borrow_mut();
+        if drags_reference(&data, gesture.current_event_state()) {
+            gesture.set_state(EventSequenceState::Denied);
+            return;
+        }
+        let index =
+            match guides::grab_at(&data.guides, image_x, image_y, GUIDE_GRAB_DISTANCE / scale) {
+                Some(Grab::Existing(index)) => index,
+                Some(Grab::New(orientation)) => {
+                    data.guides.push(Guide::new(orientation, 0));
+                    data.guides.len() - 1
+                }
+                None => {
+                    gesture.set_state(EventSequenceState::Denied);
+                    return;
+                }
+            };
+
+        dragged_begin.set(Some(index));
+        gesture.set_state(EventSequenceState::Claimed);
+    });
+
+    let dragged_update = dragged.clone();
+    let crosshair_data_update = crosshair_data.clone();
+    let scale_and_offset_update = scale_and_offset.clone();
+    let drawing_area_update = drawing_area.clone();
+    drag_gesture.connect_drag_update(move |gesture, offset_x, offset_y| {
+        let (Some(index), Some((start_x, start_y))) = (dragged_update.get(), gesture.start_point())
+        else {
+            return;
+        };
+        let (scale, _, _) = *scale_and_offset_update.borrow();
+        let (image_x, image_y) = image_position(
+            start_x + offset_x,
+            start_y + offset_y,
+            *scale_and_offset_update.borrow(),
+        );
+
+        let capture = capture.borrow();
+        let rgb_image = &capture.rgb_image;
+        let (width, height) = rgb_image.dimensions();
+        let x = image_x.clamp(0.0, f64::from(width)).round() as u32;
+        let y = image_y.clamp(0.0, f64::from(height)).round() as u32;
+
+        let mut data = crosshair_data_update.borrow_mut();
+        let threshold = data.magnitude_threshold;
+        let Some(guide) = data.guides.get_mut(index) else {
+            return;
+        };
+        let (position, across) = match guide.orientation {
+            Orientation::Horizontal => (y, x),
+            Orientation::Vertical => (x, y),
+        };
+        guide.position = rustyruler_core::guides::snap(
+            rgb_image,
+            guide.orientation,
+            position,
+            across,
+            threshold,
+            (GUIDE_SNAP_DISTANCE / scale).ceil() as u32,
+        );
+
+        drawing_area_update.queue_draw();
+    });
+
+    let drawing_area_end = drawing_area.clone();
+    drag_gesture.connect_drag_end(move |_, _, _| {
+        let Some(index) = dragged.take() else {
+            return;
+        };
+        let (scale, _, _) = *scale_and_offset.borrow();
+
+        // Guides dropped back on their edge are removed
+        let mut data = crosshair_data.borrow_mut();
+        if data
+            .guides
+            .get(index)
+            .is_some_and(|guide| f64::from(guide.position) <= GUIDE_GRAB_DISTANCE / scale)
+        {
+            data.guides.remove(index);
+        }
+
+        drawing_area_end.queue_draw();
+    });
+
+    drawing_area.add_controller(drag_gesture);
+}
+
+/// Sets up moving the reference by dragging it with Alt held
+fn setup_reference_events(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    capture: Rc<RefCell<Capture>>,
+    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+) {
+    // Position of the reference when the drag started
+    let start: Rc<Cell<Option<(i32, i32)>>> = Rc::new(Cell::new(None));
+    let drag_gesture = GestureDrag::new();
+
+    let start_begin = start.clone();
+    let crosshair_data_begin = crosshair_data.clone();
+    drag_gesture.connect_drag_begin(move |gesture, _, _| {
+        let data = crosshair_data_begin.borrow();
+        match &data.compare {
+            Some(compare) if drags_reference(&data, gesture.current_event_state()) => {
+                start_begin.set(Some(compare.offset()));
+                gesture.set_state(EventSequenceState::Claimed);
+            }
+            _ => {
+                gesture.set_state(EventSequenceState::Denied);
+            }
+        }
+    });
+
+    let start_update = start.clone();
+    let crosshair_data_update = crosshair_data.clone();
+    let drawing_area_update = drawing_area.clone();
+    drag_gesture.connect_drag_update(move |_, offset_x, offset_y| {
+        let Some((x, y)) = start_update.get() else {
+            return;
+        };
+        let (scale, _, _) = *scale_and_offset.borrow();
+        if let Some(compare) = &mut crosshair_data_update.borrow_mut().compare {
+            compare.set_offset((
+                x + (offset_x / scale).round() as i32,
+                y + (offset_y / scale).round() as i32,
+            ));
+        }
+        drawing_area_update.queue_draw();
+    });
+
+    // The changed regions are only searched again once the reference is dropped
+    let drawing_area_end = drawing_area.clone();
+    drag_gesture.connect_drag_end(move |_, _, _| {
+        if start.take().is_none() {
+            return;
+        }
+        let mut data = crosshair_data.borrow_mut();
+        refresh_compare(&mut data, &capture.borrow().rgb_image);
+        refresh_measurement(&mut data, &tools.borrow(), &capture.borrow());
+        drawing_area_end.queue_draw();
+    });
+
+    drawing_area.add_controller(drag_gesture);
+}
+
+/// Annotation being placed or moved, with how it was when the drag started
+struct AnnotationDrag {
+    index: usize,
+    part: Part,
+    start: Point,
+    original: Annotation,
+    /// Whether the drag placed a new annotation, which is dropped if left empty
+    created: bool,
+}
+
+/// Sets up placing annotations with the annotation tools, and selecting,
+/// moving and reshaping them with any tool
+fn setup_annotation_events(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    text_editor: Rc<TextEditor>,
+    config: Rc<Config>,
+) {
+    let dragged: Rc<RefCell<Option<AnnotationDrag>>> = Rc::new(RefCell::new(None));
+    let drag_gesture = GestureDrag::new();
+
+    let dragged_begin = dragged.clone();
+    let crosshair_data_begin = crosshair_data.clone();
+    let scale_and_offset_begin = scale_and_offset.clone();
+    let drawing_area_begin = drawing_area.clone();
+    drag_gesture.connect_drag_begin(move |gesture, x, y| {
+        let (scale, _, _) = *scale_and_offset_begin.borrow();
+        let (image_x, image_y) = image_position(x, y, *scale_and_offset_begin.borrow());
+        let start = Point::new(image_x, image_y);
+        let grab_distance = GUIDE_GRAB_DISTANCE / scale;
+
+        let mut data = crosshair_data_begin.borrow_mut();
+        if drags_reference(&data, gesture.current_event_state())
+            || guides::grab_at(&data.guides, image_x, image_y, grab_distance).is_some()
+        {
+            gesture.set_state(EventSequenceState::Denied);
+            return;
+        }
+
+        let grabbed = data
+            .annotations
+            .grab_at(&start, grab_distance, &config.annotations);
+        let (index, part, created) = match (grabbed, tools.borrow().active().annotation()) {
+            (Some((index, part)), _) => (index, part, false),
+            (None, Some(kind)) => {
+                let annotation = Annotation::new(kind, start);
+                let part = match annotation {
+                    Annotation::Text { .. } => Part::Whole,
+                    _ => Part::To,
+                };
+                (data.annotations.add(annotation), part, true)
+            }
+            // Pressing anywhere else with a measuring tool drops the selection
+            (None, None) => {
+                if data.annotations.selected().is_some() {
+                    data.annotations.select(None);
+                    drawing_area_begin.queue_draw();
+                }
+                gesture.set_state(EventSequenceState::Denied);
+                return;
+            }
+        };
+
+        data.annotations.select(Some(index));
+        let Some(original) = data.annotations.get(index).cloned() else {
+            return;
+        };
+        *dragged_begin.borrow_mut() = Some(AnnotationDrag {
+            index,
+            part,
+            start,
+            original,
+            created,
+        });
+        gesture.set_state(EventSequenceState::Claimed);
+        drawing_area_begin.queue_draw();
+    });
+
+    let dragged_update = dragged.clone();
+    let crosshair_data_update = crosshair_data.clone();
+    let drawing_area_update = drawing_area.clone();
+    drag_gesture.connect_drag_update(move |gesture, offset_x, offset_y| {
+        let dragged = dragged_update.borrow();
+        let (Some(drag), Some((start_x, start_y))) = (dragged.as_ref(), gesture.start_point())
+        else {
+            return;
+        };
+        let (image_x, image_y) = image_position(
+            start_x + offset_x,
+            start_y + offset_y,
+            *scale_and_offset.borrow(),
+        );
+
+        let mut data = crosshair_data_update.borrow_mut();
+        if let Some(annotation) = data.annotations.get_mut(drag.index) {
+            annotation.drag(
+                &drag.original,
+                drag.part,
+                drag.start,
+                Point::new(image_x, image_y),
+            );
+        }
+        drawing_area_update.queue_draw();
+    });
+
+    // New labels ask for their text once placed, other new annotations are
+    // dropped when the press did not drag them out
+    let drawing_area_end = drawing_area.clone();
+    drag_gesture.connect_drag_end(move |_, _, _| {
+        let Some(drag) = dragged.take() else {
+            return;
+        };
+        let mut data = crosshair_data.borrow_mut();
+        match data.annotations.get(drag.index) {
+            Some(Annotation::Text { text, .. }) if drag.created => {
+                text_editor.edit(drag.index, text);
+            }
+            Some(annotation) if drag.created && annotation.is_empty() => {
+                data.annotations.remove(drag.index);
+            }
+            _ => {}
+        }
+        drawing_area_end.queue_draw();
+    });
+
+    drawing_area.add_controller(drag_gesture);
+}
+
+/// Sets up selecting the region of the redline by dragging with the redline tool
+/// The elements are found once the region is dropped, and a click clears it
+fn setup_region_events(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    capture: Rc<RefCell<Capture>>,
+    scale_and_offset: Rc<RefCell<(f64, f64, f64)>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    config: Rc<Config>,
+) {
+    let selecting = Rc::new(Cell::new(false));
+    let drag_gesture = GestureDrag::new();
+
+    let selecting_begin = selecting.clone();
+    let crosshair_data_begin = crosshair_data.clone();
+    let scale_and_offset_begin = scale_and_offset.clone();
+    drag_gesture.connect_drag_begin(move |gesture, x, y| {
+        let (scale, _, _) = *scale_and_offset_begin.borrow();
+        let (image_x, image_y) = image_position(x, y, *scale_and_offset_begin.borrow());
+        let grab_distance = GUIDE_GRAB_DISTANCE / scale;
+
+        // Guides and annotations are still dragged with the redline tool
+        let data = crosshair_data_begin.borrow();
+        if !tools.borrow().active().selects_region()
+            || drags_reference(&data, gesture.current_event_state())
+            || guides::grab_at(&data.guides, image_x, image_y, grab_distance).is_some()
+            || data
+                .annotations
+                .grab_at(
+                    &Point::new(image_x, image_y),
+                    grab_distance,
+                    &config.annotations,
+                )
+                .is_some()
+        {
+            gesture.set_state(EventSequenceState::Denied);
+            return;
+        }
+
+        selecting_begin.set(true);
+        gesture.set_state(EventSequenceState::Claimed);
+    });
+
+    let selecting_update = selecting.clone();
+    let crosshair_data_update = crosshair_data.clone();
+    let capture_update = capture.clone();
+    let drawing_area_update = drawing_area.clone();
+    drag_gesture.connect_drag_update(move |gesture, offset_x, offset_y| {
+        let (true, Some((start_x, start_y))) = (selecting_update.get(), gesture.start_point())
+        else {
+            return;
+        };
+        let scale_and_offset = *scale_and_offset.borrow();
+        let capture = capture_update.borrow();
+        let start = image_coordinates(start_x, start_y, scale_and_offset, &capture.rgb_image);
+        let end = image_coordinates(
+            start_x + offset_x,
+            start_y + offset_y,
+            scale_and_offset,
+            &capture.rgb_image,
+        );
+
+        // Both corner pixels are part of the region
+        let (x, y) = (start.0.min(end.0), start.1.min(end.1));
+        let region = Rect {
+            x,
+            y,
+            width: start.0.max(end.0) - x + 1,
+            height: start.1.max(end.1) - y + 1,
+        };
+        crosshair_data_update.borrow_mut().redline = Some(Redline::new(region));
+        drawing_area_update.queue_draw();
+    });
+
+    let drawing_area_end = drawing_area.clone();
+    drag_gesture.connect_drag_end(move |_, _, _| {
+        if !selecting.replace(false) {
+            return;
+        }
+
+        // A click leaves a region a pixel or two wide, which clears the redline
+        let mut data = crosshair_data.borrow_mut();
+        match data.redline.take() {
+            Some(mut redline) if redline.region().width > 2 && redline.region().height > 2 => {
+                redline.find(
+                    data.measured_image(&capture.borrow()),
+                    data.magnitude_threshold,
+                );
+                data.redline = Some(redline);
+            }
+            _ => {}
+        }
+        drawing_area_end.queue_draw();
+    });
+
+    drawing_area.add_controller(drag_gesture);
+}
+
+/// Sets up the entry the text of labels is typed in
+/// Labels left without text are removed, so cancelling a new label drops it
+fn create_text_editor(
+    drawing_area: &DrawingArea,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+) -> Rc<TextEditor> {
+    let drawing_area = drawing_area.clone();
+    TextEditor::new(move |index, text| {
+        let mut data = crosshair_data.borrow_mut();
+        if let Some(text) = text
+            && let Some(Annotation::Text { text: current, .. }) = data.annotations.get_mut(index)
+        {
+            *current = text;
+        }
+        if data
+            .annotations
+            .get(index)
+            .is_some_and(Annotation::is_empty)
+        {
+            data.annotations.remove(index);
+        }
+        drawing_area.queue_draw();
+    })
+}
+
+/// Whether a press drags the reference, with Alt held while the reference is shown
+fn drags_reference(data: &CrosshairData, state: ModifierType) -> bool {
+    state.contains(ModifierType::ALT_MASK) && data.compare.as_ref().is_some_and(Compare::is_shown)
+}
+
+/// Converts a point of the drawing area to the pixel of the screenshot under it
+/// Points outside the screenshot are clamped to its bounds
+fn image_coordinates(
+    x: f64,
+    y: f64,
+    scale_and_offset: (f64, f64, f64),
+    img: &image::RgbImage,
+) -> (u32, u32) {
+    let (img_width, img_height) = img.dimensions();
+    let (image_x, image_y) = image_position(x, y, scale_and_offset);
+    let image_x = (image_x.max(0.0) as u32).min(img_width - 1);
+    let image_y = (image_y.max(0.0) as u32).min(img_height - 1);
+    (image_x, image_y)
+}
+
+/// Converts a point of the drawing area to image coordinates, without clamping
+fn image_position(x: f64, y: f64, (scale, offset_x, offset_y): (f64, f64, f64)) -> (f64, f64) {
+    ((x - offset_x) / scale, (y - offset_y) / scale)
+}
+
+/// Fills in the scale factor and physical size of the monitor once the window is shown
+/// These are needed to convert pixels to dp, mm and the other units
+fn setup_unit_context(window: &ApplicationWindow, crosshair_data: Rc<RefCell<CrosshairData>>) {
+    window.connect_map(move |window| {
+        let Some(surface) = window.surface() else {
+            return;
+        };
+        let Some(monitor) = WidgetExt::display(window).monitor_at_surface(&surface) else {
+            return;
+        };
+
+        // The fractional scale, the integer scale factor rounds 1.25 and 1.5 up to 2
+        let scale_factor = monitor.scale();
+        let geometry = monitor.geometry();
+        let (width_mm, height_mm) = (monitor.width_mm(), monitor.height_mm());
+
+        let mut data = crosshair_data.borrow_mut();
+        let context = &mut data.units.context;
+        context.scale_factor = scale_factor;
+        // Monitors that don't know their size report 0mm
+        context.pixels_per_mm = (width_mm > 0 && height_mm > 0).then(|| {
+            (
+                f64::from(geometry.width()) * scale_factor / f64::from(width_mm),
+                f64::from(geometry.height()) * scale_factor / f64::from(height_mm),
+            )
+        });
+
+        window.queue_draw();
+    });
 }
 
 /// Sets up cleanup function to run when window closes
-fn setup_cleanup(window: &ApplicationWindow, screenshot_path: PathBuf) {
+/// Also saves the last used tool and threshold, and the guides of the captured app,
+/// unless the configuration failed to load
+fn setup_cleanup(
+    window: &ApplicationWindow,
+    screenshot_path: Option<PathBuf>,
+    crosshair_data: Rc<RefCell<CrosshairData>>,
+    tools: Rc<RefCell<ToolRegistry>>,
+    save_session: bool,
+) {
     // Set up cleanup when window is closed by connecting to the 'close-request' signal
     window.connect_close_request(move |_window| {
+        if save_session {
+            let tool = tools.borrow().active().id();
+            let data = crosshair_data.borrow();
+            if let Err(err) = config::save_session(tool, data.magnitude_threshold) {
+                eprintln!("Error saving configuration: {err}");
+            }
+            if let Some(app) = &data.app
+                && let Err(err) = config::save_guides(app, &data.guides)
+            {
+                eprintln!("Error saving guides: {err}");
+            }
+        }
+
         // Clean up the temporary screenshot file
-        if let Err(err) = screenshot::cleanup_screenshot(&screenshot_path) {
+        if let Some(path) = &screenshot_path
+            && let Err(err) = screenshot::cleanup_screenshot(path)
+        {
             eprintln!("Error cleaning up screenshot: {:?}", err);
         }
         gtk4::glib::Propagation::Proceed